@@ -0,0 +1,90 @@
+//! Compares echo round trips with message loops on the shared runtime
+//! against loops pinned to dedicated workers.
+//!
+//! Run with `cargo bench --bench affinity`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::runtime::Runtime;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use wynd::affinity::ConnectionAffinity;
+use wynd::wynd::{SpawnedServer, Standalone, Wynd};
+
+/// Connected clients per run.
+const CLIENTS: usize = 16;
+/// Messages each client sends per iteration.
+const MESSAGES: usize = 32;
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+fn echo_server(runtime: &Runtime, affinity: ConnectionAffinity) -> SpawnedServer {
+    let mut wynd: Wynd<Standalone> = Wynd::new();
+    wynd.set_connection_affinity(affinity)
+        .expect("worker threads should start");
+    wynd.on_connection(|conn| async move {
+        conn.on_text(|msg, handle| async move {
+            let _ = handle.send_text(msg.data).await;
+        });
+    });
+    wynd.spawn_on(0, runtime.handle())
+        .expect("server should start")
+}
+
+async fn connect_clients(server: &SpawnedServer) -> Vec<Client> {
+    let url = format!("ws://{}/", server.local_addr());
+    let mut clients = Vec::with_capacity(CLIENTS);
+    for _ in 0..CLIENTS {
+        let (client, _) = connect_async(&url).await.expect("client should connect");
+        clients.push(client);
+    }
+    clients
+}
+
+/// Sends `MESSAGES` messages from every client and waits for every echo.
+async fn round_trips(clients: &mut [Client]) {
+    let tasks = clients.iter_mut().map(|client| async move {
+        for i in 0..MESSAGES {
+            client.send(Message::text(i.to_string())).await.unwrap();
+            client.next().await.unwrap().unwrap();
+        }
+    });
+    futures::future::join_all(tasks).await;
+}
+
+fn affinity(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+
+    let mut group = c.benchmark_group("echo_round_trips");
+    for (name, strategy) in [
+        ("shared", ConnectionAffinity::Shared),
+        ("hash_by_id", ConnectionAffinity::HashById { workers }),
+    ] {
+        let server = echo_server(&runtime, strategy);
+        let clients = runtime.block_on(connect_clients(&server));
+        let clients = tokio::sync::Mutex::new(clients);
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.to_async(&runtime)
+                .iter(|| async { round_trips(&mut clients.lock().await).await });
+        });
+
+        runtime.block_on(async {
+            for mut client in clients.into_inner() {
+                let _ = client.close(None).await;
+                // Wait for the server's close reply so the connection is gone
+                // before shutting down.
+                while let Some(Ok(_)) = client.next().await {}
+            }
+            server.shutdown().await;
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, affinity);
+criterion_main!(benches);
@@ -0,0 +1,83 @@
+//! Connection affinity.
+//!
+//! By default every connection's message loop runs on the shared tokio
+//! runtime, where work stealing may move it between worker threads. With
+//! [`ConnectionAffinity::HashById`] the server instead starts a fixed set of
+//! single-threaded workers and runs each connection's message loop on the
+//! worker picked by its id, so its reads, handlers and the replies they send
+//! stay on one thread. Set it with
+//! [`Wynd::set_connection_affinity`](crate::wynd::Wynd::set_connection_affinity).
+
+use std::thread;
+
+use tokio::{
+    runtime::{Builder, Handle},
+    sync::oneshot,
+};
+
+/// Strategy for choosing where a connection's message loop runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionAffinity {
+    /// Run message loops on the shared runtime, letting tokio balance them
+    /// across its workers.
+    #[default]
+    Shared,
+    /// Run each message loop on one of `workers` dedicated single-threaded
+    /// workers, chosen by hashing the connection id.
+    ///
+    /// A connection always lands on the same worker, and connections are
+    /// spread evenly when ids are sequential. `workers` is at least 1.
+    HashById {
+        /// The number of dedicated workers.
+        workers: usize,
+    },
+}
+
+/// A fixed set of single-threaded runtimes, each on its own OS thread.
+///
+/// The workers stop when the pool is dropped, cancelling any message loops
+/// still running on them.
+#[derive(Debug)]
+pub(crate) struct WorkerPool {
+    workers: Vec<Handle>,
+    /// Dropping these tells each worker thread to shut its runtime down.
+    _stop: Vec<oneshot::Sender<()>>,
+}
+
+impl WorkerPool {
+    /// Starts `workers` worker threads, or at least one.
+    pub(crate) fn new(workers: usize) -> std::io::Result<Self> {
+        let count = workers.max(1);
+        let mut handles = Vec::with_capacity(count);
+        let mut stop = Vec::with_capacity(count);
+
+        for index in 0..count {
+            let runtime = Builder::new_current_thread().enable_all().build()?;
+            let (stop_tx, stop_rx) = oneshot::channel::<()>();
+            handles.push(runtime.handle().clone());
+            stop.push(stop_tx);
+            thread::Builder::new()
+                .name(format!("wynd-worker-{}", index))
+                .spawn(move || {
+                    // Resolves once the sender is dropped along with the pool.
+                    let _ = runtime.block_on(stop_rx);
+                })?;
+        }
+
+        Ok(Self {
+            workers: handles,
+            _stop: stop,
+        })
+    }
+
+    /// Returns the worker that runs connection `id`.
+    pub(crate) fn worker_for(&self, id: u64) -> Handle {
+        let index = (id % self.workers.len() as u64) as usize;
+        self.workers[index].clone()
+    }
+
+    /// Returns the number of workers.
+    pub(crate) fn len(&self) -> usize {
+        self.workers.len()
+    }
+}
@@ -50,7 +50,18 @@
 //! }
 //! ```
 
-use std::{collections::HashMap, fmt::Debug, future::Future, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_tungstenite::{
@@ -60,9 +71,12 @@ use tokio_tungstenite::{
 use tungstenite::{error::UrlError, protocol::frame::coding::CloseCode, Error};
 
 use crate::{
-    handle::ConnectionHandle,
+    handle::{ConnectionHandle, OutboundTransform},
     room::RoomEvents,
-    types::{BinaryMessageEvent, CloseEvent, TextMessageEvent},
+    types::{
+        BinaryMessageEvent, CloseEvent, ErrorEvent, HandlerError, IncomingMessage, MessageKinds,
+        PongEvent, TextMessageEvent,
+    },
     wynd::BoxFuture,
     ClientRegistry,
 };
@@ -100,6 +114,51 @@ type BinaryMessageHandler<T> = Arc<
     >,
 >;
 
+/// Type alias for unified message handler functions.
+///
+/// They receive an `IncomingMessage` of either kind and a `ConnectionHandle`.
+type IncomingMessageFn<T> =
+    Box<dyn Fn(IncomingMessage, Arc<ConnectionHandle<T>>) -> BoxFuture<()> + Send + Sync>;
+
+/// Unified handler for text and binary messages.
+///
+/// Runs for data messages without a kind-specific handler, or for every data
+/// message once `preferred` is set.
+struct MessageHandler<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    handler: tokio::sync::Mutex<Option<IncomingMessageFn<T>>>,
+    preferred: AtomicBool,
+}
+
+impl<T> MessageHandler<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    /// Returns `true` if the unified handler replaces kind-specific handlers.
+    fn is_preferred(&self) -> bool {
+        self.preferred.load(Ordering::Relaxed)
+    }
+
+    /// Returns `specific`, or the unified handler's future for `message` if
+    /// there is no specific handler to run.
+    async fn or_unified(
+        &self,
+        specific: Option<BoxFuture<()>>,
+        message: impl FnOnce() -> IncomingMessage,
+        handle: &Arc<ConnectionHandle<T>>,
+    ) -> Option<BoxFuture<()>> {
+        if specific.is_some() {
+            return specific;
+        }
+        let handler = self.handler.lock().await;
+        (*handler)
+            .as_ref()
+            .map(|h| h(message(), Arc::clone(handle)))
+    }
+}
+
 /// Type alias for connection open handlers.
 ///
 /// Handlers for connection open events receive a `ConnectionHandle`
@@ -110,6 +169,43 @@ type OpenHandler<T> = Arc<
     >,
 >;
 
+/// Type alias for ping handlers.
+///
+/// Handlers for pings receive the ping payload and a `ConnectionHandle`.
+type PingHandler<T> = Arc<
+    tokio::sync::Mutex<
+        Option<Box<dyn Fn(Vec<u8>, Arc<ConnectionHandle<T>>) -> BoxFuture<()> + Send + Sync>>,
+    >,
+>;
+
+/// Type alias for pong handlers.
+///
+/// Handlers for pongs receive a `PongEvent` and a `ConnectionHandle`.
+type PongHandler<T> = Arc<
+    tokio::sync::Mutex<
+        Option<Box<dyn Fn(PongEvent, Arc<ConnectionHandle<T>>) -> BoxFuture<()> + Send + Sync>>,
+    >,
+>;
+
+/// Type alias for connection error handlers.
+///
+/// Handlers for connection errors receive an `ErrorEvent` and a `ConnectionHandle`.
+type ErrorHandler<T> = Arc<
+    tokio::sync::Mutex<
+        Option<Box<dyn Fn(ErrorEvent, Arc<ConnectionHandle<T>>) -> BoxFuture<()> + Send + Sync>>,
+    >,
+>;
+
+/// Keepalive settings for a connection.
+///
+/// The server pings the client every `interval` and closes the connection if
+/// no pong arrives within `timeout` of a ping.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Keepalive {
+    pub(crate) interval: Duration,
+    pub(crate) timeout: Duration,
+}
+
 /// Represents a WebSocket connection with event handlers.
 ///
 /// `Connection` is the main type for managing individual WebSocket connections.
@@ -120,8 +216,10 @@ type OpenHandler<T> = Arc<
 ///
 /// 1. **Connection Established**: A new `Connection` is created when a client connects
 /// 2. **Open Event**: The `on_open` handler is called when the WebSocket handshake completes
-/// 3. **Message Events**: `on_text` and `on_binary` handlers are called for incoming messages
-/// 4. **Close Event**: The `on_close` handler is called when the connection is closed
+/// 3. **Ready Event**: The `on_ready` handler is called once all handlers are registered,
+///    right before the first inbound message is read
+/// 4. **Message Events**: `on_text` and `on_binary` handlers are called for incoming messages
+/// 5. **Close Event**: The `on_close` handler is called when the connection is closed
 ///
 /// ## Example
 ///
@@ -182,21 +280,80 @@ where
     /// This can be used for logging and access control.
     addr: SocketAddr,
 
+    /// Query parameters from the handshake request URI.
+    query: HashMap<String, String>,
+
+    /// Headers of the handshake request, keyed by lowercase name.
+    pub(crate) headers: Arc<HashMap<String, String>>,
+
+    /// Names of the extensions accepted during the handshake.
+    negotiated_extensions: Vec<String>,
+
+    /// `Sec-WebSocket-Version` sent by the client during the handshake.
+    ws_version: u8,
+
     /// Handler for connection open events.
     open_handler: OpenHandler<T>,
 
+    /// Handler fired once handler registration is complete, before the
+    /// message loop starts.
+    ready_handler: OpenHandler<T>,
+
     /// Handler for text message events.
     text_message_handler: TextMessageHandler<T>,
 
     /// Handler for binary message events.
     binary_message_handler: BinaryMessageHandler<T>,
 
+    /// Handler for both text and binary message events.
+    message_handler: Arc<MessageHandler<T>>,
+
+    /// Handler for pings sent by the client.
+    ping_handler: PingHandler<T>,
+
+    /// Handler for pongs sent by the client.
+    pong_handler: PongHandler<T>,
+
+    /// Handler for errors reading from the connection.
+    error_handler: ErrorHandler<T>,
+
     /// Handler for connection close events.
     close_handler: CloseHandler,
 
+    /// Server bookkeeping run after `close_handler`, so registering an
+    /// application close handler never replaces it.
+    cleanup_handler: CloseHandler,
+
     /// State of the current connection.
     pub(crate) state: Arc<tokio::sync::Mutex<ConnState>>,
 
+    /// Whether `on_open` starts the message loop right away.
+    ///
+    /// The server clears this so that the loop only starts once the
+    /// connection handler has returned and every handler is registered.
+    auto_start: bool,
+
+    /// Whether the message loop has been started.
+    started: AtomicBool,
+
+    /// Keepalive pings sent by the server, if enabled.
+    keepalive: Option<Keepalive>,
+
+    /// Worker runtime the message loop is pinned to, if any.
+    worker: Option<tokio::runtime::Handle>,
+
+    /// Transform applied to outbound data frames, shared with the handle.
+    pub(crate) outbound_transform: OutboundTransform,
+
+    /// Bits of the [`MessageKinds`] this connection accepts, shared with the handle.
+    pub(crate) accepted_kinds: Arc<AtomicU8>,
+
+    /// Whether inbound data messages are discarded, shared with the handle.
+    pub(crate) inbound_muted: Arc<AtomicBool>,
+
+    /// Whether errors from fallible handlers are sent back to the client.
+    auto_error_reply: bool,
+
     clients: ClientRegistry<T>,
 
     /// The connection handle created during connection setup.
@@ -276,10 +433,31 @@ where
             reader: Arc::new(tokio::sync::Mutex::new(reader)),
             writer: Arc::new(tokio::sync::Mutex::new(writer)),
             addr,
+            query: HashMap::new(),
+            headers: Arc::new(HashMap::new()),
+            negotiated_extensions: Vec::new(),
+            ws_version: 13,
             open_handler: Arc::new(tokio::sync::Mutex::new(None)),
+            ready_handler: Arc::new(tokio::sync::Mutex::new(None)),
             text_message_handler: Arc::new(tokio::sync::Mutex::new(None)),
             binary_message_handler: Arc::new(tokio::sync::Mutex::new(None)),
+            message_handler: Arc::new(MessageHandler {
+                handler: tokio::sync::Mutex::new(None),
+                preferred: AtomicBool::new(false),
+            }),
+            ping_handler: Arc::new(tokio::sync::Mutex::new(None)),
+            pong_handler: Arc::new(tokio::sync::Mutex::new(None)),
+            error_handler: Arc::new(tokio::sync::Mutex::new(None)),
             close_handler: Arc::new(tokio::sync::Mutex::new(None)),
+            cleanup_handler: Arc::new(tokio::sync::Mutex::new(None)),
+            auto_start: true,
+            started: AtomicBool::new(false),
+            keepalive: None,
+            worker: None,
+            outbound_transform: OutboundTransform::default(),
+            accepted_kinds: Arc::new(AtomicU8::new(MessageKinds::ALL.bits())),
+            inbound_muted: Arc::new(AtomicBool::new(false)),
+            auto_error_reply: false,
             clients: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             handle: Arc::new(tokio::sync::Mutex::new(None)),
         }
@@ -293,6 +471,50 @@ where
         self.clients = clients;
     }
 
+    /// Sets the query parameters from the raw query string of the handshake request.
+    pub(crate) fn set_query(&mut self, raw_query: &str) {
+        self.query = parse_query(raw_query);
+    }
+
+    /// Sets the headers of the handshake request.
+    pub(crate) fn set_headers(&mut self, headers: HashMap<String, String>) {
+        self.headers = Arc::new(headers);
+    }
+
+    /// Records the extensions accepted during the handshake.
+    pub(crate) fn set_negotiated_extensions(&mut self, extensions: Vec<String>) {
+        self.negotiated_extensions = extensions;
+    }
+
+    /// Records the WebSocket protocol version the client asked for.
+    pub(crate) fn set_ws_version(&mut self, version: u8) {
+        self.ws_version = version;
+    }
+
+    /// Pins this connection's message loop to a worker runtime.
+    pub(crate) fn set_worker(&mut self, worker: tokio::runtime::Handle) {
+        self.worker = Some(worker);
+    }
+
+    /// Enables keepalive pings for this connection.
+    pub(crate) fn set_keepalive(&mut self, keepalive: Keepalive) {
+        self.keepalive = Some(keepalive);
+    }
+
+    /// Sends errors from fallible handlers back to the client as JSON.
+    #[cfg(feature = "json")]
+    pub(crate) fn set_auto_error_reply(&mut self, enabled: bool) {
+        self.auto_error_reply = enabled;
+    }
+
+    /// Defer the start of the message loop until [`Connection::start`] is called.
+    ///
+    /// Used by the server so that no inbound message is processed before the
+    /// connection handler has finished registering handlers.
+    pub(crate) fn defer_start(&mut self) {
+        self.auto_start = false;
+    }
+
     /// Set the connection handle for this connection.
     ///
     /// This method is called by the server to set the handle that was created
@@ -339,7 +561,9 @@ where
     ///
     /// ## Returns
     ///
-    /// Returns the `SocketAddr` of the remote client.
+    /// Returns the `SocketAddr` of the remote client. Connections upgraded
+    /// through ripress report the server's address, as the peer address is
+    /// not passed along with the upgrade request.
     ///
     /// ## Example
     ///
@@ -361,6 +585,263 @@ where
         self.addr
     }
 
+    /// Returns the query parameters sent with the handshake request.
+    ///
+    /// Keys and values are percent-decoded. When a key appears more than once,
+    /// the first value is kept.
+    ///
+    /// ## Returns
+    ///
+    /// Returns a map of query parameter names to values.
+    pub fn query_params(&self) -> &HashMap<String, String> {
+        &self.query
+    }
+
+    /// Returns the headers sent with the handshake request.
+    ///
+    /// Names are lowercase. A header sent more than once has its values
+    /// joined with `", "`. Behind a reverse proxy, this is where headers such
+    /// as `X-Forwarded-For` carry the client's original address.
+    ///
+    /// ## Returns
+    ///
+    /// Returns a map of header names to values.
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    /// Returns one header of the handshake request.
+    ///
+    /// ## Parameters
+    ///
+    /// - `name`: The header name, in any case
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     let client = conn.header("X-Forwarded-For").unwrap_or("direct");
+    ///     println!("Connection from {} via {}", client, conn.addr());
+    /// });
+    /// ```
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Reads a query parameter from the handshake request as a typed value.
+    ///
+    /// This lets apps read options passed at connect time, e.g.
+    /// `ws://host/chat?limit=50`, without parsing them by hand.
+    ///
+    /// ## Parameters
+    ///
+    /// - `key`: The query parameter name
+    ///
+    /// ## Returns
+    ///
+    /// Returns `None` if the parameter is missing or cannot be parsed as `V`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         let limit = conn.query_param::<u32>("limit").unwrap_or(20);
+    ///         let room = conn.query_param::<String>("room");
+    ///         println!("limit={} room={:?}", limit, room);
+    ///     });
+    /// }
+    /// ```
+    pub fn query_param<V>(&self, key: &str) -> Option<V>
+    where
+        V: FromStr,
+    {
+        self.query.get(key).and_then(|value| value.parse().ok())
+    }
+
+    /// Returns whether permessage-deflate compression was negotiated with this client.
+    ///
+    /// The flag reflects the handshake response: it is `true` only if the
+    /// server accepted the `permessage-deflate` extension offered by the
+    /// client. Clients that do not offer the extension always report `false`.
+    /// Useful for logging compression adoption or choosing payload sizes.
+    ///
+    /// The WebSocket implementation Wynd builds on (tungstenite 0.28) cannot
+    /// decode compressed frames, so the server currently declines every
+    /// `permessage-deflate` offer and clients fall back to uncompressed
+    /// frames.
+    pub fn compression_enabled(&self) -> bool {
+        self.negotiated_extensions
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case("permessage-deflate"))
+    }
+
+    /// Returns the names of the extensions agreed during the handshake.
+    ///
+    /// Lists each extension the server accepted in its
+    /// `Sec-WebSocket-Extensions` response header, such as
+    /// `permessage-deflate`, without its parameters. Extensions the client
+    /// offered but the server declined are not included, so comparing this
+    /// list with what clients offer shows why a feature is not in use.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     println!("extensions: {:?}", conn.negotiated_extensions());
+    /// });
+    /// ```
+    pub fn negotiated_extensions(&self) -> Vec<String> {
+        self.negotiated_extensions.clone()
+    }
+
+    /// Returns the `Sec-WebSocket-Version` the client sent during the handshake.
+    ///
+    /// RFC 6455 clients send `13`, and the standalone server refuses any other
+    /// version before the connection is created. Useful for logging clients;
+    /// see [`Wynd::set_required_ws_version`](crate::wynd::Wynd::set_required_ws_version)
+    /// to pin the accepted version.
+    pub fn ws_version(&self) -> u8 {
+        self.ws_version
+    }
+
+    /// Sets a transform applied to every outbound text and binary frame of
+    /// this connection.
+    ///
+    /// The transform runs on direct sends as well as broadcast, room and group
+    /// messages delivered to this connection, so it can add a per-client
+    /// encryption layer or encoding. Control frames (ping, pong, close) are
+    /// sent unchanged. Setting a new transform replaces the previous one.
+    ///
+    /// ## Parameters
+    ///
+    /// - `transform`: Function mapping each outbound message to the one sent on the wire
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    /// use wynd::Message;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         if conn.query_param::<String>("encoding").as_deref() == Some("upper") {
+    ///             conn.set_outbound_transform(|message| match message {
+    ///                 Message::Text(text) => Message::text(text.to_uppercase()),
+    ///                 other => other,
+    ///             });
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn set_outbound_transform<F>(&self, transform: F)
+    where
+        F: Fn(Message) -> Message + Send + Sync + 'static,
+    {
+        self.outbound_transform.set(Arc::new(transform));
+    }
+
+    /// Restricts the data message kinds this connection accepts.
+    ///
+    /// A client that sends a message of any other kind is disconnected with
+    /// close code `1003` ("unsupported data") instead of having the message
+    /// silently ignored. Control frames are always accepted. By default all
+    /// kinds are accepted.
+    ///
+    /// ## Parameters
+    ///
+    /// - `kinds`: The message kinds to accept
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::types::MessageKinds;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     // This protocol is text-only.
+    ///     conn.accept_only(MessageKinds::TEXT);
+    /// });
+    /// ```
+    pub fn accept_only(&self, kinds: MessageKinds) {
+        self.accepted_kinds.store(kinds.bits(), Ordering::Relaxed);
+    }
+
+    /// Stops handing inbound text and binary messages to handlers.
+    ///
+    /// Suits protocol phases where the server only pushes data after setup.
+    /// The socket stays open and keeps being read, and muted messages are
+    /// discarded as they arrive: `on_text` and `on_binary` do not run for
+    /// them, and they are not delivered later by
+    /// [`unmute_inbound`](Self::unmute_inbound). This differs from
+    /// [`ConnectionHandle::pause_reads`], which stops reading so messages
+    /// wait in the socket. Sends through the connection's handle work as
+    /// usual. Control frames are still handled, so pings are answered,
+    /// keepalive keeps working and a close from the client ends the
+    /// connection. Discarded messages still count towards the total bytes
+    /// limit and must be of an accepted kind (see
+    /// [`accept_only`](Self::accept_only)).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     let push_only = Arc::clone(&conn);
+    ///     conn.on_text(move |msg, handle| {
+    ///         let push_only = Arc::clone(&push_only);
+    ///         async move {
+    ///             if msg.data == "subscribe" {
+    ///                 // Setup is done: from now on the server only pushes.
+    ///                 push_only.mute_inbound();
+    ///                 let _ = handle.send_text("subscribed").await;
+    ///             }
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn mute_inbound(&self) {
+        self.inbound_muted.store(true, Ordering::Relaxed);
+    }
+
+    /// Resumes handing inbound messages to handlers after
+    /// [`mute_inbound`](Self::mute_inbound).
+    ///
+    /// Messages that arrived while muted are gone; only messages received
+    /// from now on reach the handlers.
+    pub fn unmute_inbound(&self) {
+        self.inbound_muted.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns `true` while inbound messages are muted.
+    pub fn inbound_muted(&self) -> bool {
+        self.inbound_muted.load(Ordering::Relaxed)
+    }
+
     /// Returns the current state of the WebSocket connection.
     ///
     /// This method asynchronously acquires a lock on the internal state
@@ -389,6 +870,91 @@ where
         s.clone()
     }
 
+    /// Sends a text message to the client without waiting for a handler.
+    ///
+    /// Delegates to the connection's [`ConnectionHandle`], so it can be used
+    /// directly inside `on_connection`.
+    ///
+    /// ## Returns
+    ///
+    /// Returns an error of kind `NotConnected` if the server has not attached
+    /// a handle to this connection yet, or if the send fails.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     let _ = conn.send_text("Welcome!").await;
+    /// });
+    /// ```
+    pub async fn send_text<S>(&self, text: S) -> Result<(), Box<dyn std::error::Error>>
+    where
+        S: Into<String>,
+    {
+        Ok(self.attached_handle().await?.send_text(text).await?)
+    }
+
+    /// Sends binary data to the client without waiting for a handler.
+    ///
+    /// The binary counterpart of [`Connection::send_text`].
+    pub async fn send_binary(&self, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(self.attached_handle().await?.send_binary(data).await?)
+    }
+
+    /// Closes the connection with a close code and reason.
+    ///
+    /// Delegates to [`ConnectionHandle::close_with`], so an `on_connection`
+    /// handler can turn a client away before it is opened. Connection
+    /// handlers registered after the one that closes are skipped.
+    ///
+    /// ## Returns
+    ///
+    /// Returns an error of kind `NotConnected` if the server has not attached
+    /// a handle to this connection yet, or the error from
+    /// [`ConnectionHandle::close_with`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     if conn.header("authorization").is_none() {
+    ///         let _ = conn.close_with(1008, "unauthorized").await;
+    ///     }
+    /// });
+    /// ```
+    pub async fn close_with<S>(
+        &self,
+        code: u16,
+        reason: S,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        S: Into<String>,
+    {
+        Ok(self
+            .attached_handle()
+            .await?
+            .close_with(code, reason)
+            .await?)
+    }
+
+    /// Returns the handle attached by the server, or a `NotConnected` error.
+    async fn attached_handle(&self) -> Result<Arc<ConnectionHandle<T>>, std::io::Error> {
+        self.handle.lock().await.clone().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "connection handle is not set yet",
+            )
+        })
+    }
+
     /// Registers a handler for connection open events.
     ///
     /// This method sets up a handler that will be called when the
@@ -431,71 +997,152 @@ where
         F: Fn(Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
-        let mut open_handler = self.open_handler.lock().await;
-        *open_handler = Some(Box::new(move |handle| Box::pin(handler(handle))));
+        {
+            let mut open_handler = self.open_handler.lock().await;
+            *open_handler = Some(Box::new(move |handle| Box::pin(handler(handle))));
+        }
+
+        self.mark_open().await;
+
+        if self.auto_start {
+            self.start().await;
+        }
+    }
+
+    /// Registers a handler that fires once the connection is ready.
+    ///
+    /// When the connection is managed by the server, the ready handler runs
+    /// exactly once, after the `on_connection` handler has returned and all
+    /// handlers are registered, and before the first inbound message is
+    /// processed. Messages sent by a client right after connecting are
+    /// therefore never dropped.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes a `ConnectionHandle` and returns a future
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_text(|msg, handle| async move {
+    ///             let _ = handle.send_text(&format!("Echo: {}", msg.data)).await;
+    ///         });
+    ///
+    ///         conn.on_ready(|handle| async move {
+    ///             let _ = handle.send_text("Ready").await;
+    ///         })
+    ///         .await;
+    ///     });
+    /// }
+    /// ```
+    pub async fn on_ready<F, Fut>(&self, handler: F)
+    where
+        F: Fn(Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut ready_handler = self.ready_handler.lock().await;
+        *ready_handler = Some(Box::new(move |handle| Box::pin(handler(handle))));
+    }
+
+    /// Marks the connection as open if it is still connecting.
+    ///
+    /// The handshake is complete by the time handlers are registered, so the
+    /// connection is open before the open handler runs or any message is read.
+    async fn mark_open(&self) {
+        let mut s = self.state.lock().await;
+        if *s == ConnState::CONNECTING {
+            *s = ConnState::OPEN;
+        }
+    }
+
+    /// Starts processing the connection.
+    ///
+    /// Runs the open handler, then the ready handler, then the message loop
+    /// in a background task. Only the first call has an effect, so the loop
+    /// never runs twice for the same connection.
+    pub(crate) async fn start(&self) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        self.mark_open().await;
 
         let handle = {
             if let Some(h) = self.handle.lock().await.clone() {
                 h
             } else {
                 let (tx, _rx) = tokio::sync::mpsc::channel::<RoomEvents<T>>(1);
-                let (response_tx, response_rx) = tokio::sync::mpsc::channel::<Vec<&'static str>>(1);
-                Arc::new(crate::handle::ConnectionHandle {
-                    id: self.id,
-                    writer: Arc::clone(&self.writer),
-                    addr: self.addr,
-                    broadcast: crate::handle::Broadcaster {
-                        clients: Arc::clone(&self.clients),
-                        current_client_id: self.id,
-                    },
-                    state: Arc::clone(&self.state),
-                    room_sender: Arc::new(tx),
-                    response_sender: Arc::new(response_tx),
-                    response_receiver: Arc::new(tokio::sync::Mutex::new(response_rx)),
-                })
+                let mut handle = ConnectionHandle::new(
+                    self.id,
+                    Arc::clone(&self.writer),
+                    self.addr,
+                    crate::handle::Broadcaster::new(Arc::clone(&self.clients), self.id),
+                    Arc::clone(&self.state),
+                    Arc::new(tx),
+                );
+                handle.outbound_transform = self.outbound_transform.clone();
+                handle.accepted_kinds = Arc::clone(&self.accepted_kinds);
+                handle.inbound_muted = Arc::clone(&self.inbound_muted);
+                handle.headers = Arc::clone(&self.headers);
+                Arc::new(handle)
             }
         };
 
         let open_handler_clone = Arc::clone(&self.open_handler);
+        let ready_handler_clone = Arc::clone(&self.ready_handler);
         let text_message_handler_clone = Arc::clone(&self.text_message_handler);
         let binary_message_handler_clone = Arc::clone(&self.binary_message_handler);
+        let message_handler_clone = Arc::clone(&self.message_handler);
+        let ping_handler_clone = Arc::clone(&self.ping_handler);
+        let pong_handler_clone = Arc::clone(&self.pong_handler);
+        let error_handler_clone = Arc::clone(&self.error_handler);
         let close_handler_clone = Arc::clone(&self.close_handler);
-        let handle_clone = Arc::clone(&handle);
+        let cleanup_handler_clone = Arc::clone(&self.cleanup_handler);
         let reader_clone = Arc::clone(&self.reader);
-        let state_clone = Arc::clone(&self.state);
-
-        tokio::spawn(async move {
-            {
-                {
-                    let mut s = state_clone.lock().await;
-                    *s = ConnState::OPEN;
-                }
+        let keepalive = self.keepalive;
+        let task = Arc::clone(&handle.task);
 
-                {
-                    let handler_fut = {
-                        let open_handler = open_handler_clone.lock().await;
-                        if let Some(ref handler) = *open_handler {
-                            Some(handler(Arc::clone(&handle_clone)))
-                        } else {
-                            None
-                        }
-                    };
-                    if let Some(fut) = handler_fut {
-                        fut.await;
-                    }
+        let message_loop = async move {
+            handle.record_setup_time();
+            for lifecycle_handler in [open_handler_clone, ready_handler_clone] {
+                let handler_fut = {
+                    let lifecycle_handler = lifecycle_handler.lock().await;
+                    (*lifecycle_handler)
+                        .as_ref()
+                        .map(|handler| handler(Arc::clone(&handle)))
+                };
+                if let Some(fut) = handler_fut {
+                    fut.await;
                 }
             }
 
             Self::message_loop(
-                handle_clone,
+                handle,
                 text_message_handler_clone,
                 binary_message_handler_clone,
+                message_handler_clone,
+                ping_handler_clone,
+                pong_handler_clone,
+                error_handler_clone,
                 close_handler_clone,
+                cleanup_handler_clone,
                 reader_clone,
-                state_clone,
+                keepalive,
             )
             .await;
-        });
+        };
+        let message_loop = match self.worker {
+            Some(ref worker) => worker.spawn(message_loop),
+            None => tokio::spawn(message_loop),
+        };
+        let _ = task.set(message_loop.abort_handle());
     }
 
     /// Registers a handler for binary message events.
@@ -542,11 +1189,90 @@ where
         F: Fn(BinaryMessageEvent, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
-        let binary_message_handler = Arc::clone(&self.binary_message_handler);
-        tokio::spawn(async move {
-            let mut lock = binary_message_handler.lock().await;
-            *lock = Some(Box::new(move |msg, handle| Box::pin(handler(msg, handle))));
-        });
+        let boxed: Box<_> =
+            Box::new(move |msg, handle| -> BoxFuture<()> { Box::pin(handler(msg, handle)) });
+        // Register synchronously when possible so the handler is in place
+        // before the message loop starts.
+        if let Ok(mut lock) = self.binary_message_handler.try_lock() {
+            *lock = Some(boxed);
+        } else {
+            let binary_message_handler = Arc::clone(&self.binary_message_handler);
+            tokio::spawn(async move {
+                let mut lock = binary_message_handler.lock().await;
+                *lock = Some(boxed);
+            });
+        }
+    }
+
+    /// Registers one handler for both text and binary messages.
+    ///
+    /// The handler receives an [`IncomingMessage`] of either kind, which
+    /// suits protocols that treat every frame the same way.
+    ///
+    /// ## Precedence
+    ///
+    /// At most one handler runs per message, never both. By default a
+    /// handler registered with [`on_text`](Self::on_text) (or
+    /// `on_text_try`) or [`on_binary`](Self::on_binary) wins for its kind,
+    /// and `on_message` handles only the kinds without one, so it can act as
+    /// a fallback. After [`prefer_on_message(true)`](Self::prefer_on_message)
+    /// `on_message` handles every data message and the kind-specific handlers
+    /// are skipped.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes an `IncomingMessage` and `ConnectionHandle`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::types::IncomingMessage;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_message(|msg, handle| async move {
+    ///         // Echo every frame back as binary.
+    ///         let _ = handle.send_binary(msg.as_bytes().to_vec()).await;
+    ///     });
+    /// });
+    /// ```
+    pub fn on_message<F, Fut>(&self, handler: F)
+    where
+        F: Fn(IncomingMessage, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let boxed: Box<_> =
+            Box::new(move |msg, handle| -> BoxFuture<()> { Box::pin(handler(msg, handle)) });
+        // Register synchronously when possible so the handler is in place
+        // before the message loop starts.
+        if let Ok(mut lock) = self.message_handler.handler.try_lock() {
+            *lock = Some(boxed);
+        } else {
+            let message_handler = Arc::clone(&self.message_handler);
+            tokio::spawn(async move {
+                let mut lock = message_handler.handler.lock().await;
+                *lock = Some(boxed);
+            });
+        }
+    }
+
+    /// Sets whether the [`on_message`](Self::on_message) handler takes
+    /// precedence over `on_text` and `on_binary` handlers.
+    ///
+    /// When `preferred` is `true`, every text and binary message goes to the
+    /// `on_message` handler alone. When `false` (the default), kind-specific
+    /// handlers run instead wherever one is registered. Without an
+    /// `on_message` handler, preferring it leaves data messages unhandled.
+    ///
+    /// ## Parameters
+    ///
+    /// - `preferred`: Whether `on_message` handles every data message
+    pub fn prefer_on_message(&self, preferred: bool) {
+        self.message_handler
+            .preferred
+            .store(preferred, Ordering::Relaxed);
     }
 
     /// Registers a handler for text message events.
@@ -602,13 +1328,225 @@ where
         F: Fn(TextMessageEvent, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
-        let text_message_handler = Arc::clone(&self.text_message_handler);
-        tokio::spawn(async move {
-            let mut lock = text_message_handler.lock().await;
-            *lock = Some(Box::new(move |msg, handle| Box::pin(handler(msg, handle))));
+        let boxed: Box<_> =
+            Box::new(move |msg, handle| -> BoxFuture<()> { Box::pin(handler(msg, handle)) });
+        // Register synchronously when possible so the handler is in place
+        // before the message loop starts.
+        if let Ok(mut lock) = self.text_message_handler.try_lock() {
+            *lock = Some(boxed);
+        } else {
+            let text_message_handler = Arc::clone(&self.text_message_handler);
+            tokio::spawn(async move {
+                let mut lock = text_message_handler.lock().await;
+                *lock = Some(boxed);
+            });
+        }
+    }
+
+    /// Registers a fallible handler for text message events.
+    ///
+    /// Works like [`Connection::on_text`], but the handler returns a
+    /// `Result`. Errors are sent back to the client as
+    /// `{ "error": "...", "code": ... }` when the server enables
+    /// `Wynd::set_auto_error_reply` (requires the `json` feature), and logged
+    /// otherwise. Replaces any handler registered with `on_text`.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes a `TextMessageEvent` and `ConnectionHandle`
+    ///   and returns `Result<(), HandlerError>`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::types::HandlerError;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_text_try(|msg, handle| async move {
+    ///             let n: u32 = msg
+    ///                 .data
+    ///                 .parse()
+    ///                 .map_err(|_| HandlerError::new("expected a number").with_code(400))?;
+    ///             let _ = handle.send_text((n * 2).to_string()).await;
+    ///             Ok(())
+    ///         });
+    ///     });
+    /// }
+    /// ```
+    pub fn on_text_try<F, Fut>(&self, handler: F)
+    where
+        F: Fn(TextMessageEvent, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let reply = self.auto_error_reply;
+        self.on_text(move |msg, handle| {
+            let handler = Arc::clone(&handler);
+            async move {
+                if let Err(err) = handler(msg, Arc::clone(&handle)).await {
+                    report_handler_error(&handle, err, reply).await;
+                }
+            }
+        });
+    }
+
+    /// Returns a typed view of this connection for the message type `M`.
+    ///
+    /// See [`crate::typed::TypedConnection::on_message`].
+    #[cfg(feature = "json")]
+    pub fn typed<M>(&self) -> crate::typed::TypedConnection<'_, M, T>
+    where
+        M: crate::typed::Message,
+    {
+        crate::typed::TypedConnection::new(self)
+    }
+
+    /// Registers a fallible handler for binary message events.
+    ///
+    /// The binary counterpart of [`Connection::on_text_try`]. Replaces any
+    /// handler registered with `on_binary`.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes a `BinaryMessageEvent` and `ConnectionHandle`
+    ///   and returns `Result<(), HandlerError>`
+    pub fn on_binary_try<F, Fut>(&self, handler: F)
+    where
+        F: Fn(BinaryMessageEvent, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let reply = self.auto_error_reply;
+        self.on_binary(move |msg, handle| {
+            let handler = Arc::clone(&handler);
+            async move {
+                if let Err(err) = handler(msg, Arc::clone(&handle)).await {
+                    report_handler_error(&handle, err, reply).await;
+                }
+            }
         });
     }
 
+    /// Registers a handler for pings sent by the client.
+    ///
+    /// The handler receives the ping payload, e.g. to track client liveness.
+    /// It runs before the automatic pong reply, or instead of it when the
+    /// server called `Wynd::disable_auto_pong`, in which case the handler can
+    /// answer with a custom payload through `ConnectionHandle::send_pong`.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes the ping payload and `ConnectionHandle`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.disable_auto_pong();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_ping(|payload, handle| async move {
+    ///         let _ = handle.send_pong(payload).await;
+    ///     });
+    /// });
+    /// ```
+    pub fn on_ping<F, Fut>(&self, handler: F)
+    where
+        F: Fn(Vec<u8>, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let boxed: Box<_> = Box::new(move |payload, handle| -> BoxFuture<()> {
+            Box::pin(handler(payload, handle))
+        });
+        if let Ok(mut lock) = self.ping_handler.try_lock() {
+            *lock = Some(boxed);
+        } else {
+            let ping_handler = Arc::clone(&self.ping_handler);
+            tokio::spawn(async move {
+                let mut lock = ping_handler.lock().await;
+                *lock = Some(boxed);
+            });
+        }
+    }
+
+    /// Registers a handler for pongs sent by the client.
+    ///
+    /// The handler receives a [`PongEvent`] with the pong payload and the time
+    /// the frame arrived, e.g. to measure round-trip latency against a ping
+    /// sent with `ConnectionHandle::send_ping`. Pongs answering keepalive
+    /// pings are reported too.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes a `PongEvent` and `ConnectionHandle`
+    pub fn on_pong<F, Fut>(&self, handler: F)
+    where
+        F: Fn(PongEvent, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let boxed: Box<_> =
+            Box::new(move |event, handle| -> BoxFuture<()> { Box::pin(handler(event, handle)) });
+        if let Ok(mut lock) = self.pong_handler.try_lock() {
+            *lock = Some(boxed);
+        } else {
+            let pong_handler = Arc::clone(&self.pong_handler);
+            tokio::spawn(async move {
+                let mut lock = pong_handler.lock().await;
+                *lock = Some(boxed);
+            });
+        }
+    }
+
+    /// Registers a handler for connection-level errors.
+    ///
+    /// The handler runs when reading from the socket fails, e.g. because the
+    /// client broke the protocol, sent invalid UTF-8 or went away without a
+    /// close frame. It receives an [`ErrorEvent`] classifying the failure and
+    /// runs before the connection is marked closed; the connection is closed
+    /// afterwards regardless of what the handler does.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes an `ErrorEvent` and `ConnectionHandle`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_error(|err, handle| async move {
+    ///         eprintln!("connection {} failed ({:?}): {}", handle.id(), err.kind, err.message);
+    ///     });
+    /// });
+    /// ```
+    pub fn on_error<F, Fut>(&self, handler: F)
+    where
+        F: Fn(ErrorEvent, Arc<ConnectionHandle<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let boxed: Box<_> =
+            Box::new(move |event, handle| -> BoxFuture<()> { Box::pin(handler(event, handle)) });
+        if let Ok(mut lock) = self.error_handler.try_lock() {
+            *lock = Some(boxed);
+        } else {
+            let error_handler = Arc::clone(&self.error_handler);
+            tokio::spawn(async move {
+                let mut lock = error_handler.lock().await;
+                *lock = Some(boxed);
+            });
+        }
+    }
+
     /// Registers a handler for connection close events.
     ///
     /// This method sets up a handler that will be called when the
@@ -657,11 +1595,80 @@ where
         F: Fn(CloseEvent) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
-        let close_handler = Arc::clone(&self.close_handler);
-        tokio::spawn(async move {
-            let mut lock = close_handler.lock().await;
-            *lock = Some(Box::new(move |event| Box::pin(handler(event))));
-        });
+        let boxed: Box<_> = Box::new(move |event| -> BoxFuture<()> { Box::pin(handler(event)) });
+        // Register synchronously when possible so the handler is in place
+        // before the message loop starts.
+        if let Ok(mut lock) = self.close_handler.try_lock() {
+            *lock = Some(boxed);
+        } else {
+            let close_handler = Arc::clone(&self.close_handler);
+            tokio::spawn(async move {
+                let mut lock = close_handler.lock().await;
+                *lock = Some(boxed);
+            });
+        }
+    }
+
+    /// Registers the server's bookkeeping for when the connection closes.
+    ///
+    /// Runs after the application's [`on_close`](Self::on_close) handler
+    /// and is kept in its own slot, so it cannot be replaced by one.
+    pub(crate) async fn on_cleanup<F, Fut>(&self, handler: F)
+    where
+        F: Fn(CloseEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        *self.cleanup_handler.lock().await = Some(Box::new(move |event| -> BoxFuture<()> {
+            Box::pin(handler(event))
+        }));
+    }
+
+    /// Runs only the server cleanup, e.g. for a connection the server evicts.
+    ///
+    /// Cleanup is idempotent, so it is safe for it to run again once the
+    /// message loop ends.
+    pub(crate) async fn run_cleanup(&self, close_event: CloseEvent) {
+        let handler_fut = {
+            let handler = self.cleanup_handler.lock().await;
+            handler.as_ref().map(|h| h(close_event))
+        };
+        if let Some(fut) = handler_fut {
+            fut.await;
+        }
+    }
+
+    /// Runs the close handler, if one is registered, then the server cleanup.
+    async fn fire_close(
+        close_handler: &CloseHandler,
+        cleanup_handler: &CloseHandler,
+        close_event: CloseEvent,
+    ) {
+        for handler in [close_handler, cleanup_handler] {
+            let handler_fut = {
+                let handler = handler.lock().await;
+                handler.as_ref().map(|h| h(close_event.clone()))
+            };
+            if let Some(fut) = handler_fut {
+                fut.await;
+            }
+        }
+    }
+
+    /// Runs the error handler for `error`, if one is registered.
+    async fn fire_error(
+        error_handler: &ErrorHandler<T>,
+        error: &Error,
+        handle: &Arc<ConnectionHandle<T>>,
+    ) {
+        let handler_fut = {
+            let handler = error_handler.lock().await;
+            handler
+                .as_ref()
+                .map(|h| h(ErrorEvent::new(error), Arc::clone(handle)))
+        };
+        if let Some(fut) = handler_fut {
+            fut.await;
+        }
     }
 
     /// Main message processing loop.
@@ -675,78 +1682,180 @@ where
     /// - `handle`: The connection handle for sending messages
     /// - `text_message_handler`: Handler for text messages
     /// - `binary_message_handler`: Handler for binary messages
+    /// - `message_handler`: Unified handler for text and binary messages
+    /// - `ping_handler`: Handler for pings
+    /// - `pong_handler`: Handler for pongs
+    /// - `error_handler`: Handler for read errors
     /// - `close_handler`: Handler for close events
+    /// - `cleanup_handler`: Server bookkeeping run after the close handler
+    /// - `keepalive`: Keepalive ping settings, if enabled
+    #[allow(clippy::too_many_arguments)]
     async fn message_loop(
         handle: Arc<ConnectionHandle<T>>,
         text_message_handler: TextMessageHandler<T>,
         binary_message_handler: BinaryMessageHandler<T>,
+        message_handler: Arc<MessageHandler<T>>,
+        ping_handler: PingHandler<T>,
+        pong_handler: PongHandler<T>,
+        error_handler: ErrorHandler<T>,
         close_handler: CloseHandler,
+        cleanup_handler: CloseHandler,
         reader: Arc<tokio::sync::Mutex<futures::stream::SplitStream<WebSocketStream<T>>>>,
-        state: Arc<tokio::sync::Mutex<ConnState>>,
+        keepalive: Option<Keepalive>,
     ) {
+        let state = Arc::clone(&handle.state);
+        let mut next_ping = keepalive.map(|k| tokio::time::Instant::now() + k.interval);
+        let mut pong_deadline: Option<tokio::time::Instant> = None;
+        let mut reads_paused = handle.reads_paused.subscribe();
+
         loop {
-            let msg = {
-                let mut rd = reader.lock().await;
-                let msg = futures::StreamExt::next(&mut *rd).await;
-                msg
+            let wake_at = pong_deadline.or(next_ping);
+            let msg = tokio::select! {
+                // Reads first: after a slow handler the pong deadline may have
+                // passed while the pong itself is already waiting to be read.
+                biased;
+                msg = async {
+                    // While paused the socket is not polled, so TCP backpressure
+                    // reaches the client.
+                    let _ = reads_paused.wait_for(|paused| !*paused).await;
+                    let mut rd = reader.lock().await;
+                    futures::StreamExt::next(&mut *rd).await
+                } => msg,
+                _ = async {
+                    match wake_at {
+                        Some(at) => tokio::time::sleep_until(at).await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    let Some(keepalive) = keepalive else { continue };
+                    let now = tokio::time::Instant::now();
+
+                    if pong_deadline.is_some() {
+                        // No pong within the timeout: treat the network as dead.
+                        *state.lock().await = ConnState::CLOSED;
+                        let _ = futures::SinkExt::close(&mut *handle.lock_writer().await).await;
+                        Self::fire_close(&close_handler, &cleanup_handler, CloseEvent::ping_timeout()).await;
+                        break;
+                    }
+
+                    let mut w = handle.lock_writer().await;
+                    let _ = futures::SinkExt::send(&mut *w, Message::Ping(Vec::new().into())).await;
+                    pong_deadline = Some(now + keepalive.timeout);
+                    next_ping = Some(now + keepalive.interval);
+                    continue;
+                }
             };
 
+            if let Some(Ok(ref frame)) = msg {
+                handle.touch();
+                handle.counters.record_received(frame);
+                if handle.record_received(frame.len()) {
+                    let _ = handle
+                        .send_close_frame(CloseCode::Size, "total bytes limit exceeded")
+                        .await;
+                    *state.lock().await = ConnState::CLOSED;
+                    Self::fire_close(
+                        &close_handler,
+                        &cleanup_handler,
+                        CloseEvent::total_bytes_exceeded(),
+                    )
+                    .await;
+                    break;
+                }
+                if !handle.accepts(frame) {
+                    let _ = handle
+                        .send_close_frame(CloseCode::Unsupported, "unsupported data")
+                        .await;
+                    *state.lock().await = ConnState::CLOSED;
+                    Self::fire_close(
+                        &close_handler,
+                        &cleanup_handler,
+                        CloseEvent::unsupported_data(),
+                    )
+                    .await;
+                    break;
+                }
+            }
+
             match msg {
+                Some(Ok(Message::Text(_) | Message::Binary(_)))
+                    if handle.inbound_muted.load(Ordering::Relaxed) => {}
                 Some(Ok(Message::Text(text))) => {
-                    let handler_fut = {
+                    let specific = if message_handler.is_preferred() {
+                        None
+                    } else {
                         let handler = text_message_handler.lock().await;
-                        if let Some(ref h) = *handler {
-                            Some(h(
-                                TextMessageEvent::new(text.to_string()),
-                                Arc::clone(&handle),
-                            ))
-                        } else {
-                            None
-                        }
+                        (*handler).as_ref().map(|h| {
+                            h(TextMessageEvent::new(text.to_string()), Arc::clone(&handle))
+                        })
                     };
+                    let handler_fut = message_handler
+                        .or_unified(
+                            specific,
+                            || IncomingMessage::Text(text.to_string()),
+                            &handle,
+                        )
+                        .await;
                     if let Some(fut) = handler_fut {
+                        let _permit = handle.acquire_handler_permit().await;
                         fut.await;
                     }
                 }
                 Some(Ok(Message::Ping(payload))) => {
-                    // Reply with Pong to keep the connection healthy.
-                    let mut w = handle.writer.lock().await;
-                    let _ = futures::SinkExt::send(&mut *w, Message::Pong(payload)).await;
+                    let handler_fut = {
+                        let handler = ping_handler.lock().await;
+                        (*handler)
+                            .as_ref()
+                            .map(|h| h(payload.to_vec(), Arc::clone(&handle)))
+                    };
+                    if let Some(fut) = handler_fut {
+                        fut.await;
+                    }
+                    if handle.auto_pong {
+                        // Reply with Pong to keep the connection healthy.
+                        let mut w = handle.lock_writer().await;
+                        let _ = futures::SinkExt::send(&mut *w, Message::Pong(payload)).await;
+                    }
                 }
-                Some(Ok(Message::Pong(_))) => {
-                    // Optional: update heartbeat/latency metrics here.
+                Some(Ok(Message::Pong(payload))) => {
+                    pong_deadline = None;
+                    let event = PongEvent::new(payload.to_vec());
+                    let handler_fut = {
+                        let handler = pong_handler.lock().await;
+                        (*handler).as_ref().map(|h| h(event, Arc::clone(&handle)))
+                    };
+                    if let Some(fut) = handler_fut {
+                        fut.await;
+                    }
                 }
                 Some(Ok(Message::Binary(data))) => {
-                    let handler_fut = {
+                    let specific = if message_handler.is_preferred() {
+                        None
+                    } else {
                         let handler = binary_message_handler.lock().await;
-                        if let Some(ref h) = *handler {
-                            Some(h(
-                                BinaryMessageEvent::new(data.to_vec()),
-                                Arc::clone(&handle),
-                            ))
-                        } else {
-                            None
-                        }
+                        (*handler)
+                            .as_ref()
+                            .map(|h| h(BinaryMessageEvent::new(data.to_vec()), Arc::clone(&handle)))
                     };
+                    let handler_fut = message_handler
+                        .or_unified(specific, || IncomingMessage::Binary(data.to_vec()), &handle)
+                        .await;
                     if let Some(fut) = handler_fut {
+                        let _permit = handle.acquire_handler_permit().await;
                         fut.await;
                     }
                 }
                 Some(Ok(Message::Close(close_frame))) => {
                     let close_event = match close_frame {
-                        Some(e) => CloseEvent::new(e.code.into(), e.reason.to_string()),
+                        Some(e) => {
+                            CloseEvent::from_remote(e.code, &e.reason, handle.max_close_reason_len)
+                        }
                         None => CloseEvent::new(1005, "No status received".into()),
                     };
 
-                    let handler_fut = {
-                        let handler = close_handler.lock().await;
-                        handler.as_ref().map(|h| h(close_event.clone()))
-                    };
-                    if let Some(fut) = handler_fut {
-                        fut.await;
-                    }
+                    Self::fire_close(&close_handler, &cleanup_handler, close_event.clone()).await;
 
-                    let mut w = handle.writer.lock().await;
+                    let mut w = handle.lock_writer().await;
 
                     let _ = futures::SinkExt::send(
                         &mut *w,
@@ -766,7 +1875,26 @@ where
                 Some(Ok(_)) => {
                     eprintln!("Unhandled message type");
                 }
+                Some(Err(ref error @ Error::Capacity(ref e))) => {
+                    handle.record_error(e);
+                    Self::fire_error(&error_handler, error, &handle).await;
+                    // The oversized message is discarded before it is buffered.
+                    let _ = handle
+                        .send_close_frame(CloseCode::Size, "message too large")
+                        .await;
+                    eprintln!("Closing connection {}: {}", handle.id(), e);
+                    *state.lock().await = ConnState::CLOSED;
+                    Self::fire_close(
+                        &close_handler,
+                        &cleanup_handler,
+                        CloseEvent::message_too_large(),
+                    )
+                    .await;
+                    break;
+                }
                 Some(Err(e)) => {
+                    handle.record_error(&e);
+                    Self::fire_error(&error_handler, &e, &handle).await;
                     let close_code = determine_close_code(&e);
 
                     if let Ok(mut w) = handle.writer.try_lock() {
@@ -785,14 +1913,83 @@ where
                         let mut s = state.lock().await;
                         *s = ConnState::CLOSED;
                     }
+                    Self::fire_close(&close_handler, &cleanup_handler, CloseEvent::abnormal())
+                        .await;
+                    break;
+                }
+                None => {
+                    // The stream ended without a close frame.
+                    *state.lock().await = ConnState::CLOSED;
+                    Self::fire_close(&close_handler, &cleanup_handler, CloseEvent::abnormal())
+                        .await;
                     break;
                 }
-                _ => {}
             }
         }
     }
 }
 
+/// Reports an error returned by a fallible message handler.
+///
+/// Sends the error to the client as JSON when `reply` is set, and logs it otherwise.
+async fn report_handler_error<T>(
+    handle: &ConnectionHandle<T>,
+    err: HandlerError,
+    #[cfg_attr(not(feature = "json"), allow(unused_variables))] reply: bool,
+) where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    #[cfg(feature = "json")]
+    if reply {
+        if let Err(e) = handle.send_text(err.to_json()).await {
+            eprintln!(
+                "Failed to send error reply to client {}: {}",
+                handle.id(),
+                e
+            );
+        }
+        return;
+    }
+    eprintln!("Handler error on connection {}: {}", handle.id(), err);
+}
+
+/// Parses a raw query string into percent-decoded key/value pairs.
+fn parse_query(raw_query: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    for pair in raw_query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        params
+            .entry(decode_query_component(key))
+            .or_insert_with(|| decode_query_component(value));
+    }
+    params
+}
+
+/// Decodes `+` and `%XX` escapes in a query string component.
+fn decode_query_component(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
 fn determine_close_code(e: &Error) -> CloseCode {
     match e {
         Error::Utf8(_) => CloseCode::Invalid,
@@ -0,0 +1,58 @@
+//! Server-side connection groups.
+//!
+//! Groups are lightweight routing primitives keyed by a [`GroupId`]. Unlike
+//! rooms, they are never announced to clients and have no join/leave
+//! semantics of their own: the server decides which connections belong to a
+//! group and can then broadcast to that subset, e.g. to shard broadcasts by
+//! region. Connections are removed from every group when they disconnect.
+
+use std::fmt::Display;
+
+/// Identifier of a connection group.
+///
+/// Groups can be keyed either by a number or by a name. Both forms can be
+/// created from plain values through the `From` implementations, so most APIs
+/// accept `impl Into<GroupId>`.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::group::GroupId;
+///
+/// assert_eq!(GroupId::from(7), GroupId::Id(7));
+/// assert_eq!(GroupId::from("eu-west"), GroupId::Name("eu-west".to_string()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GroupId {
+    /// A numeric group identifier.
+    Id(u64),
+    /// A named group identifier.
+    Name(String),
+}
+
+impl From<u64> for GroupId {
+    fn from(id: u64) -> Self {
+        GroupId::Id(id)
+    }
+}
+
+impl From<String> for GroupId {
+    fn from(name: String) -> Self {
+        GroupId::Name(name)
+    }
+}
+
+impl From<&str> for GroupId {
+    fn from(name: &str) -> Self {
+        GroupId::Name(name.to_string())
+    }
+}
+
+impl Display for GroupId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupId::Id(id) => write!(f, "{}", id),
+            GroupId::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
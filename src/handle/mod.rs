@@ -4,20 +4,181 @@
 //! `Broadcaster` for sending messages to multiple clients. These types are
 //! created and managed by the server and used inside connection event handlers.
 //! See `wynd::Wynd` and `conn::Connection` for where these are produced.
-use std::{fmt::Debug, net::SocketAddr, sync::Arc};
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    fmt::{Debug, Display},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
 
 use tokio::{
     io::{AsyncRead, AsyncWrite},
-    sync::oneshot,
+    sync::{mpsc, oneshot, Semaphore, SemaphorePermit},
+    task::AbortHandle,
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
+    },
+    WebSocketStream,
 };
-use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
 
 use crate::{
     conn::ConnState,
+    group::GroupId,
+    metrics::{Counters, Histogram, SETUP_TIME_BUCKETS_MS},
     room::{ClientInfo, RoomEvents, RoomMethods},
-    ClientRegistry,
+    types::{is_connection_gone, BatchSendError, CloseEvent, MessageKinds, RoomError, WyndError},
+    ClientRegistry, GroupRegistry, TagRegistry, UserRegistry,
 };
 
+/// Item handed to a connection's writer task.
+#[derive(Debug)]
+pub(crate) enum Outbound {
+    /// A frame to write to the client.
+    Frame(Message),
+    /// Signalled once every frame queued before it has been written.
+    Barrier(oneshot::Sender<()>),
+}
+
+/// Function applied to every outbound data frame of a connection.
+type TransformFn = Arc<dyn Fn(Message) -> Message + Send + Sync>;
+
+/// Shared slot holding a connection's outbound transform, if one is set.
+#[derive(Clone, Default)]
+pub(crate) struct OutboundTransform(Arc<std::sync::RwLock<Option<TransformFn>>>);
+
+impl Debug for OutboundTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OutboundTransform")
+    }
+}
+
+/// Type-keyed values attached to a connection, shared by all its handles.
+#[derive(Clone, Default)]
+pub(crate) struct ConnectionData(Arc<std::sync::Mutex<HashMap<TypeId, Box<dyn Any + Send>>>>);
+
+impl Debug for ConnectionData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ConnectionData")
+    }
+}
+
+impl ConnectionData {
+    /// Stores `value`, replacing any value of the same type.
+    fn set<D>(&self, value: D)
+    where
+        D: Any + Send,
+    {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(TypeId::of::<D>(), Box::new(value));
+    }
+
+    /// Returns a copy of the stored value of type `D`, if any.
+    fn get<D>(&self) -> Option<D>
+    where
+        D: Any + Send + Clone,
+    {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&TypeId::of::<D>())
+            .and_then(|value| value.downcast_ref::<D>())
+            .cloned()
+    }
+}
+
+impl OutboundTransform {
+    /// Replaces the transform.
+    pub(crate) fn set(&self, transform: TransformFn) {
+        *self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(transform);
+    }
+
+    /// Runs the message through the transform, or returns it unchanged.
+    pub(crate) fn apply(&self, message: Message) -> Message {
+        let transform = self
+            .0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        match transform {
+            Some(transform) => transform(message),
+            None => message,
+        }
+    }
+}
+
+/// Maximum number of important broadcasts buffered for a paused connection.
+///
+/// Once the buffer is full, the oldest buffered message is dropped to make
+/// room for each new one. See [`Broadcaster::text_important`].
+pub const IMPORTANT_BACKLOG_LIMIT: usize = 64;
+
+/// Default capacity of the channel carrying room query responses to a handle.
+///
+/// See [`Wynd::set_room_response_capacity`](crate::wynd::Wynd::set_room_response_capacity).
+pub const DEFAULT_ROOM_RESPONSE_CAPACITY: usize = 16;
+
+/// Default number of sends between yields to the scheduler in broadcast and
+/// room fan-out loops.
+///
+/// See [`Wynd::set_broadcast_yield_interval`](crate::wynd::Wynd::set_broadcast_yield_interval).
+pub const DEFAULT_BROADCAST_YIELD_INTERVAL: usize = 64;
+
+/// Largest payload a ping or pong frame may carry, in bytes (RFC 6455).
+pub const MAX_CONTROL_PAYLOAD: usize = 125;
+
+/// Rejects ping and pong payloads that do not fit in a control frame.
+fn check_control_payload(kind: &str, payload: &[u8]) -> Result<(), WyndError> {
+    if payload.len() > MAX_CONTROL_PAYLOAD {
+        return Err(WyndError::InvalidPayload(format!(
+            "{} payload is {} bytes, control frames carry at most {}",
+            kind,
+            payload.len(),
+            MAX_CONTROL_PAYLOAD
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects close codes a server may not send, and reasons too long to fit
+/// a close frame.
+pub(crate) fn check_close_frame(code: u16, reason: &str) -> Result<(), WyndError> {
+    if !matches!(code, 1000 | 1001 | 1003 | 1008 | 1011..=1013 | 3000..=4999) {
+        return Err(WyndError::InvalidPayload(format!(
+            "{} is not a close code the server may send",
+            code
+        )));
+    }
+    if reason.len() > CloseEvent::MAX_REASON_LEN {
+        return Err(WyndError::InvalidPayload(format!(
+            "close reason is {} bytes, close frames carry at most {}",
+            reason.len(),
+            CloseEvent::MAX_REASON_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Time constant of the moving average behind [`ConnectionHandle::message_rate`].
+const MESSAGE_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Decays a message rate by the time elapsed since it was last updated.
+fn decay_rate(rate: f64, elapsed: Duration) -> f64 {
+    rate * (-elapsed.as_secs_f64() / MESSAGE_RATE_WINDOW.as_secs_f64()).exp()
+}
+
 /// Handle for interacting with a WebSocket connection.
 ///
 /// `ConnectionHandle` provides methods to send messages and manage
@@ -82,9 +243,87 @@ where
     pub(crate) state: Arc<tokio::sync::Mutex<ConnState>>,
 
     pub(crate) room_sender: Arc<tokio::sync::mpsc::Sender<RoomEvents<T>>>,
-    pub(crate) response_sender: Arc<tokio::sync::mpsc::Sender<Vec<&'static str>>>,
-    pub(crate) response_receiver:
-        Arc<tokio::sync::Mutex<tokio::sync::mpsc::Receiver<Vec<&'static str>>>>,
+    pub(crate) response_sender: Arc<tokio::sync::mpsc::Sender<Vec<String>>>,
+    pub(crate) response_receiver: Arc<tokio::sync::Mutex<tokio::sync::mpsc::Receiver<Vec<String>>>>,
+
+    /// Shared registry of server-side connection groups.
+    pub(crate) groups: GroupRegistry,
+
+    /// Shared index of user ids to the connections that belong to them.
+    pub(crate) users: UserRegistry,
+
+    /// Shared index of tags to the connections that carry them.
+    pub(crate) tags: TagRegistry,
+
+    /// When a message was last received from the client.
+    pub(crate) last_activity: Arc<std::sync::Mutex<Instant>>,
+
+    /// Exponentially decaying count of inbound frames, as of `last_activity`.
+    pub(crate) message_rate: Arc<std::sync::Mutex<f64>>,
+
+    /// Transform applied to outbound data frames, shared with the `Connection`.
+    pub(crate) outbound_transform: OutboundTransform,
+
+    /// Total payload bytes received from the client.
+    pub(crate) bytes_received: Arc<AtomicU64>,
+
+    /// Lifetime limit on `bytes_received`, if one is configured.
+    pub(crate) max_total_bytes: Option<u64>,
+
+    /// Longest close reason, in characters, accepted from the client.
+    pub(crate) max_close_reason_len: usize,
+
+    /// Whether the message loop replies to pings itself.
+    pub(crate) auto_pong: bool,
+
+    /// Whether room messages fail with `RoomError::NotAMember` for rooms
+    /// this connection has not joined.
+    pub(crate) require_room_membership: bool,
+
+    /// How many writer lock acquisitions had to wait for another holder.
+    pub(crate) writer_contention: Arc<AtomicU64>,
+
+    /// Server-wide bound on concurrently running handlers, if configured.
+    pub(crate) handler_permits: Option<Arc<Semaphore>>,
+
+    /// Bits of the [`MessageKinds`] the connection accepts, shared with the `Connection`.
+    pub(crate) accepted_kinds: Arc<AtomicU8>,
+
+    /// Whether inbound data messages are discarded, shared with the `Connection`.
+    pub(crate) inbound_muted: Arc<AtomicBool>,
+
+    /// Headers of the handshake request, shared with the `Connection`.
+    pub(crate) headers: Arc<HashMap<String, String>>,
+
+    /// Most recent send or protocol error, cleared by a successful send.
+    pub(crate) last_error: Arc<std::sync::Mutex<Option<String>>>,
+
+    /// Whether the message loop should stop reading from the client.
+    pub(crate) reads_paused: Arc<tokio::sync::watch::Sender<bool>>,
+
+    /// Important broadcasts held back while reads are paused.
+    pub(crate) important_backlog: Arc<std::sync::Mutex<VecDeque<String>>>,
+
+    /// Bounded queue feeding the connection's writer task, if configured.
+    pub(crate) send_queue: Option<mpsc::Sender<Outbound>>,
+
+    /// When the server accepted the connection.
+    pub(crate) accepted_at: Instant,
+
+    /// Time from accept until the connection opened, once it has.
+    pub(crate) setup_time: Arc<OnceLock<Duration>>,
+
+    /// Server-wide histogram of connection setup times.
+    pub(crate) setup_times: Arc<Histogram>,
+
+    /// Server-wide traffic counters.
+    pub(crate) counters: Arc<Counters>,
+
+    /// Aborts the task running the connection's message loop, once started.
+    pub(crate) task: Arc<OnceLock<AbortHandle>>,
+
+    /// Application values attached with [`ConnectionHandle::set_data`].
+    pub(crate) data: ConnectionData,
 }
 
 impl<T> Clone for ConnectionHandle<T>
@@ -101,6 +340,32 @@ where
             room_sender: Arc::clone(&self.room_sender),
             response_sender: Arc::clone(&self.response_sender),
             response_receiver: Arc::clone(&self.response_receiver),
+            groups: Arc::clone(&self.groups),
+            users: Arc::clone(&self.users),
+            tags: Arc::clone(&self.tags),
+            last_activity: Arc::clone(&self.last_activity),
+            message_rate: Arc::clone(&self.message_rate),
+            outbound_transform: self.outbound_transform.clone(),
+            bytes_received: Arc::clone(&self.bytes_received),
+            max_total_bytes: self.max_total_bytes,
+            max_close_reason_len: self.max_close_reason_len,
+            auto_pong: self.auto_pong,
+            require_room_membership: self.require_room_membership,
+            writer_contention: Arc::clone(&self.writer_contention),
+            handler_permits: self.handler_permits.clone(),
+            accepted_kinds: Arc::clone(&self.accepted_kinds),
+            inbound_muted: Arc::clone(&self.inbound_muted),
+            headers: Arc::clone(&self.headers),
+            last_error: Arc::clone(&self.last_error),
+            reads_paused: Arc::clone(&self.reads_paused),
+            important_backlog: Arc::clone(&self.important_backlog),
+            send_queue: self.send_queue.clone(),
+            accepted_at: self.accepted_at,
+            setup_time: Arc::clone(&self.setup_time),
+            setup_times: Arc::clone(&self.setup_times),
+            counters: Arc::clone(&self.counters),
+            task: Arc::clone(&self.task),
+            data: self.data.clone(),
         }
     }
 }
@@ -109,6 +374,458 @@ impl<T> ConnectionHandle<T>
 where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
+    /// Creates a new handle for a connection.
+    ///
+    /// The handle gets its own response channel for room queries and an empty
+    /// group registry; the server replaces the registry with its shared one.
+    pub(crate) fn new(
+        id: u64,
+        writer: Arc<tokio::sync::Mutex<futures::stream::SplitSink<WebSocketStream<T>, Message>>>,
+        addr: SocketAddr,
+        broadcast: Broadcaster<T>,
+        state: Arc<tokio::sync::Mutex<ConnState>>,
+        room_sender: Arc<tokio::sync::mpsc::Sender<RoomEvents<T>>>,
+    ) -> Self {
+        let (response_sender, response_receiver) =
+            tokio::sync::mpsc::channel(DEFAULT_ROOM_RESPONSE_CAPACITY);
+
+        Self {
+            id,
+            writer,
+            addr,
+            broadcast,
+            state,
+            room_sender,
+            response_sender: Arc::new(response_sender),
+            response_receiver: Arc::new(tokio::sync::Mutex::new(response_receiver)),
+            groups: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            users: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            tags: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            last_activity: Arc::new(std::sync::Mutex::new(Instant::now())),
+            message_rate: Arc::new(std::sync::Mutex::new(0.0)),
+            outbound_transform: OutboundTransform::default(),
+            bytes_received: Arc::new(AtomicU64::new(0)),
+            max_total_bytes: None,
+            max_close_reason_len: CloseEvent::MAX_REASON_LEN,
+            auto_pong: true,
+            require_room_membership: false,
+            writer_contention: Arc::new(AtomicU64::new(0)),
+            handler_permits: None,
+            accepted_kinds: Arc::new(AtomicU8::new(MessageKinds::ALL.bits())),
+            inbound_muted: Arc::new(AtomicBool::new(false)),
+            headers: Arc::new(HashMap::new()),
+            last_error: Arc::new(std::sync::Mutex::new(None)),
+            reads_paused: Arc::new(tokio::sync::watch::Sender::new(false)),
+            important_backlog: Arc::new(std::sync::Mutex::new(VecDeque::new())),
+            send_queue: None,
+            accepted_at: Instant::now(),
+            setup_time: Arc::new(OnceLock::new()),
+            setup_times: Arc::new(Histogram::new(&SETUP_TIME_BUCKETS_MS)),
+            counters: Arc::new(Counters::default()),
+            task: Arc::new(OnceLock::new()),
+            data: ConnectionData::default(),
+        }
+    }
+
+    /// Replaces the room response channel with one holding up to `capacity` responses.
+    pub(crate) fn set_response_capacity(&mut self, capacity: usize) {
+        let (response_sender, response_receiver) = tokio::sync::mpsc::channel(capacity.max(1));
+        self.response_sender = Arc::new(response_sender);
+        self.response_receiver = Arc::new(tokio::sync::Mutex::new(response_receiver));
+    }
+
+    /// Returns how many sends had to wait for another task to release this
+    /// connection's writer.
+    ///
+    /// Every frame written to the connection, including broadcasts, room
+    /// messages and control frames, takes the writer lock. A steadily rising
+    /// count means several tasks are competing to write to the same client,
+    /// which often points to an overloaded connection or a design that
+    /// funnels too much through one socket.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|_msg, handle| async move {
+    ///         if handle.writer_contention() > 1_000 {
+    ///             eprintln!("connection {} is a write hotspot", handle.id());
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn writer_contention(&self) -> u64 {
+        self.writer_contention.load(Ordering::Relaxed)
+    }
+
+    /// Returns how long the connection took to set up, from the server
+    /// accepting it until it opened.
+    ///
+    /// Covers the WebSocket handshake and server-side setup such as
+    /// middleware and the `on_connection` handler. Returns `None` until the
+    /// connection has opened, i.e. before its `on_open` handler runs. Slow
+    /// setups often point to network or TLS trouble; see
+    /// [`Wynd::setup_time_histogram`](crate::wynd::Wynd::setup_time_histogram)
+    /// for the server-wide distribution.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         if let Some(setup) = handle.setup_time() {
+    ///             println!("connection {} set up in {:?}", handle.id(), setup);
+    ///         }
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub fn setup_time(&self) -> Option<Duration> {
+        self.setup_time.get().copied()
+    }
+
+    /// Records the setup time once, when the connection opens.
+    pub(crate) fn record_setup_time(&self) {
+        let elapsed = self.accepted_at.elapsed();
+        if self.setup_time.set(elapsed).is_ok() {
+            self.setup_times.record(elapsed);
+            self.counters.record_connection();
+        }
+    }
+
+    /// Stops the connection's message loop without a closing handshake.
+    ///
+    /// Used when a shutdown times out, so a connection that never finishes
+    /// closing cannot keep its task alive.
+    pub(crate) fn abort(&self) {
+        if let Some(task) = self.task.get() {
+            task.abort();
+        }
+    }
+
+    /// Locks the writer, counting the acquisition if it had to wait.
+    pub(crate) async fn lock_writer(
+        &self,
+    ) -> tokio::sync::MutexGuard<'_, futures::stream::SplitSink<WebSocketStream<T>, Message>> {
+        lock_counted(&self.writer, &self.writer_contention).await
+    }
+
+    /// Gives the connection a send queue holding up to `capacity` data frames
+    /// and spawns the task that writes them.
+    ///
+    /// The task exits when the client goes away or every handle is dropped.
+    pub(crate) fn set_send_queue_capacity(&mut self, capacity: usize) {
+        let (sender, mut receiver) = mpsc::channel(capacity.max(1));
+        let writer = Arc::clone(&self.writer);
+        let contention = Arc::clone(&self.writer_contention);
+        tokio::spawn(async move {
+            while let Some(item) = receiver.recv().await {
+                match item {
+                    Outbound::Frame(message) => {
+                        let mut writer = lock_counted(&writer, &contention).await;
+                        if futures::SinkExt::send(&mut *writer, message).await.is_err() {
+                            break;
+                        }
+                    }
+                    Outbound::Barrier(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        self.send_queue = Some(sender);
+    }
+
+    /// Queues data frames without waiting, failing if they do not all fit.
+    ///
+    /// A full queue is reported as [`WyndError::Backpressure`]; a queue whose
+    /// writer task has exited as [`WyndError::ConnectionClosed`].
+    fn enqueue(
+        &self,
+        queue: &mpsc::Sender<Outbound>,
+        messages: Vec<Message>,
+    ) -> Result<(), WyndError> {
+        use tokio::sync::mpsc::error::TrySendError;
+
+        let permits = match queue.try_reserve_many(messages.len()) {
+            Ok(permits) => permits,
+            Err(TrySendError::Full(())) => return Err(WyndError::Backpressure),
+            Err(TrySendError::Closed(())) => return Err(WyndError::ConnectionClosed),
+        };
+        for (permit, message) in permits.zip(messages) {
+            permit.send(Outbound::Frame(message));
+        }
+        Ok(())
+    }
+
+    /// Waits until every frame already in the send queue has been written.
+    ///
+    /// Close frames and replayed important broadcasts bypass the queue, so
+    /// those paths call this first to keep them behind the data sent before
+    /// them.
+    async fn flush_send_queue(&self) {
+        let Some(ref queue) = self.send_queue else {
+            return;
+        };
+        let (done, written) = oneshot::channel();
+        if queue.send(Outbound::Barrier(done)).await.is_ok() {
+            let _ = written.await;
+        }
+    }
+
+    /// Waits for a free server-wide handler slot if a limit is configured.
+    pub(crate) async fn acquire_handler_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match self.handler_permits {
+            Some(ref permits) => permits.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Returns the most recent send or protocol error on this connection.
+    ///
+    /// Failed sends and errors reading from the client are recorded here, and
+    /// the next successful send clears it, so application code can see why a
+    /// connection is misbehaving without an error handler.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the error message, or `None` if the last send succeeded or
+    /// nothing has failed yet.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if handle.send_text(msg.data).await.is_err() {
+    ///             eprintln!("echo failed: {:?}", handle.last_error());
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Records `error` as the connection's most recent error.
+    pub(crate) fn record_error(&self, error: &impl Display) {
+        *self
+            .last_error
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(error.to_string());
+    }
+
+    /// Records the outcome of a send for [`last_error`](Self::last_error).
+    fn track<R, E: Display>(&self, result: Result<R, E>) -> Result<R, E> {
+        match result {
+            Ok(_) => {
+                *self
+                    .last_error
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+            }
+            Err(ref e) => self.record_error(e),
+        }
+        result
+    }
+
+    /// Returns when a message was last received from the client.
+    ///
+    /// Starts at the time the connection was established and is updated for
+    /// every inbound frame, including pings and pongs.
+    pub fn last_activity(&self) -> Instant {
+        *self
+            .last_activity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Records inbound activity on the connection.
+    pub(crate) fn touch(&self) {
+        let now = Instant::now();
+        let mut last = self
+            .last_activity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut rate = self
+            .message_rate
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *rate =
+            decay_rate(*rate, now.duration_since(*last)) + 1.0 / MESSAGE_RATE_WINDOW.as_secs_f64();
+        *last = now;
+    }
+
+    /// Returns how many messages per second the client has been sending.
+    ///
+    /// This is an exponential moving average over roughly the last second,
+    /// updated on every inbound frame (including pings and pongs) and
+    /// decaying towards zero while the client is idle. Use it to spot fast
+    /// senders and throttle or warn them before they hit a hard limit.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|_msg, handle| async move {
+    ///         if handle.message_rate() > 50.0 {
+    ///             let _ = handle.send_text("Slow down").await;
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn message_rate(&self) -> f64 {
+        let last = self
+            .last_activity
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let rate = self
+            .message_rate
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        decay_rate(*rate, last.elapsed())
+    }
+
+    /// Returns the total payload bytes received from the client so far.
+    ///
+    /// Counts every inbound frame, including pings and pongs.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    /// Adds an inbound frame to the byte count.
+    ///
+    /// Returns `true` if the connection is now over its total bytes limit.
+    pub(crate) fn record_received(&self, len: usize) -> bool {
+        let total = self.bytes_received.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+        self.max_total_bytes.is_some_and(|max| total > max)
+    }
+
+    /// Returns `true` if `message` is a data frame of an accepted kind, or a
+    /// control frame.
+    pub(crate) fn accepts(&self, message: &Message) -> bool {
+        let accepted = MessageKinds::from_bits(self.accepted_kinds.load(Ordering::Relaxed));
+        match message {
+            Message::Text(_) => accepted.contains(MessageKinds::TEXT),
+            Message::Binary(_) => accepted.contains(MessageKinds::BINARY),
+            _ => true,
+        }
+    }
+
+    /// Stops reading messages from the client until [`resume_reads`](Self::resume_reads).
+    ///
+    /// Unlike delaying handlers, this stops polling the socket altogether, so
+    /// the OS receive buffer fills up and TCP flow control makes the client's
+    /// sends block. This is the way to throttle a fast producer. A message
+    /// that is already being handled finishes normally. Keepalive pings are
+    /// still sent, but pongs are not read while paused, so a pause longer
+    /// than the keepalive timeout closes the connection.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_text(|msg, handle| async move {
+    ///             // Apply backpressure while a slow job runs.
+    ///             handle.pause_reads();
+    ///             println!("processing {}", msg.data);
+    ///             handle.resume_reads();
+    ///         });
+    ///     });
+    /// }
+    /// ```
+    pub fn pause_reads(&self) {
+        self.reads_paused.send_replace(true);
+    }
+
+    /// Resumes reading messages after [`pause_reads`](Self::pause_reads).
+    ///
+    /// Important broadcasts buffered while paused (see
+    /// [`Broadcaster::text_important`]) are replayed in the background.
+    pub fn resume_reads(&self) {
+        self.reads_paused.send_replace(false);
+        if !self.lock_important_backlog().is_empty() {
+            let handle = self.clone();
+            tokio::spawn(async move { handle.replay_important().await });
+        }
+    }
+
+    /// Locks the important broadcast buffer, ignoring poisoning.
+    fn lock_important_backlog(&self) -> std::sync::MutexGuard<'_, VecDeque<String>> {
+        self.important_backlog
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Buffers an important message instead of sending it, if reads are
+    /// paused or earlier important messages are still waiting.
+    ///
+    /// Returns `true` if the message was buffered.
+    pub(crate) fn queue_important(&self, text: &str) -> bool {
+        let mut backlog = self.lock_important_backlog();
+        if backlog.is_empty() && !self.reads_paused() {
+            return false;
+        }
+        if backlog.len() == IMPORTANT_BACKLOG_LIMIT {
+            backlog.pop_front();
+        }
+        backlog.push_back(text.to_owned());
+        true
+    }
+
+    /// Sends buffered important messages in order until the buffer is empty
+    /// or reads are paused again.
+    ///
+    /// Frames already in the send queue are written first, so the replay
+    /// never overtakes data sent before it.
+    async fn replay_important(&self) {
+        self.flush_send_queue().await;
+        let mut writer = self.lock_writer().await;
+        while !self.reads_paused() {
+            let Some(text) = self.lock_important_backlog().pop_front() else {
+                break;
+            };
+            let message = self.outbound_transform.apply(Message::Text(text.into()));
+            if let Err(e) = futures::SinkExt::send(&mut *writer, message).await {
+                if !is_disconnect_error(&e) {
+                    eprintln!("Failed to replay broadcast to client {}: {}", self.id, e);
+                }
+                self.lock_important_backlog().clear();
+                break;
+            }
+        }
+    }
+
+    /// Returns `true` while reads are paused.
+    pub fn reads_paused(&self) -> bool {
+        *self.reads_paused.borrow()
+    }
+
     /// Returns the unique identifier for this connection.
     ///
     /// Each connection gets a unique ID that can be used for logging,
@@ -171,82 +888,91 @@ where
     ///     });
     /// }
     /// ```
-    pub async fn joined_rooms(&self) -> Vec<&'static str> {
-        self.room_sender
+    pub async fn joined_rooms(&self) -> Vec<String> {
+        // Hold the receiver across the request so concurrent callers sharing
+        // this handle each read the response to their own request.
+        let mut receiver = self.response_receiver.lock().await;
+        if let Err(e) = self
+            .room_sender
             .send(RoomEvents::ListRooms { client_id: self.id })
             .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to send list rooms request: {}", e),
-                )
-            })
-            .unwrap();
+        {
+            eprintln!("Failed to send list rooms request: {}", e);
+            return Vec::new();
+        }
 
-        let mut receiver = self.response_receiver.lock().await;
         receiver.recv().await.unwrap_or_default()
     }
 
-    /// Leaves all rooms that this connection has joined.
+    /// Returns `true` if this connection is a member of `room`.
     ///
-    /// This method removes the connection from all rooms it is currently a member of.
-    /// Empty rooms will be automatically cleaned up after the connection leaves.
+    /// Asks the room processor about a single room instead of fetching the
+    /// whole list with [`joined_rooms`](Self::joined_rooms), so joins and
+    /// leaves requested before this call are taken into account.
+    ///
+    /// ## Parameters
+    ///
+    /// - `room`: The room name to check
     ///
     /// ## Returns
     ///
-    /// Returns `Ok(())` if the leave request was sent successfully, or an error
-    /// if the send operation failed.
+    /// Returns `false` if the connection has not joined the room or the room
+    /// processor is not running.
     ///
     /// ## Example
     ///
     /// ```rust
     /// use wynd::wynd::{Wynd, Standalone};
     ///
-    /// #[tokio::main]
-    /// async fn main() {
-    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
     ///
-    ///     wynd.on_connection(|conn| async move {
-    ///         conn.on_open(|handle| async move {
-    ///             // Join some rooms
-    ///             let _ = handle.join("room1").await;
-    ///             let _ = handle.join("room2").await;
-    ///             
-    ///             // Later, leave all rooms
-    ///             let _ = handle.leave_all_rooms().await;
-    ///         })
-    ///         .await;
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if handle.in_room("moderators").await {
+    ///             let _ = handle.broadcast.emit_text(&msg.data).await;
+    ///         }
     ///     });
-    /// }
+    /// });
     /// ```
-    pub async fn leave_all_rooms(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.room_sender
-            .send(RoomEvents::LeaveAllRooms { client_id: self.id })
+    pub async fn in_room(&self, room: &str) -> bool {
+        let (tx, rx) = oneshot::channel();
+        if let Err(e) = self
+            .room_sender
+            .send(RoomEvents::IsMember {
+                client_id: self.id,
+                room_name: room.to_string(),
+                response_to: tx,
+            })
             .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to leave all rooms: {}", e),
-                )
-            })?;
+        {
+            eprintln!("Failed to send room membership request: {}", e);
+            return false;
+        }
 
-        Ok(())
+        rx.await.unwrap_or(false)
     }
 
-    /// Returns a list of all clients connected to the server.
+    /// Returns how many rooms this connection has joined.
     ///
-    ///
-    /// This method sends a request to the room processor and waits for the response.
-    /// It returns a vector of [`ClientInfo`] structs containing information about each client.
+    /// The count comes from the room processor, so it reflects every join
+    /// and leave requested before this call.
     ///
     /// ## Returns
     ///
-    /// Returns `Ok(Vec<ClientInfo>)` if the request was sent successfully, or an error
-    /// if the send operation failed.
+    /// Returns the number of rooms this connection is a member of.
+    pub async fn room_count(&self) -> usize {
+        self.joined_rooms().await.len()
+    }
+
+    /// Returns `true` if this connection is a member of at least one room.
+    ///
+    /// Useful for cleanup policies, e.g. closing connections that have been
+    /// idle in no rooms for a while.
     ///
     /// ## Example
     ///
     /// ```rust
+    /// use std::time::Duration;
     /// use wynd::wynd::{Wynd, Standalone};
     ///
     /// #[tokio::main]
@@ -255,36 +981,95 @@ where
     ///
     ///     wynd.on_connection(|conn| async move {
     ///         conn.on_open(|handle| async move {
-    ///             let _ = handle.clients().await;
+    ///             tokio::time::sleep(Duration::from_secs(60)).await;
+    ///             if !handle.in_any_room().await {
+    ///                 let _ = handle.close().await;
+    ///             }
     ///         })
     ///         .await;
     ///     });
     /// }
     /// ```
-    pub async fn clients(&self) -> Result<Vec<ClientInfo<T>>, std::io::Error> {
-        let (tx, rx) = oneshot::channel();
+    pub async fn in_any_room(&self) -> bool {
+        self.room_count().await > 0
+    }
+
+    /// Leaves all rooms that this connection has joined.
+    ///
+    /// This method removes the connection from all rooms it is currently a member of.
+    /// Empty rooms will be automatically cleaned up after the connection leaves.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if the leave request was sent successfully, or an error
+    /// if the send operation failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_open(|handle| async move {
+    ///             // Join some rooms
+    ///             let _ = handle.join("room1").await;
+    ///             let _ = handle.join("room2").await;
+    ///             
+    ///             // Later, leave all rooms
+    ///             let _ = handle.leave_all_rooms().await;
+    ///         })
+    ///         .await;
+    ///     });
+    /// }
+    /// ```
+    pub async fn leave_all_rooms(&self) -> Result<(), RoomError> {
+        self.room_sender
+            .send(RoomEvents::LeaveAllRooms { client_id: self.id })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns a list of all clients connected to the server.
+    ///
+    ///
+    /// This method sends a request to the room processor and waits for the response.
+    /// It returns a vector of [`ClientInfo`] structs containing information about each client.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(Vec<ClientInfo>)` if the request was sent successfully, or an error
+    /// if the send operation failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_open(|handle| async move {
+    ///             let _ = handle.clients().await;
+    ///         })
+    ///         .await;
+    ///     });
+    /// }
+    /// ```
+    pub async fn clients(&self) -> Result<Vec<ClientInfo<T>>, RoomError> {
+        let (tx, rx) = oneshot::channel();
 
         self.room_sender
             .send(RoomEvents::ListUsers { response_to: tx })
-            .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to list all users: {}", e),
-                )
-            })?;
-
-        match rx.await {
-            Ok(clients) => {
-                return Ok(clients);
-            }
-            Err(err) => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to list all users: {}", err.to_string()),
-                ));
-            }
-        };
+            .await?;
+
+        Ok(rx.await?)
     }
 
     /// Returns the remote address of this connection.
@@ -294,7 +1079,9 @@ where
     ///
     /// ## Returns
     ///
-    /// Returns the `SocketAddr` of the remote client.
+    /// Returns the `SocketAddr` of the remote client. Connections upgraded
+    /// through ripress report the server's address, as the peer address is
+    /// not passed along with the upgrade request.
     ///
     /// ## Example
     ///
@@ -317,6 +1104,46 @@ where
         self.addr
     }
 
+    /// Returns the headers sent with the handshake request.
+    ///
+    /// Names are lowercase, and a header sent more than once has its values
+    /// joined with `", "`. See [`Connection::headers`](crate::conn::Connection::headers).
+    ///
+    /// ## Returns
+    ///
+    /// Returns a map of header names to values.
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    /// Returns one header of the handshake request.
+    ///
+    /// ## Parameters
+    ///
+    /// - `name`: The header name, in any case
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         if handle.header("origin") != Some("https://example.com") {
+    ///             let _ = handle.close().await;
+    ///         }
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
     /// Returns the current state of the WebSocket handler.
     ///
     /// This method asynchronously acquires a lock on the internal state
@@ -345,11 +1172,27 @@ where
         s.clone()
     }
 
+    /// Returns a typed wrapper that sends values of the message type `M`.
+    ///
+    /// See [`crate::typed::TypedHandle::send`].
+    #[cfg(feature = "json")]
+    pub fn typed<M>(self: &Arc<Self>) -> crate::typed::TypedHandle<M, T>
+    where
+        M: crate::typed::Message,
+    {
+        crate::typed::TypedHandle::new(Arc::clone(self))
+    }
+
     /// Sends a text message to the client.
     ///
     /// This method sends a UTF-8 text message to the WebSocket client.
     /// The message is sent asynchronously and the method returns immediately.
     ///
+    /// Sends on one connection are serialized by its writer lock: tasks
+    /// sharing a handle can send concurrently and every frame is written
+    /// whole, never interleaved with another. Frames from different tasks
+    /// may arrive in any order relative to each other.
+    ///
     /// ## Parameters
     ///
     /// - `text`: The text message to send
@@ -382,13 +1225,124 @@ where
     ///     });
     /// }
     /// ```
-    pub async fn send_text<S>(&self, text: S) -> Result<(), Box<dyn std::error::Error>>
+    pub async fn send_text<S>(&self, text: S) -> Result<(), WyndError>
+    where
+        S: Into<String>,
+    {
+        self.send_text_counted(text).await.map(|_| ())
+    }
+
+    /// Sends a text message to this client only, looked up through the
+    /// server's client registry like a broadcast.
+    ///
+    /// Completes the broadcast matrix: [`Broadcaster::text`] reaches every
+    /// client except this one, [`Broadcaster::emit_text`] reaches every
+    /// client including this one, and this method reaches only this one.
+    /// The result is the same as [`send_text`](Self::send_text), except that
+    /// a connection already removed from the registry is reported as
+    /// [`WyndError::ConnectionClosed`].
+    ///
+    /// ## Parameters
+    ///
+    /// - `text`: The text message to send
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if the message was sent, or an error if this
+    /// connection is no longer registered or the send failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         handle.broadcast.text(&msg.data).await;
+    ///         let _ = handle.broadcast_to_self("delivered").await;
+    ///     });
+    /// });
+    /// ```
+    pub async fn broadcast_to_self<S>(&self, text: S) -> Result<(), WyndError>
+    where
+        S: Into<String>,
+    {
+        let handle = {
+            let clients = self.broadcast.clients.lock().await;
+            clients.get(&self.id).map(|(_, h)| Arc::clone(h))
+        };
+        match handle {
+            Some(handle) => handle.send_text(text).await,
+            None => Err(WyndError::ConnectionClosed),
+        }
+    }
+
+    /// Sends a text message to the client and reports its size.
+    ///
+    /// Behaves like [`send_text`](Self::send_text), but returns the length of
+    /// the frame payload that was written, which is useful for per-connection
+    /// byte accounting. If an outbound transform is set, the length is that
+    /// of the transformed message.
+    ///
+    /// ## Parameters
+    ///
+    /// - `text`: The text message to send
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of payload bytes sent, or an error if the send
+    /// operation failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if let Ok(sent) = handle.send_text_counted(msg.data).await {
+    ///             println!("Echoed {} bytes", sent);
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub async fn send_text_counted<S>(&self, text: S) -> Result<usize, WyndError>
     where
         S: Into<String>,
     {
         let text = text.into();
-        let mut writer = self.writer.lock().await;
-        futures::SinkExt::send(&mut *writer, Message::Text(text.into())).await?;
+        let message = self.outbound_transform.apply(Message::Text(text.into()));
+        let len = message.len();
+        if let Some(ref queue) = self.send_queue {
+            self.track(self.enqueue(queue, vec![message]))?;
+            return Ok(len);
+        }
+        let mut writer = self.lock_writer().await;
+        self.track(futures::SinkExt::send(&mut *writer, message).await)?;
+        Ok(len)
+    }
+
+    /// Sends several text messages while holding the writer, so no other
+    /// send on this connection can land between them.
+    pub(crate) async fn send_text_sequence(&self, texts: &[String]) -> Result<(), WyndError> {
+        let messages: Vec<Message> = texts
+            .iter()
+            .map(|text| {
+                self.outbound_transform
+                    .apply(Message::Text(text.clone().into()))
+            })
+            .collect();
+        if let Some(ref queue) = self.send_queue {
+            return self.track(self.enqueue(queue, messages));
+        }
+        let mut writer = self.lock_writer().await;
+        for message in messages {
+            self.track(futures::SinkExt::send(&mut *writer, message).await)?;
+        }
         Ok(())
     }
 
@@ -397,25 +1351,30 @@ where
     /// Enqueues a request to add this connection to a room, enabling
     /// room-wide broadcast delivery to this client.
     ///
-    /// - `room`: The target room name.
+    /// - `room`: The target room name, which may be built at runtime.
     ///
-    /// Returns `Ok(())` if the join request was sent, otherwise an error.
-    pub async fn join(&self, room: &'static str) -> Result<(), Box<dyn std::error::Error>> {
+    /// Returns `Ok(())` once the room processor has added the connection,
+    /// [`RoomError::CapacityExceeded`] if the room would be new and the
+    /// server is at its [`set_max_rooms`](crate::wynd::Wynd::set_max_rooms)
+    /// limit, or [`RoomError::ChannelClosed`] if the processor is not running,
+    /// e.g. because the server has not been started with
+    /// [`listen`](crate::wynd::Wynd::listen) yet.
+    pub async fn join<S>(&self, room: S) -> Result<(), RoomError>
+    where
+        S: Into<String>,
+    {
+        let (tx, rx) = oneshot::channel();
+
         self.room_sender
             .send(RoomEvents::JoinRoom {
                 client_id: self.id,
                 handle: self.clone(),
-                room_name: room,
+                room_name: room.into(),
+                ack: tx,
             })
-            .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to join room: {}", e),
-                )
-            })?;
+            .await?;
 
-        Ok(())
+        rx.await?
     }
 
     /// Leaves the specified room.
@@ -426,115 +1385,493 @@ where
     /// - `room`: The target room name.
     ///
     /// Returns `Ok(())` if the leave request was sent, otherwise an error.
-    pub async fn leave(&self, room: &'static str) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn leave<S>(&self, room: S) -> Result<(), RoomError>
+    where
+        S: Into<String>,
+    {
         self.room_sender
             .send(RoomEvents::LeaveRoom {
                 client_id: self.id,
-                room_name: room,
+                room_name: room.into(),
             })
-            .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to leave room: {}", e),
-                )
-            })?;
+            .await?;
 
         Ok(())
     }
 
-    /// Returns a [`RoomMethods`] instance for sending messages to a specific room.
+    /// Moves this connection from one room to another in a single step.
     ///
-    /// This allows you to send text or binary messages to all clients in the given room,
-    /// either including or excluding yourself (the sender), using the methods on [`RoomMethods`].
+    /// The leave and the join are applied together by the room processor, so
+    /// no room event ever sees the connection in neither room or in both.
+    /// Use this when rooms represent exclusive states, e.g. lobby → game.
+    /// The destination room's history, if enabled, is replayed as for [`join`](Self::join).
     ///
-    /// # Arguments
+    /// ## Parameters
     ///
-    /// * `room_name` - The name of the target room.
+    /// - `from`: The room to leave
+    /// - `to`: The room to join
     ///
-    /// # Returns
+    /// ## Returns
     ///
-    /// A [`RoomMethods`] object bound to the specified room and this connection.
+    /// Returns `Ok(())` once the room processor has moved the connection,
+    /// [`RoomError::CapacityExceeded`] if `to` would be a new room and the
+    /// server is at its [`set_max_rooms`](crate::wynd::Wynd::set_max_rooms)
+    /// limit even after the connection leaves `from`, in which case the
+    /// connection stays in `from`, or
+    /// [`RoomError::ChannelClosed`] if the processor is not running.
     ///
-    /// # Example
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
     ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_text(|msg, handle| async move {
+    ///             if msg.data == "start" {
+    ///                 let _ = handle.move_to("lobby", "game").await;
+    ///             }
+    ///         });
+    ///     });
+    /// }
     /// ```
-    /// use wynd::handle::ConnectionHandle;
-    /// use tokio::net::TcpStream;
+    pub async fn move_to<F, S>(&self, from: F, to: S) -> Result<(), RoomError>
+    where
+        F: Into<String>,
+        S: Into<String>,
+    {
+        let (tx, rx) = oneshot::channel();
+
+        self.room_sender
+            .send(RoomEvents::MoveRoom {
+                client_id: self.id,
+                handle: self.clone(),
+                from: from.into(),
+                to: to.into(),
+                ack: tx,
+            })
+            .await?;
+
+        rx.await?
+    }
+
+    /// Adds this connection to a server-side group.
     ///
-    /// async fn test(handle: &ConnectionHandle<TcpStream>) {
-    ///     handle.to("my_room").text("Hello, room!").await.unwrap();
-    /// };
+    /// Groups are pure routing primitives: nothing is sent to the client when
+    /// it is added to or removed from one. Use
+    /// [`Wynd::group_broadcast`](crate::wynd::Wynd::group_broadcast) to send
+    /// to every member of a group.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         handle.add_to_group("eu-west").await;
+    ///     })
+    ///     .await;
+    /// });
     /// ```
-    pub fn to(&'_ self, room_name: &'static str) -> RoomMethods<'_, T> {
-        RoomMethods {
-            room_name: room_name,
-            id: self.id,
-            room_sender: Arc::new(&self.room_sender),
+    pub async fn add_to_group(&self, group: impl Into<GroupId>) {
+        let mut groups = self.groups.lock().await;
+        groups.entry(group.into()).or_default().insert(self.id);
+    }
+
+    /// Removes this connection from a server-side group.
+    ///
+    /// Empty groups are dropped once their last member is removed.
+    pub async fn remove_from_group(&self, group: impl Into<GroupId>) {
+        let group = group.into();
+        let mut groups = self.groups.lock().await;
+        if let Some(members) = groups.get_mut(&group) {
+            members.remove(&self.id);
+            if members.is_empty() {
+                groups.remove(&group);
+            }
         }
     }
 
-    /// Sends binary data to the client.
+    /// Attaches a value to this connection, keyed by its type.
     ///
-    /// This method sends binary data to the WebSocket client.
-    /// The data is sent asynchronously and the method returns immediately.
+    /// Every handler of the connection sees the same values, so state such
+    /// as an authenticated user or a session can be set once, e.g. in
+    /// `on_open`, and read in later `on_text` calls with
+    /// [`data`](Self::data) instead of being captured in each closure.
+    /// Storing another value of the same type replaces the previous one; use
+    /// a newtype per piece of state.
     ///
     /// ## Parameters
     ///
-    /// - `data`: The binary data to send
-    ///
-    /// ## Returns
-    ///
-    /// Returns `Ok(())` if the data was sent successfully, or an error
-    /// if the send operation failed.
+    /// - `value`: The value to store
     ///
     /// ## Example
     ///
     /// ```rust
     /// use wynd::wynd::{Wynd, Standalone};
     ///
-    /// #[tokio::main]
-    /// async fn main() {
-    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// #[derive(Clone)]
+    /// struct UserId(u64);
     ///
-    ///     wynd.on_connection(|conn| async move {
-    ///         conn.on_open(|handle| async move {
-    ///             // Send some binary data
-    ///             let data = vec![1, 2, 3, 4, 5];
-    ///             let _ = handle.send_binary(data).await;
-    ///         })
-    ///         .await;
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
     ///
-    ///         conn.on_binary(|msg, handle| async move {
-    ///             // Echo the binary data back
-    ///             let _ = handle.send_binary(msg.data).await;
-    ///         });
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         handle.set_data(UserId(42));
+    ///     })
+    ///     .await;
+    ///
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if let Some(UserId(user)) = handle.data::<UserId>() {
+    ///             println!("user {} said {}", user, msg.data);
+    ///         }
     ///     });
-    /// }
+    /// });
     /// ```
-    pub async fn send_binary(&self, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
-        let mut writer = self.writer.lock().await;
-        futures::SinkExt::send(&mut *writer, Message::Binary(data.into())).await?;
-        Ok(())
+    pub fn set_data<D>(&self, value: D)
+    where
+        D: Any + Send,
+    {
+        self.data.set(value);
     }
 
-    /// Closes the WebSocket connection gracefully.
-    ///
-    /// This method sends a close frame to the client and initiates
-    /// a graceful shutdown of the WebSocket connection.
+    /// Returns a copy of the value of type `D` attached with
+    /// [`set_data`](Self::set_data).
     ///
     /// ## Returns
     ///
-    /// Returns `Ok(())` if the close frame was sent successfully, or an error
-    /// if the send operation failed.
+    /// Returns `None` if no value of that type was stored.
+    pub fn data<D>(&self) -> Option<D>
+    where
+        D: Any + Send + Clone,
+    {
+        self.data.get()
+    }
+
+    /// Associates this connection with a user id.
+    ///
+    /// A user may have several connections at once (devices, tabs); all of
+    /// them can be reached with [`Wynd::send_to_user`](crate::wynd::Wynd::send_to_user).
+    /// Calling this again moves the connection to the new user id.
     ///
     /// ## Example
     ///
     /// ```rust
     /// use wynd::wynd::{Wynd, Standalone};
     ///
-    /// #[tokio::main]
-    /// async fn main() {
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         handle.set_user_id(msg.data).await;
+    ///     });
+    /// });
+    /// ```
+    pub async fn set_user_id(&self, uid: impl Into<String>) {
+        let mut users = self.users.lock().await;
+        for connections in users.values_mut() {
+            connections.remove(&self.id);
+        }
+        users.retain(|_, connections| !connections.is_empty());
+        users.entry(uid.into()).or_default().insert(self.id);
+    }
+
+    /// Tags this connection, e.g. with its client platform.
+    ///
+    /// Tags are labels for observability: [`Wynd::tag_counts`](crate::wynd::Wynd::tag_counts)
+    /// reports how many connections carry each one. A connection can carry
+    /// any number of tags and loses all of them when it disconnects. Adding a
+    /// tag twice has no effect.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     let platform = conn.query_param::<String>("platform");
+    ///     conn.on_open(move |handle| {
+    ///         let platform = platform.clone();
+    ///         async move {
+    ///             handle.add_tag(platform.unwrap_or_else(|| "web".to_string())).await;
+    ///         }
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub async fn add_tag(&self, tag: impl Into<String>) {
+        let mut tags = self.tags.lock().await;
+        tags.entry(tag.into()).or_default().insert(self.id);
+    }
+
+    /// Removes a tag from this connection.
+    ///
+    /// Tags no connection carries any more are dropped from the counts.
+    pub async fn remove_tag(&self, tag: &str) {
+        let mut tags = self.tags.lock().await;
+        if let Some(connections) = tags.get_mut(tag) {
+            connections.remove(&self.id);
+            if connections.is_empty() {
+                tags.remove(tag);
+            }
+        }
+    }
+
+    /// Returns a [`RoomMethods`] instance for sending messages to a specific room.
+    ///
+    /// This allows you to send text or binary messages to all clients in the given room,
+    /// either including or excluding yourself (the sender), using the methods on [`RoomMethods`].
+    ///
+    /// # Arguments
+    ///
+    /// * `room_name` - The name of the target room.
+    ///
+    /// # Returns
+    ///
+    /// A [`RoomMethods`] object bound to the specified room and this connection.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wynd::handle::ConnectionHandle;
+    /// use tokio::net::TcpStream;
+    ///
+    /// async fn test(handle: &ConnectionHandle<TcpStream>) {
+    ///     handle.to("my_room").text("Hello, room!").await.unwrap();
+    /// };
+    /// ```
+    pub fn to<S>(&'_ self, room_name: S) -> RoomMethods<'_, T>
+    where
+        S: Into<String>,
+    {
+        RoomMethods {
+            room_name: room_name.into(),
+            id: self.id,
+            room_sender: &self.room_sender,
+            membership_check: self.require_room_membership.then_some(self),
+        }
+    }
+
+    /// Sends binary data to the client.
+    ///
+    /// This method sends binary data to the WebSocket client.
+    /// The data is sent asynchronously and the method returns immediately.
+    ///
+    /// ## Parameters
+    ///
+    /// - `data`: The binary data to send
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if the data was sent successfully, or an error
+    /// if the send operation failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_open(|handle| async move {
+    ///             // Send some binary data
+    ///             let data = vec![1, 2, 3, 4, 5];
+    ///             let _ = handle.send_binary(data).await;
+    ///         })
+    ///         .await;
+    ///
+    ///         conn.on_binary(|msg, handle| async move {
+    ///             // Echo the binary data back
+    ///             let _ = handle.send_binary(msg.data).await;
+    ///         });
+    ///     });
+    /// }
+    /// ```
+    pub async fn send_binary(&self, data: Vec<u8>) -> Result<(), WyndError> {
+        let message = self.outbound_transform.apply(Message::Binary(data.into()));
+        if let Some(ref queue) = self.send_queue {
+            return self.track(self.enqueue(queue, vec![message]));
+        }
+        let mut writer = self.lock_writer().await;
+        self.track(futures::SinkExt::send(&mut *writer, message).await)?;
+        Ok(())
+    }
+
+    /// Sends several text messages, taking the writer only once.
+    ///
+    /// The writer is locked once for the whole batch, which avoids per-message
+    /// lock churn when pushing a burst such as chat history on connect. No
+    /// other send on this connection can land between the frames. With a send
+    /// queue the whole batch is queued at once or not at all.
+    ///
+    /// ## Parameters
+    ///
+    /// - `msgs`: The text messages to send, in order
+    ///
+    /// ## Returns
+    ///
+    /// Returns a [`BatchSendError`] reporting how many frames were written
+    /// if the batch stops early.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         let history = ["alice: hi", "bob: hello", "alice: how are you?"];
+    ///         let _ = handle.send_many_text(&history).await;
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub async fn send_many_text(&self, msgs: &[&str]) -> Result<(), BatchSendError> {
+        let messages = msgs
+            .iter()
+            .map(|text| self.outbound_transform.apply(Message::Text((*text).into())))
+            .collect();
+        self.send_many(messages).await
+    }
+
+    /// Sends several binary messages, taking the writer only once.
+    ///
+    /// Behaves like [`send_many_text`](Self::send_many_text) for binary frames.
+    ///
+    /// ## Parameters
+    ///
+    /// - `frames`: The binary payloads to send, in order
+    ///
+    /// ## Returns
+    ///
+    /// Returns a [`BatchSendError`] reporting how many frames were written
+    /// if the batch stops early.
+    pub async fn send_many_binary(&self, frames: Vec<Vec<u8>>) -> Result<(), BatchSendError> {
+        let messages = frames
+            .into_iter()
+            .map(|data| self.outbound_transform.apply(Message::Binary(data.into())))
+            .collect();
+        self.send_many(messages).await
+    }
+
+    /// Writes already transformed frames under one writer lock.
+    ///
+    /// Each frame is flushed before the next, so a failure reports exactly
+    /// how many frames were written: the sink defers write errors to the next
+    /// flush, so counting frames that were only fed would overstate it.
+    async fn send_many(&self, messages: Vec<Message>) -> Result<(), BatchSendError> {
+        if let Some(ref queue) = self.send_queue {
+            return self
+                .track(self.enqueue(queue, messages))
+                .map_err(|e| BatchSendError::new(0, e));
+        }
+
+        let mut writer = self.lock_writer().await;
+        for (sent, message) in messages.into_iter().enumerate() {
+            self.track(futures::SinkExt::send(&mut *writer, message).await)
+                .map_err(|e| BatchSendError::new(sent, e))?;
+        }
+        Ok(())
+    }
+
+    /// Sends a ping frame to the client.
+    ///
+    /// Compliant clients answer with a pong carrying the same payload. Use it
+    /// for application-level keepalive, e.g. pinging idle clients on a timer;
+    /// [`Wynd::set_keepalive`](crate::wynd::Wynd::set_keepalive) does this
+    /// automatically for every connection.
+    ///
+    /// ## Parameters
+    ///
+    /// - `payload`: The ping payload, at most [`MAX_CONTROL_PAYLOAD`] bytes
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`WyndError::InvalidPayload`] without sending anything if the
+    /// payload is too long, or an error if the send fails.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         tokio::spawn(async move {
+    ///             loop {
+    ///                 tokio::time::sleep(Duration::from_secs(30)).await;
+    ///                 if handle.last_activity().elapsed() > Duration::from_secs(30)
+    ///                     && handle.send_ping(b"idle?".to_vec()).await.is_err()
+    ///                 {
+    ///                     break;
+    ///                 }
+    ///             }
+    ///         });
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub async fn send_ping(&self, payload: Vec<u8>) -> Result<(), WyndError> {
+        check_control_payload("ping", &payload)?;
+        let mut writer = self.lock_writer().await;
+        self.track(futures::SinkExt::send(&mut *writer, Message::Ping(payload.into())).await)?;
+        Ok(())
+    }
+
+    /// Sends a pong frame to the client.
+    ///
+    /// Used to answer pings manually from an `on_ping` handler after
+    /// `Wynd::disable_auto_pong`, or to send an unsolicited pong as a
+    /// one-way heartbeat.
+    ///
+    /// ## Parameters
+    ///
+    /// - `payload`: The pong payload, at most [`MAX_CONTROL_PAYLOAD`] bytes
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`WyndError::InvalidPayload`] without sending anything if the
+    /// payload is too long, or an error if the send fails.
+    pub async fn send_pong(&self, payload: Vec<u8>) -> Result<(), WyndError> {
+        check_control_payload("pong", &payload)?;
+        let mut writer = self.lock_writer().await;
+        self.track(futures::SinkExt::send(&mut *writer, Message::Pong(payload.into())).await)?;
+        Ok(())
+    }
+
+    /// Closes the WebSocket connection gracefully.
+    ///
+    /// This method sends a close frame to the client and initiates
+    /// a graceful shutdown of the WebSocket connection.
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if the close frame was sent successfully, or an error
+    /// if the send operation failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
     ///     let mut wynd: Wynd<Standalone> = Wynd::new();
     ///
     ///     wynd.on_connection(|conn| async move {
@@ -547,7 +1884,7 @@ where
     ///             match msg.data.as_str() {
     ///                 "quit" => {
     ///                     println!("Client requested disconnect");
-    ///                     let _ = handle.close().await;
+    ///                     let _ = handle.close_with(1000, "bye").await;
     ///                 }
     ///                 _ => {
     ///                     let _ = handle.send_text(&format!("Echo: {}", msg.data)).await;
@@ -557,18 +1894,280 @@ where
     ///     });
     /// }
     /// ```
-    pub async fn close(&self) -> Result<(), Box<dyn std::error::Error>> {
+    pub async fn close(&self) -> Result<(), WyndError> {
+        {
+            let mut s = self.state.lock().await;
+            *s = ConnState::CLOSING;
+        }
+        self.flush_send_queue().await;
+        let mut writer = self.lock_writer().await;
+        self.track(futures::SinkExt::send(&mut *writer, Message::Close(None)).await)?;
+        Ok(())
+    }
+
+    /// Closes the connection with a close code and reason.
+    ///
+    /// Unlike [`close`](Self::close), which sends an empty close frame, the
+    /// client learns why it was disconnected.
+    ///
+    /// ## Parameters
+    ///
+    /// - `code`: The close code: `1000`, `1001`, `1003`, `1008`, `1011`,
+    ///   `1012`, `1013`, or a registered or application code (`3000`–`4999`)
+    /// - `reason`: The close reason, at most 123 bytes
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`WyndError::InvalidPayload`] without sending anything if the
+    /// code is not one of the above or the reason is too long, or an error if
+    /// the send fails.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if msg.data == "quit" {
+    ///             let _ = handle.close_with(1000, "bye").await;
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub async fn close_with<S>(&self, code: u16, reason: S) -> Result<(), WyndError>
+    where
+        S: Into<String>,
+    {
+        let reason = reason.into();
+        check_close_frame(code, &reason)?;
+        self.send_close_frame(CloseCode::from(code), &reason).await
+    }
+
+    /// Sends a close frame with the given code and reason.
+    pub(crate) async fn send_close_frame(
+        &self,
+        code: CloseCode,
+        reason: &str,
+    ) -> Result<(), WyndError> {
+        {
+            let mut s = self.state.lock().await;
+            *s = ConnState::CLOSING;
+        }
+        self.flush_send_queue().await;
+        let mut writer = self.lock_writer().await;
+        self.track(
+            futures::SinkExt::send(
+                &mut *writer,
+                Message::Close(Some(CloseFrame {
+                    code,
+                    reason: reason.into(),
+                })),
+            )
+            .await,
+        )?;
+        Ok(())
+    }
+
+    /// Closes the connection after delivering messages that are already queued.
+    ///
+    /// Sends that were waiting on the connection's writer when this method was
+    /// called are written and flushed before the close frame, so a final
+    /// "server shutting down" message is not dropped by the close. Unlike
+    /// [`ConnectionHandle::close`], which races with pending sends, the
+    /// close frame is always the last frame written.
+    ///
+    /// ## Parameters
+    ///
+    /// - `timeout`: Upper bound on flushing pending messages and sending the close frame
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` once the close frame was sent, or an error if sending
+    /// failed or did not complete within `timeout`.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_text(|msg, handle| async move {
+    ///             if msg.data == "quit" {
+    ///                 let _ = handle.send_text("Goodbye!").await;
+    ///                 let _ = handle.close_flush(Duration::from_secs(1)).await;
+    ///             }
+    ///         });
+    ///     });
+    /// }
+    /// ```
+    pub async fn close_flush(&self, timeout: Duration) -> Result<(), WyndError> {
+        {
+            let mut s = self.state.lock().await;
+            *s = ConnState::CLOSING;
+        }
+
+        // The writer mutex is fair, so every send already waiting on it is
+        // written before this call gets its turn.
+        let result = tokio::time::timeout(timeout, async {
+            self.flush_send_queue().await;
+            let mut writer = self.lock_writer().await;
+            futures::SinkExt::flush(&mut *writer).await?;
+            futures::SinkExt::send(&mut *writer, Message::Close(None)).await
+        })
+        .await;
+
+        match result {
+            Ok(sent) => Ok(sent?),
+            Err(_) => Err(WyndError::write_failed(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "Timed out flushing messages before close",
+            ))),
+        }
+    }
+    /// Sends a final text message, then closes the connection.
+    ///
+    /// Both frames are written under a single acquisition of the writer, so
+    /// no other send can slip between them: the client always receives the
+    /// text immediately followed by the close frame, e.g. "kicked: spamming"
+    /// and then the disconnect.
+    ///
+    /// ## Parameters
+    ///
+    /// - `text`: The final text message
+    /// - `code`: The close code, e.g. `1000` or an application code (`4000`–`4999`);
+    ///   the same codes as [`close_with`](Self::close_with) are accepted
+    /// - `reason`: The close reason, at most 123 bytes
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if both frames were sent. Returns
+    /// [`WyndError::InvalidPayload`] without sending anything if the code
+    /// may not be sent or the reason is too long, or an error if either send
+    /// failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_text(|msg, handle| async move {
+    ///             if msg.data.contains("spam") {
+    ///                 let _ = handle
+    ///                     .send_text_and_close("kicked: spamming", 4001, "kicked")
+    ///                     .await;
+    ///             }
+    ///         });
+    ///     });
+    /// }
+    /// ```
+    pub async fn send_text_and_close<S>(
+        &self,
+        text: S,
+        code: u16,
+        reason: &str,
+    ) -> Result<(), WyndError>
+    where
+        S: Into<String>,
+    {
+        check_close_frame(code, reason)?;
         {
             let mut s = self.state.lock().await;
             *s = ConnState::CLOSING;
         }
-        let mut writer = self.writer.lock().await;
-        futures::SinkExt::send(&mut *writer, Message::Close(None)).await?;
+
+        let text = text.into();
+        let message = self.outbound_transform.apply(Message::Text(text.into()));
+        self.flush_send_queue().await;
+        let mut writer = self.lock_writer().await;
+        self.track(futures::SinkExt::send(&mut *writer, message).await)?;
+        self.track(
+            futures::SinkExt::send(
+                &mut *writer,
+                Message::Close(Some(CloseFrame {
+                    code: CloseCode::from(code),
+                    reason: reason.into(),
+                })),
+            )
+            .await,
+        )?;
         Ok(())
     }
+
+    /// Asks the client to reconnect after a delay, then closes the connection.
+    ///
+    /// Sends `{"type":"reconnect","delay_ms":...,"reason":"..."}` followed by
+    /// a close frame with code `1012` (service restart), e.g. to move clients
+    /// to other nodes while scaling. Clients should wait `delay_ms` before
+    /// reconnecting. The close reason is the given reason, truncated to fit a
+    /// close frame.
+    ///
+    /// ## Parameters
+    ///
+    /// - `delay_ms`: How long the client should wait before reconnecting
+    /// - `reason`: Why the client is asked to reconnect
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if both frames were sent, or an error if either send failed.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if msg.data == "drain" {
+    ///             let _ = handle.request_reconnect(5_000, "rebalancing").await;
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    #[cfg(feature = "json")]
+    pub async fn request_reconnect(&self, delay_ms: u32, reason: &str) -> Result<(), WyndError> {
+        let hint = serde_json::json!({
+            "type": "reconnect",
+            "delay_ms": delay_ms,
+            "reason": reason,
+        });
+        let mut end = reason.len().min(CloseEvent::MAX_REASON_LEN);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.send_text_and_close(hint.to_string(), 1012, &reason[..end])
+            .await
+    }
 }
 
 /// A helper to broadcast messages to all connected clients.
+///
+/// Methods differ in whether the sending connection gets its own message:
+///
+/// | Method | Other clients | Sender |
+/// |--------|---------------|--------|
+/// | [`text`](Self::text) / [`binary`](Self::binary) | yes | no |
+/// | [`emit_text`](Self::emit_text) / [`emit_binary`](Self::emit_binary) | yes | yes |
+/// | [`ConnectionHandle::broadcast_to_self`] | no | yes |
+///
+/// When the server has a broadcast coalescing window configured (see
+/// [`Wynd::set_broadcast_coalesce`](crate::wynd::Wynd::set_broadcast_coalesce)),
+/// rapid broadcasts are buffered and only the latest payload per target is sent
+/// once the window elapses.
 #[derive(Debug)]
 pub struct Broadcaster<T>
 where
@@ -577,6 +2176,10 @@ where
     pub(crate) current_client_id: u64,
     /// Shared registry of all active connections and their handles.
     pub(crate) clients: ClientRegistry<T>,
+    /// Shared coalescer for rapid broadcasts, if enabled on the server.
+    pub(crate) coalescer: Option<Arc<BroadcastCoalescer>>,
+    /// Sends between yields to the scheduler while broadcasting; `0` never yields.
+    pub(crate) yield_every: usize,
 }
 
 impl<T> Clone for Broadcaster<T>
@@ -587,32 +2190,145 @@ where
         Self {
             current_client_id: self.current_client_id,
             clients: self.clients.clone(),
+            coalescer: self.coalescer.clone(),
+            yield_every: self.yield_every,
         }
     }
 }
 
+/// Payload of a single broadcast.
+#[derive(Debug, Clone)]
+pub(crate) enum BroadcastPayload {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Identifies a broadcast target whose rapid broadcasts are coalesced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CoalesceKey {
+    sender: u64,
+    include_sender: bool,
+    binary: bool,
+}
+
+/// Buffers rapid broadcasts so only the latest payload per target is sent.
+///
+/// The first broadcast to a target starts a debounce task that waits for the
+/// window to elapse; broadcasts to the same target in the meantime replace the
+/// buffered payload, and the task then sends whatever payload is current.
+#[derive(Debug)]
+pub(crate) struct BroadcastCoalescer {
+    window: Duration,
+    pending: std::sync::Mutex<HashMap<CoalesceKey, BroadcastPayload>>,
+}
+
+impl BroadcastCoalescer {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Buffers a broadcast, starting a debounce task for its target if needed.
+    fn submit<T>(
+        self: &Arc<Self>,
+        broadcaster: &Broadcaster<T>,
+        payload: BroadcastPayload,
+        include_sender: bool,
+    ) where
+        T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+    {
+        let key = CoalesceKey {
+            sender: broadcaster.current_client_id,
+            include_sender,
+            binary: matches!(payload, BroadcastPayload::Binary(_)),
+        };
+
+        let first_in_window = {
+            let mut pending = self
+                .pending
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            pending.insert(key, payload).is_none()
+        };
+        if !first_in_window {
+            return;
+        }
+
+        let coalescer = Arc::clone(self);
+        let broadcaster = broadcaster.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(coalescer.window).await;
+            let payload = coalescer
+                .pending
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .remove(&key);
+            if let Some(payload) = payload {
+                broadcaster.deliver(payload, include_sender).await;
+            }
+        });
+    }
+}
+
 impl<T> Broadcaster<T>
 where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
+    /// Creates a broadcaster for the given connection without coalescing.
+    pub(crate) fn new(clients: ClientRegistry<T>, current_client_id: u64) -> Self {
+        Self {
+            current_client_id,
+            clients,
+            coalescer: None,
+            yield_every: DEFAULT_BROADCAST_YIELD_INTERVAL,
+        }
+    }
+
     /// Broadcast a UTF-8 text message to every connected client except the current one.
     pub async fn text<S>(&self, text: S)
     where
         S: Into<String>,
     {
-        let payload: String = text.into();
-        let recipients: Vec<Arc<ConnectionHandle<T>>> = {
-            let clients = self.clients.lock().await;
-            clients
-                .iter()
-                .filter_map(|(_, h)| (h.0.id() != self.current_client_id).then(|| Arc::clone(&h.1)))
-                .collect()
-        };
-        for h in recipients {
-            if let Err(e) = h.send_text(payload.clone()).await {
-                eprintln!("Failed to broadcast to client {}: {}", h.id(), e);
-            }
-        }
+        self.dispatch(BroadcastPayload::Text(text.into()), false)
+            .await;
+    }
+
+    /// Broadcast a UTF-8 text message that must not be missed to every
+    /// connected client except the current one.
+    ///
+    /// Clients whose reads are paused (see
+    /// [`ConnectionHandle::pause_reads`]) get the message buffered and
+    /// replayed, in order, when they resume. Each connection buffers at most
+    /// [`IMPORTANT_BACKLOG_LIMIT`] messages; beyond that the oldest is
+    /// dropped. Important broadcasts are never coalesced.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         handle.broadcast.text_important(format!("order filled: {}", msg.data)).await;
+    ///     });
+    /// });
+    /// ```
+    pub async fn text_important<S>(&self, text: S)
+    where
+        S: Into<String>,
+    {
+        let text = text.into();
+        let direct: Vec<Arc<ConnectionHandle<T>>> = self
+            .recipients(false)
+            .await
+            .into_iter()
+            .filter(|h| !h.queue_important(&text))
+            .collect();
+        deliver_to(&direct, &BroadcastPayload::Text(text), self.yield_every).await;
     }
 
     /// Broadcast a UTF-8 text message to every connected client.
@@ -620,17 +2336,8 @@ where
     where
         S: Into<String>,
     {
-        let payload: String = text.into();
-
-        let recipients: Vec<Arc<ConnectionHandle<T>>> = {
-            let clients = self.clients.lock().await;
-            clients.iter().map(|(_, h)| Arc::clone(&h.1)).collect()
-        };
-        for h in recipients {
-            if let Err(e) = h.send_text(payload.clone()).await {
-                eprintln!("Failed to broadcast to client {}: {}", h.id(), e);
-            }
-        }
+        self.dispatch(BroadcastPayload::Text(text.into()), true)
+            .await;
     }
 
     /// Broadcast a binary message to every connected client.
@@ -638,16 +2345,8 @@ where
     where
         B: Into<Vec<u8>>,
     {
-        let payload = bytes.into();
-        let recipients: Vec<Arc<ConnectionHandle<T>>> = {
-            let clients = self.clients.lock().await;
-            clients.iter().map(|(_, h)| Arc::clone(&h.1)).collect()
-        };
-        for h in recipients {
-            if let Err(e) = h.send_binary(payload.clone()).await {
-                eprintln!("Failed to broadcast to client {}: {}", h.id(), e);
-            }
-        }
+        self.dispatch(BroadcastPayload::Binary(bytes.into()), true)
+            .await;
     }
 
     /// Broadcast a binary message to every connected client except the current one.
@@ -655,18 +2354,100 @@ where
     where
         B: Into<Vec<u8>>,
     {
-        let payload = bytes.into();
-        let recipients: Vec<Arc<ConnectionHandle<T>>> = {
-            let clients = self.clients.lock().await;
-            clients
-                .iter()
-                .filter_map(|(_, h)| (h.0.id() != self.current_client_id).then(|| Arc::clone(&h.1)))
-                .collect()
+        self.dispatch(BroadcastPayload::Binary(bytes.into()), false)
+            .await;
+    }
+
+    /// Sends a broadcast now, or buffers it when coalescing is enabled.
+    async fn dispatch(&self, payload: BroadcastPayload, include_sender: bool) {
+        match self.coalescer {
+            Some(ref coalescer) => coalescer.submit(self, payload, include_sender),
+            None => self.deliver(payload, include_sender).await,
+        }
+    }
+
+    /// Sends a payload to every connected client, optionally skipping the sender.
+    async fn deliver(&self, payload: BroadcastPayload, include_sender: bool) {
+        let recipients = self.recipients(include_sender).await;
+        deliver_to(&recipients, &payload, self.yield_every).await;
+    }
+
+    /// Snapshots the handles of every connected client, optionally skipping the sender.
+    async fn recipients(&self, include_sender: bool) -> Vec<Arc<ConnectionHandle<T>>> {
+        let clients = self.clients.lock().await;
+        clients
+            .values()
+            .filter(|&h| include_sender || h.0.id() != self.current_client_id)
+            .map(|h| Arc::clone(&h.1))
+            .collect()
+    }
+}
+
+/// Locks `writer`, adding to `contention` if the lock had to be waited for.
+async fn lock_counted<'a, T>(
+    writer: &'a tokio::sync::Mutex<futures::stream::SplitSink<WebSocketStream<T>, Message>>,
+    contention: &AtomicU64,
+) -> tokio::sync::MutexGuard<'a, futures::stream::SplitSink<WebSocketStream<T>, Message>>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    match writer.try_lock() {
+        Ok(writer) => writer,
+        Err(_) => {
+            contention.fetch_add(1, Ordering::Relaxed);
+            writer.lock().await
+        }
+    }
+}
+
+/// Sends a broadcast payload to each recipient.
+///
+/// Recipients are snapshotted before sending, so a client may disconnect in
+/// between; errors caused by that are expected churn and skipped silently.
+/// Any other send error is logged.
+///
+/// Returns the number of failures that were logged.
+pub(crate) async fn deliver_to<T>(
+    recipients: &[Arc<ConnectionHandle<T>>],
+    payload: &BroadcastPayload,
+    yield_every: usize,
+) -> usize
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    let mut failures = 0;
+    for (sent, h) in recipients.iter().enumerate() {
+        yield_periodically(sent, yield_every).await;
+        let result = match payload {
+            BroadcastPayload::Text(text) => h.send_text(text.clone()).await,
+            BroadcastPayload::Binary(bytes) => h.send_binary(bytes.clone()).await,
         };
-        for h in recipients {
-            if let Err(e) = h.send_binary(payload.clone()).await {
+        if let Err(e) = result {
+            if !is_disconnect_error(&e) {
                 eprintln!("Failed to broadcast to client {}: {}", h.id(), e);
+                failures += 1;
             }
         }
     }
+    failures
+}
+
+/// Yields to the scheduler before every `every`-th send of a fan-out loop.
+///
+/// Sends to healthy clients rarely wait, so a long loop would otherwise keep
+/// its worker thread busy and starve other tasks. `every == 0` never yields.
+pub(crate) async fn yield_periodically(sent: usize, every: usize) {
+    if every > 0 && sent > 0 && sent.is_multiple_of(every) {
+        tokio::task::yield_now().await;
+    }
+}
+
+/// Returns `true` if a send failed only because the peer has gone away.
+pub(crate) fn is_disconnect_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(error) = error.downcast_ref::<WyndError>() {
+        return matches!(error, WyndError::ConnectionClosed);
+    }
+    error
+        .downcast_ref::<tokio_tungstenite::tungstenite::Error>()
+        .is_some_and(is_connection_gone)
 }
@@ -77,9 +77,12 @@
 //!
 //! MIT License - see LICENSE file for details.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
-use crate::{conn::Connection, handle::ConnectionHandle, wynd::ConnectionId};
+use crate::{conn::Connection, group::GroupId, handle::ConnectionHandle, wynd::ConnectionId};
 
 /// WebSocket connection management and event handling.
 ///
@@ -115,12 +118,46 @@ pub mod handle;
 /// messages to all members in a room.
 pub mod room;
 
+/// Server-side connection groups.
+///
+/// Provides [`group::GroupId`] for routing broadcasts to dynamic cohorts of
+/// connections without any client-visible join/leave semantics.
+pub mod group;
+
+/// Server metrics.
+///
+/// Provides [`metrics::HistogramSnapshot`], returned by metric accessors on
+/// [`wynd::Wynd`].
+pub mod metrics;
+
+/// Connection affinity.
+///
+/// Provides [`affinity::ConnectionAffinity`] for pinning connections' message
+/// loops to dedicated worker threads.
+pub mod affinity;
+
+/// Strongly-typed messaging (enabled with `json`).
+///
+/// Provides the [`typed::Message`] trait and typed wrappers around connections
+/// and handles that (de)serialize messages automatically.
+#[cfg(feature = "json")]
+pub mod typed;
+
 pub(crate) type ClientRegistry<T> =
     Arc<tokio::sync::Mutex<HashMap<ConnectionId, (Arc<Connection<T>>, Arc<ConnectionHandle<T>>)>>>;
+pub(crate) type GroupRegistry = Arc<tokio::sync::Mutex<HashMap<GroupId, HashSet<ConnectionId>>>>;
+pub(crate) type UserRegistry = Arc<tokio::sync::Mutex<HashMap<String, HashSet<ConnectionId>>>>;
+pub(crate) type TagRegistry = Arc<tokio::sync::Mutex<HashMap<String, HashSet<ConnectionId>>>>;
 pub(crate) mod middleware;
 
 pub use middleware::{Middleware, Next};
 
+/// CIDR network type used by the IP allowlist and blocklist on `Wynd`.
+pub use ipnet::IpNet;
+
+/// WebSocket message type passed to outbound transforms.
+pub use tokio_tungstenite::tungstenite::Message;
+
 #[cfg(feature = "with-ripress")]
 /// HTTP context and response builder utilities (enabled with `with-ripress`).
 ///
@@ -0,0 +1,246 @@
+//! Server metrics.
+//!
+//! Metrics are recorded with atomics as connections make progress, so reading
+//! them never blocks the server. [`Wynd::setup_time_histogram`] returns a
+//! [`HistogramSnapshot`] of how long connections took to set up, and
+//! [`Wynd::metrics`] returns a [`WyndMetrics`] snapshot of the server.
+//!
+//! [`Wynd::setup_time_histogram`]: crate::wynd::Wynd::setup_time_histogram
+//! [`Wynd::metrics`]: crate::wynd::Wynd::metrics
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use tokio_tungstenite::tungstenite::Message;
+
+/// Upper bounds of the setup time buckets, in milliseconds.
+pub const SETUP_TIME_BUCKETS_MS: [u64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1_000, 5_000];
+
+/// Histogram of durations with fixed bucket bounds.
+#[derive(Debug)]
+pub(crate) struct Histogram {
+    /// Upper bound of each bucket, in milliseconds.
+    bounds_ms: &'static [u64],
+    /// Count per bucket, with a final bucket for values above every bound.
+    counts: Vec<AtomicU64>,
+    /// Sum of every recorded value, in microseconds.
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub(crate) fn new(bounds_ms: &'static [u64]) -> Self {
+        Self {
+            bounds_ms,
+            counts: (0..=bounds_ms.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Adds a duration to the bucket it falls into.
+    pub(crate) fn record(&self, value: Duration) {
+        let bucket = self
+            .bounds_ms
+            .iter()
+            .position(|&bound| value <= Duration::from_millis(bound))
+            .unwrap_or(self.bounds_ms.len());
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(value.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    /// Copies the current counts.
+    pub(crate) fn snapshot(&self) -> HistogramSnapshot {
+        let counts: Vec<u64> = self
+            .counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        let (overflow, bucketed) = counts.split_last().expect("histogram has a final bucket");
+
+        HistogramSnapshot {
+            buckets: self
+                .bounds_ms
+                .iter()
+                .map(|&bound| Duration::from_millis(bound))
+                .zip(bucketed.iter().copied())
+                .collect(),
+            overflow: *overflow,
+            count: counts.iter().sum(),
+            sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Point-in-time copy of a duration histogram.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::wynd::{Wynd, Standalone};
+///
+/// let wynd: Wynd<Standalone> = Wynd::new();
+///
+/// let setup = wynd.setup_time_histogram();
+/// for (bound, count) in &setup.buckets {
+///     println!("<= {:?}: {}", bound, count);
+/// }
+/// println!("> last bound: {}, mean: {:?}", setup.overflow, setup.mean());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    /// Upper bound of each bucket and how many values fell into it.
+    ///
+    /// Buckets are not cumulative: a value is counted only in the first
+    /// bucket whose bound it does not exceed.
+    pub buckets: Vec<(Duration, u64)>,
+    /// Number of values above the largest bound.
+    pub overflow: u64,
+    /// Number of values recorded.
+    pub count: u64,
+    /// Sum of every value recorded.
+    pub sum: Duration,
+}
+
+impl HistogramSnapshot {
+    /// Returns the average recorded value, or `None` if nothing was recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let micros = self.sum.as_micros() / u128::from(self.count);
+        Some(Duration::from_micros(
+            u64::try_from(micros).unwrap_or(u64::MAX),
+        ))
+    }
+}
+
+/// Server-wide traffic counters, shared by every connection.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    connections_accepted: AtomicU64,
+    handshake_failures: AtomicU64,
+    connections_opened: AtomicU64,
+    messages_received: AtomicU64,
+    bytes_received: AtomicU64,
+    room_messages: AtomicU64,
+    /// When the server started listening, on the monotonic and wall clocks.
+    started_at: OnceLock<(Instant, SystemTime)>,
+}
+
+impl Counters {
+    /// Records that the server started; later calls keep the first time.
+    pub(crate) fn record_start(&self) {
+        self.started_at
+            .get_or_init(|| (Instant::now(), SystemTime::now()));
+    }
+
+    /// Returns the wall-clock time the server started, if it has.
+    pub(crate) fn started_at(&self) -> Option<SystemTime> {
+        self.started_at.get().map(|(_, at)| *at)
+    }
+
+    /// Returns how long the server has been running, if it has started.
+    pub(crate) fn uptime(&self) -> Option<Duration> {
+        self.started_at.get().map(|(at, _)| at.elapsed())
+    }
+
+    /// Counts a connection whose WebSocket handshake succeeded.
+    pub(crate) fn record_accepted(&self) {
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a WebSocket handshake that failed or timed out.
+    pub(crate) fn record_handshake_failure(&self) {
+        self.handshake_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts `n` messages routed through a room.
+    pub(crate) fn record_room_messages(&self, n: usize) {
+        self.room_messages.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// Counts a connection that finished opening.
+    pub(crate) fn record_connection(&self) {
+        self.connections_opened.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts an inbound frame; only text and binary frames count as messages.
+    pub(crate) fn record_received(&self, frame: &Message) {
+        self.bytes_received
+            .fetch_add(frame.len() as u64, Ordering::Relaxed);
+        if matches!(frame, Message::Text(_) | Message::Binary(_)) {
+            self.messages_received.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Point-in-time snapshot of server metrics.
+///
+/// Returned by [`Wynd::metrics`](crate::wynd::Wynd::metrics) and passed to
+/// [`Wynd::on_metrics`](crate::wynd::Wynd::on_metrics) callbacks. Counters
+/// are totals since the server was created, so the difference between two
+/// snapshots is the traffic in between.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::wynd::{Wynd, Standalone};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let wynd: Wynd<Standalone> = Wynd::new();
+///
+///     let metrics = wynd.metrics().await;
+///     println!(
+///         "{} connected, {} messages received",
+///         metrics.connections, metrics.messages_received
+///     );
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WyndMetrics {
+    /// Connections currently registered with the server.
+    pub connections: usize,
+    /// Connections whose WebSocket handshake succeeded.
+    pub connections_accepted: u64,
+    /// WebSocket handshakes that failed or timed out.
+    pub handshake_failures: u64,
+    /// Connections that finished opening, i.e. reached `on_open`.
+    pub connections_opened: u64,
+    /// Text and binary messages received from clients.
+    pub messages_received: u64,
+    /// Bytes received from clients, counting every frame.
+    pub bytes_received: u64,
+    /// Messages routed through rooms, counting each message of a sequence.
+    pub room_messages: u64,
+    /// How long connections took to set up.
+    pub setup_time: HistogramSnapshot,
+    /// How long the server has been running, or `None` before it starts.
+    pub uptime: Option<Duration>,
+}
+
+impl WyndMetrics {
+    /// Takes a snapshot of the counters alongside the current connection count.
+    pub(crate) fn collect(
+        connections: usize,
+        counters: &Counters,
+        setup_times: &Histogram,
+    ) -> Self {
+        Self {
+            connections,
+            connections_accepted: counters.connections_accepted.load(Ordering::Relaxed),
+            handshake_failures: counters.handshake_failures.load(Ordering::Relaxed),
+            connections_opened: counters.connections_opened.load(Ordering::Relaxed),
+            messages_received: counters.messages_received.load(Ordering::Relaxed),
+            bytes_received: counters.bytes_received.load(Ordering::Relaxed),
+            room_messages: counters.room_messages.load(Ordering::Relaxed),
+            setup_time: setup_times.snapshot(),
+            uptime: counters.uptime(),
+        }
+    }
+}
@@ -4,6 +4,16 @@ use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::{conn::Connection, handle::ConnectionHandle, wynd::BoxFuture};
 
+/// Type alias for the function that continues a middleware chain.
+pub(crate) type NextFn<T> = Arc<
+    dyn Fn(
+            Arc<Connection<T>>,
+            Arc<ConnectionHandle<T>>,
+        ) -> BoxFuture<Result<(Arc<Connection<T>>, Arc<ConnectionHandle<T>>), String>>
+        + Send
+        + Sync,
+>;
+
 pub type MiddlewareHandler<T> = Arc<
     dyn Fn(
             Arc<Connection<T>>,
@@ -45,7 +55,7 @@ impl<T> Middleware<T>
 where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
-    pub(crate) async fn handle<'a>(
+    pub(crate) async fn handle(
         &self,
         conn: Arc<Connection<T>>,
         handle: Arc<ConnectionHandle<T>>,
@@ -67,17 +77,7 @@ pub struct Next<T>
 where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
-    pub(crate) next_fn: Option<
-        Arc<
-            dyn Fn(
-                    Arc<Connection<T>>,
-                    Arc<ConnectionHandle<T>>,
-                )
-                    -> BoxFuture<Result<(Arc<Connection<T>>, Arc<ConnectionHandle<T>>), String>>
-                + Send
-                + Sync,
-        >,
-    >,
+    pub(crate) next_fn: Option<NextFn<T>>,
 }
 
 impl<T> Clone for Next<T>
@@ -95,17 +95,7 @@ impl<T> Next<T>
 where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
-    pub(crate) fn new(
-        next_fn: Arc<
-            dyn Fn(
-                    Arc<Connection<T>>,
-                    Arc<ConnectionHandle<T>>,
-                )
-                    -> BoxFuture<Result<(Arc<Connection<T>>, Arc<ConnectionHandle<T>>), String>>
-                + Send
-                + Sync,
-        >,
-    ) -> Self {
+    pub(crate) fn new(next_fn: NextFn<T>) -> Self {
         Self {
             next_fn: Some(next_fn),
         }
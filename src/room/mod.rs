@@ -3,13 +3,17 @@
 //! Defines `Room`, room coordination events (`RoomEvents`), and `RoomMethods`
 //! which you obtain from `handle::ConnectionHandle::to()` to target a named
 //! room. Rooms allow grouping clients and broadcasting text/binary messages.
-use crate::handle::ConnectionHandle;
+use crate::handle::{BroadcastPayload, ConnectionHandle};
+use crate::types::RoomError;
 use crate::wynd::ConnectionId;
+use std::collections::VecDeque;
 use std::fmt::Debug;
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, sync::Arc};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc::Sender;
 use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
 /// A collection of connections identified by a room name.
 ///
@@ -22,7 +26,121 @@ where
     T: AsyncRead + AsyncWrite + Unpin + Send + Debug + 'static,
 {
     pub(crate) room_clients: HashMap<u64, ConnectionHandle<T>>,
-    pub(crate) room_name: &'static str,
+    pub(crate) room_name: String,
+    /// Persistent rooms are kept even when their last member leaves.
+    pub(crate) persistent: bool,
+    /// Arbitrary key/value metadata attached to the room.
+    pub(crate) metadata: HashMap<String, String>,
+    /// Most recent messages sent to the room, oldest first.
+    pub(crate) history: VecDeque<BroadcastPayload>,
+    /// How many messages `history` keeps; `0` disables it.
+    pub(crate) history_limit: usize,
+    /// Limit on how fast messages are accepted into the room, if any.
+    pub(crate) rate_limit: Option<RateLimit>,
+    /// Times of the most recent messages, oldest first, for flood detection.
+    pub(crate) recent_messages: VecDeque<Instant>,
+}
+
+/// Callback run with a room's name when the room is flooded.
+pub(crate) type RoomFloodHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Server-wide flood threshold: more than `max_msgs` messages to one room
+/// within `window` runs `handler`.
+#[derive(Clone)]
+pub(crate) struct FloodWatch {
+    pub(crate) max_msgs: usize,
+    pub(crate) window: Duration,
+    pub(crate) handler: RoomFloodHandler,
+}
+
+impl FloodWatch {
+    /// Counts a message sent to `room`, running the handler if it pushed the
+    /// room over the threshold.
+    pub(crate) fn observe<T>(&self, room: &mut Room<T>)
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + Debug + 'static,
+    {
+        if room.note_message(self.max_msgs, self.window) {
+            (self.handler)(&room.room_name);
+        }
+    }
+}
+
+/// Reports how many connections a room message was written to.
+///
+/// The count is sent when the report is dropped, so a message the room
+/// processor skips (unknown room, non-member sender, rate limited) is
+/// reported as delivered to nobody.
+pub(crate) struct DeliveryReport {
+    sender: Option<oneshot::Sender<usize>>,
+    delivered: usize,
+}
+
+impl DeliveryReport {
+    pub(crate) fn new(sender: Option<oneshot::Sender<usize>>) -> Self {
+        Self {
+            sender,
+            delivered: 0,
+        }
+    }
+
+    /// Counts one recipient the message was written to.
+    pub(crate) fn delivered(&mut self) {
+        self.delivered += 1;
+    }
+}
+
+impl Drop for DeliveryReport {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(self.delivered);
+        }
+    }
+}
+
+/// Token bucket allowing up to `capacity` messages per `per`.
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimit {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl RateLimit {
+    /// Creates a full bucket allowing `max_msgs` messages per `per`.
+    pub(crate) fn new(max_msgs: u32, per: Duration) -> Self {
+        let capacity = f64::from(max_msgs);
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / per.as_secs_f64().max(f64::EPSILON),
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token if one is available.
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<T> Default for Room<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + Debug + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> Room<T>
@@ -33,7 +151,124 @@ where
     pub fn new() -> Self {
         Self {
             room_clients: HashMap::new(),
-            room_name: "",
+            room_name: String::new(),
+            persistent: false,
+            metadata: HashMap::new(),
+            history: VecDeque::new(),
+            history_limit: 0,
+            rate_limit: None,
+            recent_messages: VecDeque::new(),
+        }
+    }
+
+    /// Creates an empty room with the given name.
+    pub(crate) fn named(room_name: impl Into<String>) -> Self {
+        Self {
+            room_name: room_name.into(),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the room's name.
+    pub fn name(&self) -> &str {
+        &self.room_name
+    }
+
+    /// Returns how many connections are in the room.
+    pub fn member_count(&self) -> usize {
+        self.room_clients.len()
+    }
+
+    /// Returns the ids of the connections in the room, sorted ascending.
+    pub fn member_ids(&self) -> Vec<ConnectionId> {
+        let mut ids: Vec<ConnectionId> = self.room_clients.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Copies the room's name and members into a detached room.
+    ///
+    /// Messages sent through the copy reach the same members but are not
+    /// recorded in the original room's history.
+    pub(crate) fn detached(&self) -> Self {
+        Self {
+            room_clients: self.room_clients.clone(),
+            ..Self::named(self.room_name.clone())
+        }
+    }
+
+    /// Returns `true` if the room has no members, is not persistent, keeps
+    /// no history and has no rate limit.
+    pub(crate) fn is_disposable(&self) -> bool {
+        !self.persistent
+            && self.history_limit == 0
+            && self.rate_limit.is_none()
+            && self.room_clients.is_empty()
+    }
+
+    /// Returns `true` if a message may be sent to the room now, consuming
+    /// one unit of its rate limit.
+    pub(crate) fn admit(&mut self) -> bool {
+        self.rate_limit
+            .as_mut()
+            .is_none_or(|limit| limit.try_acquire())
+    }
+
+    /// Records a message in the room's sliding window.
+    ///
+    /// Returns `true` if this message is the one that took the room past
+    /// `max_msgs` messages within `window`. Further messages do not return
+    /// `true` again until the window has dropped back under the threshold.
+    pub(crate) fn note_message(&mut self, max_msgs: usize, window: Duration) -> bool {
+        let now = Instant::now();
+        while self
+            .recent_messages
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) > window)
+        {
+            self.recent_messages.pop_front();
+        }
+
+        let was_flooded = self.recent_messages.len() > max_msgs;
+        self.recent_messages.push_back(now);
+        // Only the newest `max_msgs + 1` times are needed to tell whether the
+        // room is over the threshold.
+        if self.recent_messages.len() > max_msgs + 1 {
+            self.recent_messages.pop_front();
+        }
+        !was_flooded && self.recent_messages.len() > max_msgs
+    }
+
+    /// Sets how many recent messages the room keeps, dropping the oldest
+    /// ones if the history is already longer.
+    pub(crate) fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        while self.history.len() > limit {
+            self.history.pop_front();
+        }
+    }
+
+    /// Records a message sent to the room if history is enabled.
+    pub(crate) fn record(&mut self, payload: &BroadcastPayload) {
+        if self.history_limit == 0 {
+            return;
+        }
+        if self.history.len() == self.history_limit {
+            self.history.pop_front();
+        }
+        self.history.push_back(payload.clone());
+    }
+
+    /// Describes this room as a [`RoomDefinition`].
+    pub(crate) fn definition(&self) -> RoomDefinition {
+        let mut members: Vec<ConnectionId> = self.room_clients.keys().copied().collect();
+        members.sort_unstable();
+
+        RoomDefinition {
+            name: self.room_name.to_string(),
+            persistent: self.persistent,
+            members,
+            metadata: self.metadata.clone(),
         }
     }
 
@@ -76,6 +311,68 @@ where
     }
 }
 
+/// Serializable description of a single room.
+///
+/// Produced by [`Wynd::export_rooms`](crate::wynd::Wynd::export_rooms) as part of a
+/// [`RoomSnapshot`]. Member ids are informational only: live connections cannot
+/// be restored, so importing a definition only recreates the room itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoomDefinition {
+    /// The room name.
+    pub name: String,
+    /// Whether the room is kept when it has no members.
+    pub persistent: bool,
+    /// Ids of the connections in the room when it was exported.
+    #[cfg_attr(feature = "json", serde(default))]
+    pub members: Vec<ConnectionId>,
+    /// Key/value metadata attached to the room.
+    #[cfg_attr(feature = "json", serde(default))]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Point-in-time description of every room on a server.
+///
+/// Use [`Wynd::export_rooms`](crate::wynd::Wynd::export_rooms) to capture the
+/// room state and [`Wynd::import_rooms`](crate::wynd::Wynd::import_rooms) to
+/// reload persistent rooms, e.g. on boot from a saved config. With the `json`
+/// feature enabled the snapshot can be serialized with serde.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::room::{RoomDefinition, RoomSnapshot};
+///
+/// let snapshot = RoomSnapshot {
+///     rooms: vec![RoomDefinition {
+///         name: "lobby".to_string(),
+///         persistent: true,
+///         ..Default::default()
+///     }],
+/// };
+///
+/// assert_eq!(snapshot.rooms[0].name, "lobby");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoomSnapshot {
+    /// The rooms that existed when the snapshot was taken.
+    pub rooms: Vec<RoomDefinition>,
+}
+
+#[cfg(feature = "json")]
+impl RoomSnapshot {
+    /// Serializes the snapshot to a JSON string.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Parses a snapshot from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
 /// Information about a client connected to the server.
 #[derive(Debug)]
 pub struct ClientInfo<T>
@@ -101,7 +398,9 @@ where
         /// Handle to the client's connection.
         handle: ConnectionHandle<T>,
         /// Target room name to join.
-        room_name: &'static str,
+        room_name: String,
+        /// Channel told whether the join succeeded.
+        ack: oneshot::Sender<Result<(), RoomError>>,
     },
 
     /// Request to list all the clients connected to the server.
@@ -110,12 +409,38 @@ where
         response_to: oneshot::Sender<Vec<ClientInfo<T>>>,
     },
 
+    /// Request to count the members of a room.
+    CountMembers {
+        /// Target room name.
+        room_name: String,
+        /// Channel to send the member count to.
+        response_to: oneshot::Sender<usize>,
+    },
+
+    /// Request to list the members of a room.
+    ListMembers {
+        /// Target room name.
+        room_name: String,
+        /// Channel to send the member ids to, sorted ascending.
+        response_to: oneshot::Sender<Vec<ConnectionId>>,
+    },
+
     /// Request to List all the rooms joined by client with given id.
     ListRooms {
         /// Unique identifier of the client.
         client_id: u64,
     },
 
+    /// Request to check whether a client is a member of a room.
+    IsMember {
+        /// Unique identifier of the client.
+        client_id: u64,
+        /// Room name to check.
+        room_name: String,
+        /// Channel to send the answer to.
+        response_to: oneshot::Sender<bool>,
+    },
+
     /// Response to List all the rooms joined by client with given id.
     ListRoomsResponse {
         /// Unique identifier of the client.
@@ -129,9 +454,21 @@ where
         /// Sender client identifier.
         client_id: u64,
         /// Target room name.
-        room_name: &'static str,
+        room_name: String,
         /// UTF-8 text payload.
         text: String,
+        /// Receives how many connections the message was written to.
+        delivered: Option<oneshot::Sender<usize>>,
+    },
+
+    /// Ordered text messages broadcast to a room as one unit.
+    TextSequence {
+        /// Sender client identifier.
+        client_id: u64,
+        /// Target room name.
+        room_name: String,
+        /// UTF-8 text payloads, in delivery order.
+        texts: Vec<String>,
     },
 
     /// Text message broadcast to a room.
@@ -139,9 +476,11 @@ where
         /// Sender client identifier.
         client_id: u64,
         /// Target room name.
-        room_name: &'static str,
+        room_name: String,
         /// UTF-8 text payload.
         text: String,
+        /// Receives how many connections the message was written to.
+        delivered: Option<oneshot::Sender<usize>>,
     },
 
     /// Binary message broadcast to a room.
@@ -149,7 +488,7 @@ where
         /// Sender client identifier.
         client_id: u64,
         /// Target room name.
-        room_name: &'static str,
+        room_name: String,
         /// Binary payload.
         bytes: Vec<u8>,
     },
@@ -159,7 +498,7 @@ where
         /// Sender client identifier.
         client_id: u64,
         /// Target room name.
-        room_name: &'static str,
+        room_name: String,
         /// Binary payload.
         bytes: Vec<u8>,
     },
@@ -169,7 +508,21 @@ where
         /// Unique identifier of the client.
         client_id: u64,
         /// Target room name to leave.
-        room_name: &'static str,
+        room_name: String,
+    },
+
+    /// Request to leave one room and join another in a single step.
+    MoveRoom {
+        /// Unique identifier of the client.
+        client_id: u64,
+        /// Handle to the client's connection.
+        handle: ConnectionHandle<T>,
+        /// Room to leave.
+        from: String,
+        /// Room to join.
+        to: String,
+        /// Channel told whether the move succeeded.
+        ack: oneshot::Sender<Result<(), RoomError>>,
     },
 
     /// Request to leave all rooms.
@@ -204,75 +557,234 @@ where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
     /// The name of the target room.
-    pub(crate) room_name: &'static str,
+    pub(crate) room_name: String,
     /// The sender used to dispatch room events.
-    pub(crate) room_sender: Arc<&'room_sender Sender<RoomEvents<T>>>,
+    pub(crate) room_sender: &'room_sender Sender<RoomEvents<T>>,
     /// The unique identifier of the client (sender).
     pub(crate) id: u64,
+    /// The sender's handle, when messages require room membership.
+    pub(crate) membership_check: Option<&'room_sender ConnectionHandle<T>>,
 }
 
 impl<T> RoomMethods<'_, T>
 where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
+    /// Fails with [`RoomError::NotAMember`] if membership is required and
+    /// the sender has not joined the room.
+    ///
+    /// Membership is asked of the room processor, so joins requested before
+    /// this call are taken into account.
+    async fn ensure_member(&self) -> Result<(), RoomError> {
+        let Some(handle) = self.membership_check else {
+            return Ok(());
+        };
+        if handle.in_room(&self.room_name).await {
+            Ok(())
+        } else {
+            Err(RoomError::NotAMember(self.room_name.clone()))
+        }
+    }
+
     /// Sends a UTF-8 text message to all clients in the room except the current client (sender).
     ///
+    /// Only members can message a room: the room processor drops messages
+    /// from a sender that has not joined it.
+    ///
     /// # Arguments
     ///
     /// * `text` - The text message to send.
     ///
     /// # Returns
     ///
-    /// * `Result<(), std::io::Error>` - Ok if the message was sent, Err otherwise.
-    pub async fn text<S>(&self, text: S) -> Result<(), std::io::Error>
+    /// * `Result<(), RoomError>` - Ok if the message was handed to the room processor, Err otherwise.
+    pub async fn text<S>(&self, text: S) -> Result<(), RoomError>
     where
         S: Into<String>,
     {
+        self.ensure_member().await?;
         self.room_sender
             .send(RoomEvents::TextMessage {
                 client_id: self.id,
-                room_name: self.room_name,
+                room_name: self.room_name.clone(),
                 text: text.into(),
+                delivered: None,
             })
-            .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to send text to room: {}", e),
-                )
-            })?;
+            .await?;
         Ok(())
     }
 
+    /// Like [`text`](Self::text), but waits for the room processor and
+    /// returns how many connections the message was written to.
+    ///
+    /// The sender is never counted, so for a sender in a room of `n` members
+    /// the count is at most `n - 1`. A sender that is not a member gets `0`,
+    /// since its message is dropped, as do messages to unknown rooms and
+    /// messages rejected by the room's rate limit. Members whose write
+    /// failed are not counted. Use
+    /// [`emit_text_counted`](Self::emit_text_counted) to include the sender.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text message to send.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, RoomError>` - The number of recipients, or an error if the room processor is not running.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if let Ok(heard) = handle.to("lobby").text_counted(msg.data).await {
+    ///             let _ = handle.send_text(&format!("{} people heard you", heard)).await;
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub async fn text_counted<S>(&self, text: S) -> Result<usize, RoomError>
+    where
+        S: Into<String>,
+    {
+        self.ensure_member().await?;
+        let (tx, rx) = oneshot::channel();
+        self.room_sender
+            .send(RoomEvents::TextMessage {
+                client_id: self.id,
+                room_name: self.room_name.clone(),
+                text: text.into(),
+                delivered: Some(tx),
+            })
+            .await?;
+        Ok(rx.await?)
+    }
+
+    /// Sends several UTF-8 text messages, in order, to all clients in the room
+    /// except the current client (sender).
+    ///
+    /// The messages travel as a single room event and are written to each
+    /// recipient back to back, so other room messages cannot be interleaved
+    /// between them. The sequence counts as one message for the room's rate
+    /// limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `msgs` - The text messages to send, in delivery order.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), RoomError>` - Ok if the messages were handed to the room processor, Err otherwise.
+    pub async fn text_sequence(&self, msgs: &[&str]) -> Result<(), RoomError> {
+        self.ensure_member().await?;
+        self.room_sender
+            .send(RoomEvents::TextSequence {
+                client_id: self.id,
+                room_name: self.room_name.clone(),
+                texts: msgs.iter().map(|m| m.to_string()).collect(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Schedules a UTF-8 text message to all clients in the room except the
+    /// current client (sender), to be sent after `delay`.
+    ///
+    /// Returns immediately; the message is delivered by a background timer.
+    /// If the sender is no longer in the room when the delay elapses (for
+    /// example because the room emptied), the message is skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text message to send.
+    /// * `delay` - How long to wait before sending.
+    ///
+    /// # Returns
+    ///
+    /// * `JoinHandle<()>` - Handle to the timer task; abort it to cancel the send.
+    pub fn text_delayed<S>(&self, text: S, delay: Duration) -> JoinHandle<()>
+    where
+        S: Into<String>,
+    {
+        let room_sender = self.room_sender.clone();
+        let event = RoomEvents::TextMessage {
+            client_id: self.id,
+            room_name: self.room_name.clone(),
+            text: text.into(),
+            delivered: None,
+        };
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Err(e) = room_sender.send(event).await {
+                eprintln!("Failed to send delayed text to room: {}", e);
+            }
+        })
+    }
+
     /// Sends a UTF-8 text message to all clients in the room including the current client (sender).
     ///
+    /// As with [`text`](Self::text), messages from a sender that has not
+    /// joined the room are dropped.
+    ///
     /// # Arguments
     ///
     /// * `text` - The text message to broadcast.
     ///
     /// # Returns
     ///
-    /// * `Result<(), std::io::Error>` - Ok if the message was broadcast, Err otherwise.
-    pub async fn emit_text<S>(&self, text: S) -> Result<(), std::io::Error>
+    /// * `Result<(), RoomError>` - Ok if the message was handed to the room processor, Err otherwise.
+    pub async fn emit_text<S>(&self, text: S) -> Result<(), RoomError>
     where
         S: Into<String>,
     {
+        self.ensure_member().await?;
         self.room_sender
             .send(RoomEvents::EmitTextMessage {
                 client_id: self.id,
-                room_name: self.room_name,
+                room_name: self.room_name.clone(),
                 text: text.into(),
+                delivered: None,
             })
-            .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to emit text to room: {}", e),
-                )
-            })?;
+            .await?;
         Ok(())
     }
 
+    /// Like [`emit_text`](Self::emit_text), but waits for the room processor
+    /// and returns how many connections the message was written to.
+    ///
+    /// The sender is counted, so for a sender in a room of `n` members the
+    /// count is at most `n`. Otherwise the count follows
+    /// [`text_counted`](Self::text_counted): `0` for a sender that is not a
+    /// member, and failed writes are not counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text message to broadcast.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, RoomError>` - The number of recipients, or an error if the room processor is not running.
+    pub async fn emit_text_counted<S>(&self, text: S) -> Result<usize, RoomError>
+    where
+        S: Into<String>,
+    {
+        self.ensure_member().await?;
+        let (tx, rx) = oneshot::channel();
+        self.room_sender
+            .send(RoomEvents::EmitTextMessage {
+                client_id: self.id,
+                room_name: self.room_name.clone(),
+                text: text.into(),
+                delivered: Some(tx),
+            })
+            .await?;
+        Ok(rx.await?)
+    }
+
     /// Sends a binary message to all clients in the room except the current client (sender).
     ///
     /// # Arguments
@@ -281,24 +793,19 @@ where
     ///
     /// # Returns
     ///
-    /// * `Result<(), std::io::Error>` - Ok if the message was sent, Err otherwise.
-    pub async fn binary<B>(&self, bytes: B) -> Result<(), std::io::Error>
+    /// * `Result<(), RoomError>` - Ok if the message was handed to the room processor, Err otherwise.
+    pub async fn binary<B>(&self, bytes: B) -> Result<(), RoomError>
     where
         B: Into<Vec<u8>>,
     {
+        self.ensure_member().await?;
         self.room_sender
             .send(RoomEvents::BinaryMessage {
                 client_id: self.id,
-                room_name: self.room_name,
+                room_name: self.room_name.clone(),
                 bytes: bytes.into(),
             })
-            .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to send binary to room: {}", e),
-                )
-            })?;
+            .await?;
         Ok(())
     }
 
@@ -310,24 +817,73 @@ where
     ///
     /// # Returns
     ///
-    /// * `Result<(), std::io::Error>` - Ok if the message was broadcast, Err otherwise.
-    pub async fn emit_binary<B>(&self, bytes: B) -> Result<(), std::io::Error>
+    /// * `Result<(), RoomError>` - Ok if the message was handed to the room processor, Err otherwise.
+    pub async fn emit_binary<B>(&self, bytes: B) -> Result<(), RoomError>
     where
         B: Into<Vec<u8>>,
     {
+        self.ensure_member().await?;
         self.room_sender
             .send(RoomEvents::EmitBinaryMessage {
                 client_id: self.id,
-                room_name: self.room_name,
+                room_name: self.room_name.clone(),
                 bytes: bytes.into(),
             })
-            .await
-            .map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Failed to emit binary to room: {}", e),
-                )
-            })?;
+            .await?;
         Ok(())
     }
+
+    /// Returns how many connections are in the room.
+    ///
+    /// The count comes from the room processor, so it reflects every join
+    /// and leave requested before this call. A room nobody has joined has
+    /// no members.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, RoomError>` - The member count, or an error if the room processor is not running.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         let _ = handle.join("lobby").await;
+    ///         if let Ok(online) = handle.to("lobby").member_count().await {
+    ///             let _ = handle.send_text(&format!("{} users online", online)).await;
+    ///         }
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub async fn member_count(&self) -> Result<usize, RoomError> {
+        let (tx, rx) = oneshot::channel();
+        self.room_sender
+            .send(RoomEvents::CountMembers {
+                room_name: self.room_name.clone(),
+                response_to: tx,
+            })
+            .await?;
+        Ok(rx.await?)
+    }
+
+    /// Returns the ids of the connections in the room, sorted ascending.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<ConnectionId>, RoomError>` - The member ids, or an error if the room processor is not running.
+    pub async fn member_ids(&self) -> Result<Vec<ConnectionId>, RoomError> {
+        let (tx, rx) = oneshot::channel();
+        self.room_sender
+            .send(RoomEvents::ListMembers {
+                room_name: self.room_name.clone(),
+                response_to: tx,
+            })
+            .await?;
+        Ok(rx.await?)
+    }
 }
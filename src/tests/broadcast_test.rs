@@ -0,0 +1,246 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::DuplexStream;
+
+    use crate::handle::{
+        deliver_to, is_disconnect_error, BroadcastPayload, ConnectionHandle,
+        IMPORTANT_BACKLOG_LIMIT,
+    };
+    use crate::tests::utils::{connect, recv_text};
+    use crate::types::WyndError;
+    use crate::wynd::Wynd;
+
+    fn create_test_server() -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        wynd
+    }
+
+    async fn handle_of(wynd: &Wynd<DuplexStream>, id: u64) -> Arc<ConnectionHandle<DuplexStream>> {
+        let clients = wynd.clients.lock().await;
+        Arc::clone(&clients.get(&id).expect("client was not registered").1)
+    }
+
+    #[tokio::test]
+    async fn coalesced_broadcasts_send_only_the_latest_payload() {
+        let mut wynd = create_test_server();
+        wynd.set_broadcast_coalesce(Duration::from_millis(100));
+
+        let _sender = connect(&wynd).await;
+        let mut receiver = connect(&wynd).await;
+        let handle = handle_of(&wynd, 0).await;
+
+        for i in 0..10 {
+            handle.broadcast.text(format!("cursor {}", i)).await;
+        }
+
+        assert_eq!(recv_text(&mut receiver).await.as_deref(), Some("cursor 9"));
+        assert_eq!(recv_text(&mut receiver).await, None);
+    }
+
+    #[tokio::test]
+    async fn broadcasts_are_not_coalesced_by_default() {
+        let wynd = create_test_server();
+
+        let _sender = connect(&wynd).await;
+        let mut receiver = connect(&wynd).await;
+        let handle = handle_of(&wynd, 0).await;
+
+        for i in 0..3 {
+            handle.broadcast.text(format!("cursor {}", i)).await;
+        }
+
+        for i in 0..3 {
+            let expected = format!("cursor {}", i);
+            assert_eq!(recv_text(&mut receiver).await, Some(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn coalescing_keeps_targets_apart() {
+        let mut wynd = create_test_server();
+        wynd.set_broadcast_coalesce(Duration::from_millis(50));
+
+        let mut sender = connect(&wynd).await;
+        let handle = handle_of(&wynd, 0).await;
+
+        handle.broadcast.text("to others").await;
+        handle.broadcast.emit_text("first").await;
+        handle.broadcast.emit_text("to everyone").await;
+
+        // Only the `emit_text` target includes the sender, and only its latest payload is sent.
+        assert_eq!(recv_text(&mut sender).await.as_deref(), Some("to everyone"));
+        assert_eq!(recv_text(&mut sender).await, None);
+    }
+
+    #[tokio::test]
+    async fn broadcast_to_just_disconnected_client_is_not_a_failure() {
+        let wynd = create_test_server();
+
+        let mut staying = connect(&wynd).await;
+        let leaving = connect(&wynd).await;
+
+        // Snapshot the recipients, then let one of them drop off before sending.
+        let recipients = vec![handle_of(&wynd, 0).await, handle_of(&wynd, 1).await];
+        drop(leaving);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let payload = BroadcastPayload::Text("still here".to_string());
+        assert_eq!(deliver_to(&recipients, &payload, 0).await, 0);
+        assert_eq!(recv_text(&mut staying).await.as_deref(), Some("still here"));
+        // The stale handle really does fail; it is just not reported.
+        assert!(recipients[1].send_text("gone").await.is_err());
+    }
+
+    #[test]
+    fn only_disconnects_count_as_expected_send_errors() {
+        use tokio_tungstenite::tungstenite::error::{CapacityError, Error};
+
+        assert!(is_disconnect_error(&Error::ConnectionClosed));
+        assert!(is_disconnect_error(&Error::Io(std::io::Error::from(
+            std::io::ErrorKind::BrokenPipe
+        ))));
+        assert!(!is_disconnect_error(&Error::Capacity(
+            CapacityError::MessageTooLong {
+                size: 2,
+                max_size: 1
+            }
+        )));
+        assert!(!is_disconnect_error(&std::io::Error::other("disk full")));
+    }
+
+    #[tokio::test]
+    async fn important_broadcasts_are_replayed_after_resume() {
+        let wynd = create_test_server();
+
+        let _sender = connect(&wynd).await;
+        let mut receiver = connect(&wynd).await;
+        let sender = handle_of(&wynd, 0).await;
+        let paused = handle_of(&wynd, 1).await;
+
+        paused.pause_reads();
+        for n in 0..IMPORTANT_BACKLOG_LIMIT + 2 {
+            sender.broadcast.text_important(n.to_string()).await;
+        }
+        assert_eq!(recv_text(&mut receiver).await, None);
+
+        paused.resume_reads();
+        // The buffer keeps the newest messages, so the two oldest are dropped.
+        for n in 2..IMPORTANT_BACKLOG_LIMIT + 2 {
+            assert_eq!(recv_text(&mut receiver).await, Some(n.to_string()));
+        }
+
+        sender.broadcast.text_important("live").await;
+        assert_eq!(recv_text(&mut receiver).await.as_deref(), Some("live"));
+    }
+
+    #[tokio::test]
+    async fn important_replay_stays_behind_queued_sends() {
+        let mut wynd = create_test_server();
+        wynd.with_send_queue_capacity(16);
+
+        let _sender = connect(&wynd).await;
+        let mut receiver = connect(&wynd).await;
+        let sender = handle_of(&wynd, 0).await;
+        let paused = handle_of(&wynd, 1).await;
+
+        paused.pause_reads();
+        sender.broadcast.text_important("replayed").await;
+        paused.send_text("queued").await.unwrap();
+        paused.resume_reads();
+
+        assert_eq!(recv_text(&mut receiver).await.as_deref(), Some("queued"));
+        assert_eq!(recv_text(&mut receiver).await.as_deref(), Some("replayed"));
+    }
+
+    #[tokio::test]
+    async fn large_broadcast_lets_other_tasks_run() {
+        let mut wynd = create_test_server();
+        wynd.set_broadcast_yield_interval(8);
+
+        let mut clients = Vec::new();
+        for _ in 0..200 {
+            clients.push(connect(&wynd).await);
+        }
+        let handle = handle_of(&wynd, 0).await;
+
+        // Counts how often the scheduler gets to run another task.
+        let ticks = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let ticker = {
+            let ticks = Arc::clone(&ticks);
+            tokio::spawn(async move {
+                loop {
+                    ticks.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        tokio::task::yield_now().await;
+
+        let before = ticks.load(std::sync::atomic::Ordering::Relaxed);
+        handle.broadcast.text("big news").await;
+        let during = ticks.load(std::sync::atomic::Ordering::Relaxed) - before;
+        ticker.abort();
+
+        // 199 recipients with a yield every 8 sends.
+        assert!(during >= 24, "other task ran only {} times", during);
+        assert_eq!(
+            recv_text(&mut clients[199]).await.as_deref(),
+            Some("big news")
+        );
+    }
+
+    #[tokio::test]
+    async fn broadcast_text_excludes_the_sender() {
+        let wynd = create_test_server();
+
+        let mut sender = connect(&wynd).await;
+        let mut receiver = connect(&wynd).await;
+        let handle = handle_of(&wynd, 0).await;
+
+        handle.broadcast.text("to others").await;
+
+        assert_eq!(recv_text(&mut receiver).await.as_deref(), Some("to others"));
+        assert_eq!(recv_text(&mut sender).await, None);
+    }
+
+    #[tokio::test]
+    async fn emit_text_includes_the_sender() {
+        let wynd = create_test_server();
+
+        let mut sender = connect(&wynd).await;
+        let mut receiver = connect(&wynd).await;
+        let handle = handle_of(&wynd, 0).await;
+
+        handle.broadcast.emit_text("to everyone").await;
+
+        assert_eq!(
+            recv_text(&mut receiver).await.as_deref(),
+            Some("to everyone")
+        );
+        assert_eq!(recv_text(&mut sender).await.as_deref(), Some("to everyone"));
+    }
+
+    #[tokio::test]
+    async fn broadcast_to_self_reaches_only_the_sender() {
+        let wynd = create_test_server();
+
+        let mut sender = connect(&wynd).await;
+        let mut receiver = connect(&wynd).await;
+        let handle = handle_of(&wynd, 0).await;
+
+        handle.broadcast_to_self("just me").await.unwrap();
+
+        assert_eq!(recv_text(&mut sender).await.as_deref(), Some("just me"));
+        assert_eq!(recv_text(&mut receiver).await, None);
+
+        wynd.clients.lock().await.remove(&0);
+        assert!(matches!(
+            handle.broadcast_to_self("gone").await,
+            Err(WyndError::ConnectionClosed)
+        ));
+    }
+}
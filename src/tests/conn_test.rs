@@ -1,8 +1,15 @@
 #[cfg(test)]
 mod tests {
     use crate::conn::{ConnState, Connection};
-    use crate::handle::{Broadcaster, ConnectionHandle};
+    use crate::handle::{Broadcaster, ConnectionHandle, MAX_CONTROL_PAYLOAD};
+    use crate::tests::utils::{connect, connect_to, peer_addr, recv_text, TestClient};
+    use crate::types::{
+        CloseCause, CloseEvent, ConnectionErrorKind, HandlerError, IncomingMessage, MessageKinds,
+        RoomError, WyndError,
+    };
+    use crate::wynd::Wynd;
 
+    use futures::{SinkExt, StreamExt};
     use std::collections::HashMap;
     use std::{
         io,
@@ -13,11 +20,22 @@ mod tests {
         time::Duration,
     };
     use tokio::{
-        io::{AsyncRead, AsyncWrite, ReadBuf},
+        io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf},
         sync::{mpsc, Mutex},
         time::timeout,
     };
-    use tokio_tungstenite::WebSocketStream;
+    use tokio_tungstenite::{
+        client_async,
+        tungstenite::{
+            protocol::{
+                frame::coding::{CloseCode, Data, OpCode},
+                frame::Frame,
+                CloseFrame,
+            },
+            Message,
+        },
+        WebSocketStream,
+    };
 
     // Mock stream for testing
     #[derive(Debug)]
@@ -124,24 +142,57 @@ mod tests {
         .await;
         let (writer, _reader) = futures::StreamExt::split(ws_stream);
 
-        let handle = ConnectionHandle {
-            id: 123,
-            writer: Arc::new(Mutex::new(writer)),
+        let handle = ConnectionHandle::new(
+            123,
+            Arc::new(Mutex::new(writer)),
             addr,
-            broadcast: Broadcaster {
-                clients: Arc::new(Mutex::new(HashMap::new())),
-                current_client_id: 123,
-            },
-            state: Arc::new(Mutex::new(ConnState::OPEN)),
-            response_receiver: Arc::new(Mutex::new(tokio::sync::mpsc::channel(100).1)),
-            response_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-            room_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-        };
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 123),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        );
 
         assert_eq!(handle.id(), 123);
         assert_eq!(handle.addr(), addr);
     }
 
+    #[tokio::test]
+    async fn test_connection_send_without_handle_fails() {
+        let stream = MockStream::new();
+        let addr = "127.0.0.1:8080".parse().unwrap();
+        let ws_stream = WebSocketStream::from_raw_socket(
+            stream,
+            tokio_tungstenite::tungstenite::protocol::Role::Server,
+            None,
+        )
+        .await;
+        let connection = Connection::new(1, ws_stream, addr);
+
+        let err = connection.send_text("hello").await.unwrap_err();
+        let err = err.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        assert!(connection.send_binary(vec![1]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connection_sends_from_on_connection() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.send_text("welcome").await.unwrap();
+            conn.send_binary(vec![1, 2, 3]).await.unwrap();
+        });
+
+        let mut client = connect(&wynd).await;
+
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("welcome"));
+        let frame = timeout(Duration::from_millis(500), client.next())
+            .await
+            .unwrap();
+        assert_eq!(
+            frame.unwrap().unwrap(),
+            Message::Binary(vec![1, 2, 3].into())
+        );
+    }
+
     #[tokio::test]
     async fn test_on_open_handler() {
         let stream = MockStream::new();
@@ -215,19 +266,14 @@ mod tests {
         .await;
         let (writer, _reader) = futures::StreamExt::split(ws_stream);
 
-        let handle = ConnectionHandle {
-            id: 1,
-            writer: Arc::new(Mutex::new(writer)),
+        let handle = ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
             addr,
-            broadcast: Broadcaster {
-                clients: Arc::new(Mutex::new(HashMap::new())),
-                current_client_id: 123,
-            },
-            state: Arc::new(Mutex::new(ConnState::OPEN)),
-            response_receiver: Arc::new(Mutex::new(tokio::sync::mpsc::channel(100).1)),
-            response_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-            room_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-        };
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 123),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        );
 
         // In a real test environment, you'd verify the message was actually sent
         // For now, we just test that the method doesn't panic
@@ -249,19 +295,14 @@ mod tests {
         .await;
         let (writer, _reader) = futures::StreamExt::split(ws_stream);
 
-        let handle = ConnectionHandle {
-            id: 1,
-            writer: Arc::new(Mutex::new(writer)),
+        let handle = ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
             addr,
-            broadcast: Broadcaster {
-                clients: Arc::new(Mutex::new(HashMap::new())),
-                current_client_id: 1,
-            },
-            state: Arc::new(Mutex::new(ConnState::OPEN)),
-            response_receiver: Arc::new(Mutex::new(tokio::sync::mpsc::channel(100).1)),
-            response_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-            room_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-        };
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 1),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        );
 
         let data = vec![1, 2, 3, 4, 5];
         let _result = handle.send_binary(data).await;
@@ -282,19 +323,14 @@ mod tests {
         .await;
         let (writer, _reader) = futures::StreamExt::split(ws_stream);
 
-        let handle = ConnectionHandle {
-            id: 1,
-            writer: Arc::new(Mutex::new(writer)),
+        let handle = ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
             addr,
-            broadcast: Broadcaster {
-                clients: Arc::new(Mutex::new(HashMap::new())),
-                current_client_id: 1,
-            },
-            state: Arc::new(Mutex::new(ConnState::OPEN)),
-            response_receiver: Arc::new(Mutex::new(tokio::sync::mpsc::channel(100).1)),
-            response_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-            room_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-        };
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 1),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        );
 
         let _result = handle.close().await;
 
@@ -313,19 +349,14 @@ mod tests {
         .await;
         let (writer, _reader) = futures::StreamExt::split(ws_stream);
 
-        let handle = Arc::new(ConnectionHandle {
-            id: 1,
-            writer: Arc::new(Mutex::new(writer)),
+        let handle = Arc::new(ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
             addr,
-            broadcast: Broadcaster {
-                clients: Arc::new(Mutex::new(HashMap::new())),
-                current_client_id: 1,
-            },
-            state: Arc::new(Mutex::new(ConnState::OPEN)),
-            response_receiver: Arc::new(Mutex::new(tokio::sync::mpsc::channel(100).1)),
-            response_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-            room_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-        });
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 1),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        ));
 
         // Test concurrent sending from multiple tasks
         let handles: Vec<_> = (0..5)
@@ -341,11 +372,83 @@ mod tests {
             })
             .collect();
         for task_handle in handles {
-            let _result = task_handle.await.expect("Task should complete");
+            task_handle.await.expect("Task should complete");
             // In a proper test, you'd verify all messages were sent correctly
         }
     }
 
+    #[tokio::test]
+    async fn test_concurrent_sends_write_whole_frames() {
+        const TASKS: usize = 16;
+        const SENDS: usize = 20;
+
+        // A small pipe forces each frame to be written in several pieces, so
+        // any interleaving between tasks would corrupt the stream.
+        let (server_io, client_io) = tokio::io::duplex(512);
+        let server = WebSocketStream::from_raw_socket(
+            server_io,
+            tokio_tungstenite::tungstenite::protocol::Role::Server,
+            None,
+        )
+        .await;
+        let mut client = WebSocketStream::from_raw_socket(
+            client_io,
+            tokio_tungstenite::tungstenite::protocol::Role::Client,
+            None,
+        )
+        .await;
+        let (writer, _reader) = futures::StreamExt::split(server);
+        let handle = Arc::new(ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
+            "127.0.0.1:8080".parse().unwrap(),
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 1),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        ));
+
+        let payload = |task: usize, n: usize| format!("{}:{}:", task, n) + &"x".repeat(100 * n);
+        let senders: Vec<_> = (0..TASKS)
+            .map(|task| {
+                let handle = Arc::clone(&handle);
+                tokio::spawn(async move {
+                    for n in 0..SENDS {
+                        if n % 2 == 0 {
+                            handle.send_text(payload(task, n)).await.unwrap();
+                        } else {
+                            handle
+                                .send_binary(payload(task, n).into_bytes())
+                                .await
+                                .unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut next = [0; TASKS];
+        for _ in 0..TASKS * SENDS {
+            let frame = timeout(Duration::from_secs(5), client.next())
+                .await
+                .expect("every frame should arrive")
+                .expect("stream should stay open")
+                .expect("every frame should decode");
+            let data = match frame {
+                Message::Text(text) => text.to_string(),
+                Message::Binary(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
+                other => panic!("unexpected frame {:?}", other),
+            };
+            let task: usize = data.split(':').next().unwrap().parse().unwrap();
+            // Each task's frames arrive whole and in the order it sent them.
+            assert_eq!(data, payload(task, next[task]));
+            next[task] += 1;
+        }
+        assert!(next.iter().all(|&count| count == SENDS));
+        for sender in senders {
+            sender.await.unwrap();
+        }
+    }
+
     #[test]
     fn test_connection_id_and_addr() {
         let id = 42;
@@ -396,19 +499,14 @@ mod tests {
         .await;
         let (writer, _reader) = futures::StreamExt::split(ws_stream);
 
-        let handle = ConnectionHandle {
-            id: 1,
-            writer: Arc::new(Mutex::new(writer)),
+        let handle = ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
             addr,
-            broadcast: Broadcaster {
-                clients: Arc::new(Mutex::new(HashMap::new())),
-                current_client_id: 1,
-            },
-            state: Arc::new(Mutex::new(ConnState::OPEN)),
-            response_receiver: Arc::new(Mutex::new(tokio::sync::mpsc::channel(100).1)),
-            response_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-            room_sender: Arc::new(tokio::sync::mpsc::channel(100).0),
-        };
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 1),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        );
 
         // Test sending to a potentially closed connection
         // In a real test, you'd set up the mock to return an error
@@ -416,4 +514,1463 @@ mod tests {
 
         // Depending on your mock implementation, you can test error cases
     }
+
+    /// Helper function to create a server that reports ping payloads on a
+    /// channel; with auto pong disabled, pings are answered with a "manual" pong
+    fn create_ping_server(auto_pong: bool) -> (Wynd<DuplexStream>, mpsc::Receiver<Vec<u8>>) {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        if !auto_pong {
+            wynd.disable_auto_pong();
+        }
+        let (ping_tx, ping_rx) = mpsc::channel(4);
+
+        wynd.on_connection(move |conn| {
+            let ping_tx = ping_tx.clone();
+            async move {
+                conn.on_ping(move |payload, handle| {
+                    let ping_tx = ping_tx.clone();
+                    async move {
+                        if !auto_pong {
+                            handle.send_pong(b"manual".to_vec()).await.unwrap();
+                        }
+                        let _ = ping_tx.send(payload).await;
+                    }
+                });
+                conn.on_text(|msg, handle| async move {
+                    let _ = handle.send_text(msg.data).await;
+                });
+            }
+        });
+
+        (wynd, ping_rx)
+    }
+
+    /// Reads frames until `marker` is echoed back, returning the pongs seen before it.
+    async fn pongs_before_echo(client: &mut TestClient, marker: &str) -> Vec<Vec<u8>> {
+        client.send(Message::text(marker)).await.unwrap();
+        let mut pongs = Vec::new();
+        loop {
+            match timeout(Duration::from_millis(500), client.next()).await {
+                Ok(Some(Ok(Message::Pong(payload)))) => pongs.push(payload.to_vec()),
+                Ok(Some(Ok(Message::Text(text)))) if text.as_str() == marker => return pongs,
+                other => panic!("unexpected frame: {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ping_is_answered_and_reported_by_default() {
+        let (wynd, mut pings) = create_ping_server(true);
+        let mut client = connect(&wynd).await;
+
+        client
+            .send(Message::Ping(b"hb".to_vec().into()))
+            .await
+            .unwrap();
+
+        let payload = timeout(Duration::from_millis(500), pings.recv())
+            .await
+            .unwrap();
+        assert_eq!(payload, Some(b"hb".to_vec()));
+        assert_eq!(
+            pongs_before_echo(&mut client, "done").await,
+            vec![b"hb".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disable_auto_pong_leaves_reply_to_on_ping() {
+        let (wynd, mut pings) = create_ping_server(false);
+        let mut client = connect(&wynd).await;
+
+        client
+            .send(Message::Ping(b"hb".to_vec().into()))
+            .await
+            .unwrap();
+
+        let payload = timeout(Duration::from_millis(500), pings.recv())
+            .await
+            .unwrap();
+        assert_eq!(payload, Some(b"hb".to_vec()));
+        // Only the handler's pong arrives; no automatic "hb" pong is sent.
+        assert_eq!(
+            pongs_before_echo(&mut client, "done").await,
+            vec![b"manual".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_ping_and_pong_validate_payload_length() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                let too_long = vec![0; MAX_CONTROL_PAYLOAD + 1];
+                let ping_rejected = handle
+                    .send_ping(too_long.clone())
+                    .await
+                    .is_err_and(|e| matches!(e, WyndError::InvalidPayload(_)));
+                let pong_rejected = handle
+                    .send_pong(too_long)
+                    .await
+                    .is_err_and(|e| matches!(e, WyndError::InvalidPayload(_)));
+                let rejected = ping_rejected && pong_rejected;
+                handle
+                    .send_ping(vec![7; MAX_CONTROL_PAYLOAD])
+                    .await
+                    .unwrap();
+                handle.send_pong(b"beat".to_vec()).await.unwrap();
+                let _ = handle.send_text(rejected.to_string()).await;
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("go")).await.unwrap();
+
+        let mut frames = Vec::new();
+        for _ in 0..3 {
+            let frame = timeout(Duration::from_millis(500), client.next())
+                .await
+                .unwrap();
+            frames.push(frame.unwrap().unwrap());
+        }
+        assert_eq!(
+            frames,
+            vec![
+                Message::Ping(vec![7; MAX_CONTROL_PAYLOAD].into()),
+                Message::Pong(b"beat".to_vec().into()),
+                Message::text("true"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_writer_contention_counts_waiting_senders() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                let uncontended = handle.writer_contention();
+
+                // Hold the writer so that every concurrent sender has to wait.
+                let writer = handle.writer.lock().await;
+                let senders: Vec<_> = (0..4)
+                    .map(|n| {
+                        let handle = Arc::clone(&handle);
+                        tokio::spawn(async move {
+                            let _ = handle.send_text(format!("msg {}", n)).await;
+                        })
+                    })
+                    .collect();
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                drop(writer);
+                for sender in senders {
+                    sender.await.unwrap();
+                }
+
+                let reply = format!("{} {}", uncontended, handle.writer_contention());
+                let _ = handle.send_text(reply).await;
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("go")).await.unwrap();
+
+        let mut texts = Vec::new();
+        for _ in 0..5 {
+            texts.push(recv_text(&mut client).await.unwrap());
+        }
+        assert_eq!(texts.last().map(String::as_str), Some("0 4"));
+        texts.pop();
+        texts.sort();
+        assert_eq!(texts, ["msg 0", "msg 1", "msg 2", "msg 3"]);
+    }
+
+    #[tokio::test]
+    async fn test_on_pong_receives_payload_and_arrival_time() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            let sent_at = std::time::Instant::now();
+            conn.on_pong(move |event, handle| async move {
+                let reply = format!(
+                    "{} {}",
+                    String::from_utf8_lossy(&event.data),
+                    event.received_at >= sent_at
+                );
+                let _ = handle.send_text(reply).await;
+            });
+            conn.on_open(|handle| async move {
+                handle.send_ping(b"rtt-1".to_vec()).await.unwrap();
+            })
+            .await;
+        });
+
+        let mut client = connect(&wynd).await;
+
+        // Reading the ping queues the client's pong, which is flushed by the next read.
+        let mut frames = Vec::new();
+        for _ in 0..2 {
+            let frame = timeout(Duration::from_millis(500), client.next())
+                .await
+                .unwrap();
+            frames.push(frame.unwrap().unwrap());
+        }
+        assert_eq!(
+            frames,
+            vec![
+                Message::Ping(b"rtt-1".to_vec().into()),
+                Message::text("rtt-1 true"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_ping_receives_payload_and_handle() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let (ping_tx, mut pings) = mpsc::channel(4);
+
+        wynd.on_connection(move |conn| {
+            let ping_tx = ping_tx.clone();
+            async move {
+                let id = conn.id();
+                conn.on_ping(move |payload, handle| {
+                    let ping_tx = ping_tx.clone();
+                    async move {
+                        let _ = ping_tx.send((payload, handle.id() == id)).await;
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        let payload = vec![0, 159, 146, 150];
+        client
+            .send(Message::Ping(payload.clone().into()))
+            .await
+            .unwrap();
+
+        let fired = timeout(Duration::from_millis(500), pings.recv())
+            .await
+            .expect("on_ping handler did not fire");
+        assert_eq!(fired, Some((payload, true)));
+    }
+
+    #[tokio::test]
+    async fn test_room_operations_report_channel_closed() {
+        let stream = MockStream::new();
+        let addr = "127.0.0.1:8080".parse().unwrap();
+        let ws_stream = WebSocketStream::from_raw_socket(
+            stream,
+            tokio_tungstenite::tungstenite::protocol::Role::Server,
+            None,
+        )
+        .await;
+        let (writer, _reader) = futures::StreamExt::split(ws_stream);
+
+        // The receiver is dropped immediately, as if the room processor had stopped.
+        let handle = ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
+            addr,
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 1),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        );
+
+        assert_eq!(handle.join("lobby").await, Err(RoomError::ChannelClosed));
+        assert_eq!(handle.leave("lobby").await, Err(RoomError::ChannelClosed));
+        assert_eq!(
+            handle.move_to("lobby", "games").await,
+            Err(RoomError::ChannelClosed)
+        );
+        assert_eq!(
+            handle.leave_all_rooms().await,
+            Err(RoomError::ChannelClosed)
+        );
+        assert_eq!(
+            handle.to("lobby").text("hi").await,
+            Err(RoomError::ChannelClosed)
+        );
+        assert_eq!(
+            handle.to("lobby").emit_binary(&[1, 2]).await,
+            Err(RoomError::ChannelClosed)
+        );
+        assert!(matches!(
+            handle.clients().await,
+            Err(RoomError::ChannelClosed)
+        ));
+    }
+
+    #[test]
+    fn test_room_error_display_and_boxing() {
+        let cases = [
+            (RoomError::ChannelClosed, "room processor is not running"),
+            (
+                RoomError::RoomNotFound("lobby".to_string()),
+                "room lobby does not exist",
+            ),
+            (
+                RoomError::CapacityExceeded("lobby".to_string()),
+                "room lobby is at capacity",
+            ),
+            (
+                RoomError::NotAMember("lobby".to_string()),
+                "not a member of room lobby",
+            ),
+        ];
+
+        for (err, message) in cases {
+            let boxed: Box<dyn std::error::Error> = Box::new(err.clone());
+            assert_eq!(boxed.to_string(), message);
+            assert_eq!(boxed.downcast_ref::<RoomError>(), Some(&err));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_state_is_open_when_on_open_fires() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let (state_tx, mut state_rx) = mpsc::channel(1);
+
+        wynd.on_connection(move |conn| {
+            let state_tx = state_tx.clone();
+            async move {
+                conn.on_open(move |handle| {
+                    let state_tx = state_tx.clone();
+                    async move {
+                        let _ = state_tx.send(handle.state().await).await;
+                    }
+                })
+                .await;
+            }
+        });
+
+        let _client = connect(&wynd).await;
+
+        let state = timeout(Duration::from_millis(500), state_rx.recv())
+            .await
+            .expect("on_open did not fire");
+        assert_eq!(state, Some(ConnState::OPEN));
+    }
+
+    #[tokio::test]
+    async fn test_state_is_open_after_connecting() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|_handle| async move {}).await;
+        });
+
+        let _client = connect(&wynd).await;
+
+        let handle = {
+            let clients = wynd.clients.lock().await;
+            let (_, handle) = clients.values().next().expect("client was not registered");
+            Arc::clone(handle)
+        };
+        assert_eq!(handle.state().await, ConnState::OPEN);
+    }
+
+    #[tokio::test]
+    async fn test_message_sent_on_connect_is_not_dropped() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            // Slow setup, so the client's first message arrives before on_text is registered.
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            conn.on_text(|msg, handle| async move {
+                let _ = handle.send_text(format!("echo: {}", msg.data)).await;
+            });
+
+            conn.on_ready(|handle| async move {
+                let _ = handle.send_text("ready").await;
+            })
+            .await;
+        });
+
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let (server, mut client) =
+            tokio::join!(wynd.handle_connection(server_io, peer_addr(1)), async {
+                let (mut client, _) = client_async("ws://localhost/", client_io)
+                    .await
+                    .expect("client side of the handshake should succeed");
+                client.send(Message::text("hello")).await.unwrap();
+                client
+            });
+        server.expect("server side of the handshake should succeed");
+
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("ready"));
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("echo: hello"));
+        assert_eq!(recv_text(&mut client).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_close_flush_delivers_queued_message_before_close() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+
+        let mut client = connect(&wynd).await;
+        let handle = {
+            let clients = wynd.clients.lock().await;
+            let (_, handle) = clients.values().next().expect("client was not registered");
+            Arc::clone(handle)
+        };
+
+        // Hold the writer so the message below is queued rather than sent.
+        let writer = handle.writer.lock().await;
+
+        let sender = Arc::clone(&handle);
+        let send = tokio::spawn(async move { sender.send_text("final state").await.is_ok() });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let closer = Arc::clone(&handle);
+        let close =
+            tokio::spawn(async move { closer.close_flush(Duration::from_secs(1)).await.is_ok() });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        drop(writer);
+        assert!(send.await.unwrap());
+        assert!(close.await.unwrap());
+
+        let first = timeout(Duration::from_millis(500), client.next()).await;
+        assert!(matches!(first, Ok(Some(Ok(Message::Text(text)))) if text == "final state"));
+        let second = timeout(Duration::from_millis(500), client.next()).await;
+        assert!(matches!(second, Ok(Some(Ok(Message::Close(_))))));
+    }
+
+    #[tokio::test]
+    async fn test_close_flush_times_out_when_writer_is_stuck() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+
+        let _client = connect(&wynd).await;
+        let handle = {
+            let clients = wynd.clients.lock().await;
+            let (_, handle) = clients.values().next().expect("client was not registered");
+            Arc::clone(handle)
+        };
+
+        let _writer = handle.writer.lock().await;
+
+        assert!(handle.close_flush(Duration::from_millis(50)).await.is_err());
+        assert_eq!(handle.state().await, ConnState::CLOSING);
+    }
+
+    /// Connects to a bare server at `path` and returns the server-side connection.
+    async fn connection_for_path(path: &str) -> Arc<Connection<DuplexStream>> {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+        let _client = connect_to(&wynd, path, peer_addr(1)).await;
+        let clients = wynd.clients.lock().await;
+        let (conn, _) = clients.values().next().expect("client was not registered");
+        Arc::clone(conn)
+    }
+
+    #[tokio::test]
+    async fn test_query_param_reads_strings() {
+        let conn = connection_for_path("/chat?room=lobby&token=a%2Bb&name=Jane+Doe").await;
+
+        assert_eq!(conn.query_param::<String>("room").as_deref(), Some("lobby"));
+        assert_eq!(conn.query_param::<String>("token").as_deref(), Some("a+b"));
+        assert_eq!(
+            conn.query_param::<String>("name").as_deref(),
+            Some("Jane Doe")
+        );
+        assert_eq!(conn.query_params().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_query_param_reads_integers() {
+        let conn = connection_for_path("/?limit=50&offset=-3&limit=10").await;
+
+        assert_eq!(conn.query_param::<u32>("limit"), Some(50));
+        assert_eq!(conn.query_param::<i64>("offset"), Some(-3));
+        // A value that does not parse as the requested type is treated as absent.
+        assert_eq!(conn.query_param::<u32>("offset"), None);
+    }
+
+    #[tokio::test]
+    async fn test_query_param_missing() {
+        let conn = connection_for_path("/?flag").await;
+
+        assert_eq!(conn.query_param::<String>("flag").as_deref(), Some(""));
+        assert_eq!(conn.query_param::<u32>("limit"), None);
+
+        let conn = connection_for_path("/").await;
+        assert!(conn.query_params().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_application_close_code_is_classified() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let (event_tx, mut event_rx) = mpsc::channel(1);
+
+        wynd.on_connection(move |conn| {
+            let event_tx = event_tx.clone();
+            async move {
+                conn.on_close(move |event| {
+                    let event_tx = event_tx.clone();
+                    async move {
+                        let _ = event_tx.send(event).await;
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        client
+            .close(Some(CloseFrame {
+                code: CloseCode::from(4001),
+                reason: "session expired".into(),
+            }))
+            .await
+            .unwrap();
+
+        let event = timeout(Duration::from_millis(500), event_rx.recv())
+            .await
+            .expect("on_close did not fire")
+            .unwrap();
+        assert_eq!(event.code, 4001);
+        assert!(event.is_application_code());
+        assert_eq!(event.cause(), CloseCause::Remote);
+    }
+
+    #[tokio::test]
+    async fn test_send_text_counted_returns_payload_bytes() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let sent = handle.send_text_counted(msg.data).await.unwrap();
+                let _ = handle.send_text(sent.to_string()).await;
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        let message = "héllo wörld 👋";
+        client.send(Message::text(message)).await.unwrap();
+
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some(message));
+        let expected = message.len().to_string();
+        assert_eq!(recv_text(&mut client).await, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_text_only_connection_rejects_binary_with_1003() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let (event_tx, mut event_rx) = mpsc::channel(1);
+
+        wynd.on_connection(move |conn| {
+            let event_tx = event_tx.clone();
+            async move {
+                conn.accept_only(MessageKinds::TEXT);
+                conn.on_text(|msg, handle| async move {
+                    let _ = handle.send_text(msg.data).await;
+                });
+                conn.on_close(move |event| {
+                    let event_tx = event_tx.clone();
+                    async move {
+                        let _ = event_tx.send(event).await;
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("still fine")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("still fine"));
+
+        client.send(Message::binary(vec![1, 2, 3])).await.unwrap();
+        let event = timeout(Duration::from_millis(500), event_rx.recv())
+            .await
+            .expect("on_close did not fire")
+            .unwrap();
+        assert_eq!(event.code, 1003);
+        assert_eq!(event.cause(), CloseCause::UnsupportedData);
+
+        let close = timeout(Duration::from_millis(500), client.next())
+            .await
+            .expect("server did not send a close frame");
+        match close {
+            Some(Ok(Message::Close(Some(frame)))) => {
+                assert_eq!(frame.code, CloseCode::Unsupported)
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_message_kinds_combine() {
+        let both = MessageKinds::TEXT | MessageKinds::BINARY;
+        assert_eq!(both, MessageKinds::ALL);
+        assert!(both.contains(MessageKinds::BINARY));
+        assert!(!MessageKinds::TEXT.contains(MessageKinds::BINARY));
+        assert_eq!(MessageKinds::default(), MessageKinds::ALL);
+    }
+
+    #[tokio::test]
+    async fn test_message_rate_tracks_send_rate() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_msg, _handle| async move {});
+        });
+
+        let mut client = connect(&wynd).await;
+        let handle = Arc::clone(&wynd.clients.lock().await[&0].1);
+        assert_eq!(handle.message_rate(), 0.0);
+
+        // 20 messages per second for 1.5 seconds.
+        for _ in 0..30 {
+            client.send(Message::text("tick")).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let rate = handle.message_rate();
+        assert!((10.0..30.0).contains(&rate), "unexpected rate {}", rate);
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(handle.message_rate() < rate);
+    }
+
+    #[tokio::test]
+    async fn test_client_close_reason_is_sanitized() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_max_close_reason_len(12);
+        let (event_tx, mut event_rx) = mpsc::channel(1);
+
+        wynd.on_connection(move |conn| {
+            let event_tx = event_tx.clone();
+            async move {
+                conn.on_close(move |event| {
+                    let event_tx = event_tx.clone();
+                    async move {
+                        let _ = event_tx.send(event).await;
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        client
+            .close(Some(CloseFrame {
+                code: CloseCode::Normal,
+                reason: "bye\r\nFAKE LOG LINE\u{1b}[31m".into(),
+            }))
+            .await
+            .unwrap();
+
+        let event = timeout(Duration::from_millis(500), event_rx.recv())
+            .await
+            .expect("on_close did not fire")
+            .unwrap();
+        assert_eq!(event.reason, "byeFAKE LOG ");
+    }
+
+    /// Creates a server with a short keepalive that reports close events on a channel.
+    fn create_keepalive_server() -> (Wynd<DuplexStream>, mpsc::Receiver<CloseEvent>) {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_keepalive(Duration::from_millis(50), Duration::from_millis(50));
+        let (event_tx, event_rx) = mpsc::channel(1);
+
+        wynd.on_connection(move |conn| {
+            let event_tx = event_tx.clone();
+            async move {
+                conn.on_close(move |event| {
+                    let event_tx = event_tx.clone();
+                    async move {
+                        let _ = event_tx.send(event).await;
+                    }
+                });
+            }
+        });
+
+        (wynd, event_rx)
+    }
+
+    #[tokio::test]
+    async fn test_pong_read_after_a_slow_handler_keeps_the_connection() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_keepalive(Duration::from_millis(50), Duration::from_millis(100));
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                // Outlives the pong timeout while the client's pong is in flight.
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                let _ = handle.send_text("done").await;
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        for _ in 0..5 {
+            // Let a ping go out unread, send a message, and only then read the
+            // ping, so its pong arrives while the handler is running.
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            client.send(Message::text("work")).await.unwrap();
+            let reply = timeout(Duration::from_millis(500), async {
+                loop {
+                    match client.next().await {
+                        Some(Ok(Message::Ping(_))) => continue,
+                        other => return other,
+                    }
+                }
+            })
+            .await
+            .expect("no reply from the handler");
+            assert_eq!(reply.unwrap().unwrap(), Message::text("done"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_pong_reports_ping_timeout() {
+        let (wynd, mut event_rx) = create_keepalive_server();
+
+        // The client never reads, so it never answers the server's pings.
+        let _client = connect(&wynd).await;
+
+        let event = timeout(Duration::from_millis(500), event_rx.recv())
+            .await
+            .expect("on_close did not fire")
+            .unwrap();
+        assert_eq!(event.cause(), CloseCause::PingTimeout);
+        assert_eq!(event.code, 1006);
+        assert_eq!(event.reason, "ping timeout");
+    }
+
+    #[tokio::test]
+    async fn test_answered_pings_keep_connection_open() {
+        let (wynd, mut event_rx) = create_keepalive_server();
+
+        // Reading lets the client answer each ping with a pong.
+        let mut client = connect(&wynd).await;
+        let reader = tokio::spawn(async move { while client.next().await.is_some() {} });
+
+        assert!(timeout(Duration::from_millis(300), event_rx.recv())
+            .await
+            .is_err());
+        reader.abort();
+    }
+
+    /// Reverses the text of outbound text frames.
+    fn reverse_text(message: Message) -> Message {
+        match message {
+            Message::Text(text) => Message::text(text.chars().rev().collect::<String>()),
+            other => other,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbound_transform_applies_to_sends() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.set_outbound_transform(reverse_text);
+            conn.on_open(|handle| async move {
+                let _ = handle.send_text("hello").await;
+            })
+            .await;
+        });
+
+        let mut client = connect(&wynd).await;
+
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("olleh"));
+    }
+
+    #[tokio::test]
+    async fn test_outbound_transform_is_per_connection() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            if conn.query_param::<String>("encoding").as_deref() == Some("reversed") {
+                conn.set_outbound_transform(reverse_text);
+            }
+            conn.on_text(|msg, handle| async move {
+                handle.broadcast.emit_text(msg.data).await;
+            });
+        });
+
+        let mut plain = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut reversed = connect_to(&wynd, "/?encoding=reversed", peer_addr(2)).await;
+
+        plain.send(Message::text("abc")).await.unwrap();
+
+        assert_eq!(recv_text(&mut plain).await.as_deref(), Some("abc"));
+        assert_eq!(recv_text(&mut reversed).await.as_deref(), Some("cba"));
+    }
+
+    /// Creates a server whose fallible handler rejects anything but "ping".
+    fn create_fallible_server() -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text_try(|msg, handle| async move {
+                if msg.data != "ping" {
+                    return Err(HandlerError::new("unknown method").with_code(404));
+                }
+                let _ = handle.send_text("pong").await;
+                Ok(())
+            });
+        });
+        wynd
+    }
+
+    #[tokio::test]
+    async fn test_handler_error_is_not_sent_by_default() {
+        let wynd = create_fallible_server();
+        let mut client = connect(&wynd).await;
+
+        client.send(Message::text("nope")).await.unwrap();
+        client.send(Message::text("ping")).await.unwrap();
+
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("pong"));
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn test_handler_error_is_sent_as_json_when_enabled() {
+        let mut wynd = create_fallible_server();
+        wynd.set_auto_error_reply(true);
+        let mut client = connect(&wynd).await;
+
+        client.send(Message::text("nope")).await.unwrap();
+
+        let reply = recv_text(&mut client).await.unwrap();
+        let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(
+            reply,
+            serde_json::json!({ "error": "unknown method", "code": 404 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_text_and_close_sends_text_then_close() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                assert!(matches!(
+                    handle.send_text_and_close("lost", 1006, "bad").await,
+                    Err(WyndError::InvalidPayload(_))
+                ));
+                assert!(matches!(
+                    handle
+                        .send_text_and_close("lost", 4001, "x".repeat(124).as_str())
+                        .await,
+                    Err(WyndError::InvalidPayload(_))
+                ));
+                let _ = handle
+                    .send_text_and_close("kicked: spamming", 4001, "kicked")
+                    .await;
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("spam")).await.unwrap();
+
+        // Rejected frames send nothing, so the text comes from the valid call.
+        let text = timeout(Duration::from_millis(500), client.next())
+            .await
+            .unwrap();
+        assert_eq!(text.unwrap().unwrap(), Message::text("kicked: spamming"));
+        let close = timeout(Duration::from_millis(500), client.next())
+            .await
+            .unwrap();
+        match close.unwrap().unwrap() {
+            Message::Close(Some(frame)) => {
+                assert_eq!(frame.code, CloseCode::from(4001));
+                assert_eq!(frame.reason, "kicked");
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_data_persists_across_handlers() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct UserId(u64);
+        #[derive(Clone, Debug, PartialEq)]
+        struct Session(String);
+
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                assert_eq!(handle.data::<UserId>(), None);
+                handle.set_data(UserId(1));
+                handle.set_data(UserId(42));
+                handle.set_data(Session("abc".to_string()));
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                let UserId(user) = handle.data::<UserId>().unwrap();
+                let Session(session) = handle.data::<Session>().unwrap();
+                let _ = handle
+                    .send_text(format!("{}:{}:{}", user, session, msg.data))
+                    .await;
+            });
+        });
+
+        let mut first = connect(&wynd).await;
+        first.send(Message::text("hi")).await.unwrap();
+        assert_eq!(recv_text(&mut first).await.as_deref(), Some("42:abc:hi"));
+
+        // Each connection has its own values.
+        let _second = connect(&wynd).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let clients = wynd.clients.lock().await;
+        clients[&1].1.set_data(UserId(7));
+        assert_eq!(clients[&1].1.data::<UserId>(), Some(UserId(7)));
+        assert_eq!(clients[&0].1.data::<UserId>(), Some(UserId(42)));
+    }
+
+    #[tokio::test]
+    async fn test_close_with_sends_code_and_reason() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                for code in [999, 1005, 1006, 1015, 2999, 5000] {
+                    assert!(matches!(
+                        handle.close_with(code, "bad").await,
+                        Err(WyndError::InvalidPayload(_))
+                    ));
+                }
+                assert!(matches!(
+                    handle.close_with(1000, "x".repeat(124)).await,
+                    Err(WyndError::InvalidPayload(_))
+                ));
+                let _ = handle.send_text("still open").await;
+                handle.close_with(1000, "bye").await.unwrap();
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("quit")).await.unwrap();
+
+        // Rejected codes send nothing, so the connection stays usable.
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("still open"));
+        let close = timeout(Duration::from_millis(500), client.next())
+            .await
+            .unwrap();
+        match close.unwrap().unwrap() {
+            Message::Close(Some(frame)) => {
+                assert_eq!(frame.code, CloseCode::Normal);
+                assert_eq!(frame.reason, "bye");
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn test_request_reconnect_sends_hint_then_1012() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                handle.request_reconnect(2500, "rebalancing").await.unwrap();
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("drain")).await.unwrap();
+
+        let hint = recv_text(&mut client).await.unwrap();
+        let hint: serde_json::Value = serde_json::from_str(&hint).unwrap();
+        assert_eq!(
+            hint,
+            serde_json::json!({ "type": "reconnect", "delay_ms": 2500, "reason": "rebalancing" })
+        );
+        let close = timeout(Duration::from_millis(500), client.next())
+            .await
+            .unwrap();
+        match close.unwrap().unwrap() {
+            Message::Close(Some(frame)) => {
+                assert_eq!(frame.code, CloseCode::Restart);
+                assert_eq!(frame.reason, "rebalancing");
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_total_bytes_limit_closes_connection() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_max_total_bytes_per_connection(100);
+        let (event_tx, mut event_rx) = mpsc::channel(1);
+        let handled = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let handled_clone = Arc::clone(&handled);
+
+        wynd.on_connection(move |conn| {
+            let event_tx = event_tx.clone();
+            let handled = Arc::clone(&handled_clone);
+            async move {
+                conn.on_text(move |_, _| {
+                    let handled = Arc::clone(&handled);
+                    async move {
+                        handled.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                });
+                conn.on_close(move |event| {
+                    let event_tx = event_tx.clone();
+                    async move {
+                        let _ = event_tx.send(event).await;
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        // Each message is small, but 30 of them add up to 300 bytes.
+        for _ in 0..30 {
+            if client.send(Message::text("0123456789")).await.is_err() {
+                break;
+            }
+        }
+
+        let event = timeout(Duration::from_millis(500), event_rx.recv())
+            .await
+            .expect("on_close did not fire")
+            .unwrap();
+        assert_eq!(event.code, 1009);
+        assert_eq!(event.cause(), CloseCause::TotalBytesExceeded);
+        assert_eq!(handled.load(std::sync::atomic::Ordering::SeqCst), 10);
+
+        let close = loop {
+            match timeout(Duration::from_millis(500), client.next()).await {
+                Ok(Some(Ok(Message::Close(frame)))) => break frame,
+                Ok(Some(Ok(_))) => continue,
+                other => panic!("expected a close frame, got {:?}", other),
+            }
+        };
+        assert_eq!(close.unwrap().code, CloseCode::Size);
+    }
+
+    #[tokio::test]
+    async fn test_on_error_reports_invalid_utf8_before_closing() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let (error_tx, mut error_rx) = mpsc::channel(1);
+
+        wynd.on_connection(move |conn| {
+            let error_tx = error_tx.clone();
+            async move {
+                conn.on_error(move |err, handle| {
+                    let error_tx = error_tx.clone();
+                    async move {
+                        let _ = error_tx.send((err, handle.state().await)).await;
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        let invalid = Frame::message(vec![0xff, 0xfe], OpCode::Data(Data::Text), true);
+        client.send(Message::Frame(invalid)).await.unwrap();
+
+        let (err, state) = timeout(Duration::from_millis(500), error_rx.recv())
+            .await
+            .expect("on_error did not fire")
+            .unwrap();
+        assert_eq!(err.kind, ConnectionErrorKind::Utf8);
+        assert!(!err.message.is_empty());
+        // The handler runs before the connection is marked closed.
+        assert_eq!(state, ConnState::OPEN);
+
+        let close = loop {
+            match timeout(Duration::from_millis(500), client.next()).await {
+                Ok(Some(Ok(Message::Close(frame)))) => break frame,
+                Ok(Some(Ok(_))) => continue,
+                other => panic!("expected a close frame, got {:?}", other),
+            }
+        };
+        assert_eq!(close.unwrap().code, CloseCode::Invalid);
+    }
+
+    #[tokio::test]
+    async fn test_paused_reads_apply_backpressure_until_resumed() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let (handle_tx, mut handle_rx) = mpsc::channel(1);
+        let received = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let received_clone = Arc::clone(&received);
+
+        wynd.on_connection(move |conn| {
+            let handle_tx = handle_tx.clone();
+            let received = Arc::clone(&received_clone);
+            async move {
+                conn.on_open(move |handle| {
+                    let handle_tx = handle_tx.clone();
+                    async move {
+                        handle.pause_reads();
+                        let _ = handle_tx.send(handle).await;
+                    }
+                })
+                .await;
+                conn.on_binary(move |_, _| {
+                    let received = Arc::clone(&received);
+                    async move {
+                        received.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        let handle = handle_rx.recv().await.unwrap();
+        assert!(handle.reads_paused());
+
+        // The in-memory pipe holds 64 KiB, so sends stall once it is full.
+        let payload = vec![0u8; 16 * 1024];
+        let mut sent = 0;
+        while sent < 32 {
+            let send = client.send(Message::binary(payload.clone()));
+            if timeout(Duration::from_millis(100), send).await.is_err() {
+                break;
+            }
+            sent += 1;
+        }
+        assert!(sent < 32, "client was never blocked");
+        assert_eq!(received.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        handle.resume_reads();
+        timeout(Duration::from_millis(500), client.flush())
+            .await
+            .expect("client still blocked after resume")
+            .unwrap();
+
+        let expected = sent + 1;
+        timeout(Duration::from_millis(500), async {
+            while received.load(std::sync::atomic::Ordering::SeqCst) < expected {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("buffered messages were not delivered after resume");
+    }
+
+    #[tokio::test]
+    async fn test_full_send_queue_reports_backpressure_without_blocking() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.with_send_queue_capacity(2);
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                // Stall the writer task so queued frames pile up.
+                let writer = handle.writer.lock().await;
+                let mut accepted = 0;
+                let mut backpressure = false;
+                for n in 0..10 {
+                    match handle.send_text(format!("msg {}", n)).await {
+                        Ok(()) => accepted += 1,
+                        Err(e) => {
+                            backpressure = e.is_backpressure();
+                            break;
+                        }
+                    }
+                }
+                drop(writer);
+
+                let _ = handle
+                    .send_text_and_close(format!("{} {}", accepted, backpressure), 1000, "")
+                    .await;
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("go")).await.unwrap();
+
+        let mut texts = Vec::new();
+        while let Some(text) = recv_text(&mut client).await {
+            texts.push(text);
+        }
+        let report = texts.pop().unwrap();
+        let (accepted, backpressure) = report.split_once(' ').unwrap();
+        let accepted: usize = accepted.parse().unwrap();
+        assert!((2..=3).contains(&accepted), "accepted {}", accepted);
+        assert_eq!(backpressure, "true");
+        let expected: Vec<String> = (0..accepted).map(|n| format!("msg {}", n)).collect();
+        assert_eq!(texts, expected);
+    }
+
+    #[tokio::test]
+    async fn test_send_after_close_reports_connection_closed() {
+        let outcome = Arc::new(std::sync::Mutex::new(None));
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let recorded = Arc::clone(&outcome);
+        wynd.on_connection(move |conn| {
+            let recorded = Arc::clone(&recorded);
+            async move {
+                conn.on_text(move |_, handle| {
+                    let recorded = Arc::clone(&recorded);
+                    async move {
+                        handle.close().await.unwrap();
+                        let text = handle.send_text("too late").await;
+                        let binary = handle.send_binary(vec![1]).await;
+                        *recorded.lock().unwrap() = Some(
+                            matches!(text, Err(WyndError::ConnectionClosed))
+                                && matches!(binary, Err(WyndError::ConnectionClosed)),
+                        );
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("bye")).await.unwrap();
+        assert!(matches!(client.next().await, Some(Ok(Message::Close(_)))));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*outcome.lock().unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_send_many_delivers_batches_in_order_and_reports_progress() {
+        let failed_after = Arc::new(std::sync::Mutex::new(None));
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let recorded = Arc::clone(&failed_after);
+        wynd.on_connection(move |conn| {
+            let recorded = Arc::clone(&recorded);
+            async move {
+                conn.on_text(move |_, handle| {
+                    let recorded = Arc::clone(&recorded);
+                    async move {
+                        handle
+                            .send_many_text(&["one", "two", "three"])
+                            .await
+                            .unwrap();
+                        handle
+                            .send_many_binary(vec![vec![1], vec![2, 2]])
+                            .await
+                            .unwrap();
+                        handle.close().await.unwrap();
+
+                        let err = handle.send_many_text(&["late"]).await.unwrap_err();
+                        *recorded.lock().unwrap() = Some(err.sent());
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("go")).await.unwrap();
+
+        for expected in ["one", "two", "three"] {
+            assert_eq!(recv_text(&mut client).await.as_deref(), Some(expected));
+        }
+        for expected in [vec![1], vec![2, 2]] {
+            match client.next().await {
+                Some(Ok(Message::Binary(data))) => assert_eq!(data.to_vec(), expected),
+                other => panic!("expected a binary frame, got {:?}", other),
+            }
+        }
+        assert!(matches!(client.next().await, Some(Ok(Message::Close(_)))));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*failed_after.lock().unwrap(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_message_closes_with_1009() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.with_max_message_size(1024).with_max_frame_size(1024);
+        let (event_tx, mut event_rx) = mpsc::channel(1);
+        let handled = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let handled_clone = Arc::clone(&handled);
+
+        wynd.on_connection(move |conn| {
+            let event_tx = event_tx.clone();
+            let handled = Arc::clone(&handled_clone);
+            async move {
+                conn.on_binary(move |_, _| {
+                    let handled = Arc::clone(&handled);
+                    async move {
+                        handled.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                });
+                conn.on_close(move |event| {
+                    let event_tx = event_tx.clone();
+                    async move {
+                        let _ = event_tx.send(event).await;
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::binary(vec![0; 1024])).await.unwrap();
+        client.send(Message::binary(vec![0; 4096])).await.unwrap();
+
+        let event = timeout(Duration::from_millis(500), event_rx.recv())
+            .await
+            .expect("on_close did not fire")
+            .unwrap();
+        assert_eq!(event.code, 1009);
+        assert_eq!(event.cause(), CloseCause::MessageTooLarge);
+        assert_eq!(handled.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        match timeout(Duration::from_millis(500), client.next()).await {
+            Ok(Some(Ok(Message::Close(Some(frame))))) => {
+                assert_eq!(frame.code, CloseCode::Size);
+                assert_eq!(frame.reason, "message too large");
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_muted_inbound_discards_messages_but_keeps_sending() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let handled = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let handled_clone = Arc::clone(&handled);
+
+        wynd.on_connection(move |conn| {
+            let handled = Arc::clone(&handled_clone);
+            async move {
+                let muted_conn = Arc::clone(&conn);
+                let text_handled = Arc::clone(&handled);
+                conn.on_text(move |msg, handle| {
+                    let muted_conn = Arc::clone(&muted_conn);
+                    let handled = Arc::clone(&text_handled);
+                    async move {
+                        handled.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        if msg.data == "mute" {
+                            muted_conn.mute_inbound();
+                            let _ = handle.send_text("muted").await;
+                        }
+                    }
+                });
+                conn.on_binary(move |_, _| {
+                    let handled = Arc::clone(&handled);
+                    async move {
+                        handled.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("mute")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("muted"));
+
+        client.send(Message::text("ignored")).await.unwrap();
+        client.send(Message::binary(vec![1, 2, 3])).await.unwrap();
+        client.send(Message::Ping(vec![7].into())).await.unwrap();
+        match timeout(Duration::from_millis(500), client.next()).await {
+            Ok(Some(Ok(Message::Pong(payload)))) => assert_eq!(payload.as_ref(), [7]),
+            other => panic!("expected a pong, got {:?}", other),
+        }
+        assert_eq!(handled.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let id = wynd.client_ids().await[0];
+        wynd.with_client(id, |handle| async move {
+            handle.send_text("push").await.unwrap();
+        })
+        .await
+        .unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("push"));
+        assert_eq!(handled.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_on_text_registered_while_message_loop_runs_fires() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            let running = Arc::clone(&conn);
+            conn.on_binary(move |_, handle| {
+                let running = Arc::clone(&running);
+                async move {
+                    // The message loop is running: swap in a text handler.
+                    running.on_text(|msg, handle| async move {
+                        let _ = handle.send_text(&format!("late: {}", msg.data)).await;
+                    });
+                    let _ = handle.send_text("registered").await;
+                }
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("before")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await, None);
+
+        client.send(Message::binary(vec![0])).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("registered"));
+
+        client.send(Message::text("after")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("late: after"));
+    }
+
+    #[tokio::test]
+    async fn test_on_message_falls_back_and_can_take_precedence() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            let preferring = Arc::clone(&conn);
+            conn.on_text(move |msg, handle| {
+                let preferring = Arc::clone(&preferring);
+                async move {
+                    if msg.data == "prefer" {
+                        preferring.prefer_on_message(true);
+                    }
+                    let _ = handle.send_text(&format!("on_text: {}", msg.data)).await;
+                }
+            });
+            conn.on_message(|msg, handle| async move {
+                let reply = match msg {
+                    IncomingMessage::Text(text) => format!("on_message text: {}", text),
+                    IncomingMessage::Binary(data) => format!("on_message binary: {:?}", data),
+                };
+                let _ = handle.send_text(&reply).await;
+            });
+        });
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("hi")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("on_text: hi"));
+        client.send(Message::binary(vec![1, 2])).await.unwrap();
+        assert_eq!(
+            recv_text(&mut client).await.as_deref(),
+            Some("on_message binary: [1, 2]")
+        );
+
+        client.send(Message::text("prefer")).await.unwrap();
+        assert_eq!(
+            recv_text(&mut client).await.as_deref(),
+            Some("on_text: prefer")
+        );
+        client.send(Message::text("hi")).await.unwrap();
+        assert_eq!(
+            recv_text(&mut client).await.as_deref(),
+            Some("on_message text: hi")
+        );
+        assert_eq!(recv_text(&mut client).await, None);
+    }
+
+    async fn mock_handle(stream: MockStream) -> ConnectionHandle<MockStream> {
+        let ws_stream = WebSocketStream::from_raw_socket(
+            stream,
+            tokio_tungstenite::tungstenite::protocol::Role::Server,
+            None,
+        )
+        .await;
+        let (writer, _reader) = futures::StreamExt::split(ws_stream);
+
+        ConnectionHandle::new(
+            1,
+            Arc::new(Mutex::new(writer)),
+            "127.0.0.1:8080".parse().unwrap(),
+            Broadcaster::new(Arc::new(Mutex::new(HashMap::new())), 1),
+            Arc::new(Mutex::new(ConnState::OPEN)),
+            Arc::new(tokio::sync::mpsc::channel(100).0),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_last_error_records_failed_sends_and_clears_on_success() {
+        let mut broken = MockStream::new();
+        broken.closed = true;
+        let handle = mock_handle(broken).await;
+        assert_eq!(handle.last_error(), None);
+
+        assert!(handle.send_text("hello").await.is_err());
+        let error = handle.last_error().expect("failed send is recorded");
+        assert!(error.contains("Stream closed"), "{}", error);
+        assert_eq!(handle.clone().last_error(), Some(error));
+
+        // Close frames are tracked like data sends.
+        let mut broken = MockStream::new();
+        broken.closed = true;
+        let handle = mock_handle(broken).await;
+        assert!(handle.close_with(1000, "bye").await.is_err());
+        assert!(handle.last_error().is_some());
+
+        let handle = mock_handle(MockStream::new()).await;
+        handle.record_error(&"earlier failure");
+        assert_eq!(handle.last_error().as_deref(), Some("earlier failure"));
+        handle.send_binary(vec![1, 2, 3]).await.unwrap();
+        assert_eq!(handle.last_error(), None);
+    }
 }
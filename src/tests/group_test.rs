@@ -0,0 +1,282 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::{IpAddr, SocketAddr};
+    use std::time::Duration;
+
+    use futures::SinkExt;
+    use tokio::io::DuplexStream;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::tests::utils::{connect, connect_to, peer_addr, recv_text};
+    use crate::types::CloseCause;
+    use crate::wynd::Wynd;
+
+    /// Helper function to create a server whose connections just open
+    fn create_test_server() -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|_handle| async move {}).await;
+        });
+
+        wynd
+    }
+
+    #[tokio::test]
+    async fn group_broadcast_reaches_only_group_members() {
+        let wynd = create_test_server();
+
+        let mut first = connect(&wynd).await;
+        let mut second = connect(&wynd).await;
+        let mut third = connect(&wynd).await;
+
+        wynd.add_to_group("eu-west", 0).await;
+        wynd.add_to_group("eu-west", 1).await;
+        wynd.add_to_group("us-east", 2).await;
+
+        let delivered = wynd.group_broadcast("eu-west", "hello eu").await;
+
+        assert_eq!(delivered, 2);
+        assert_eq!(recv_text(&mut first).await.as_deref(), Some("hello eu"));
+        assert_eq!(recv_text(&mut second).await.as_deref(), Some("hello eu"));
+        assert_eq!(recv_text(&mut third).await, None);
+    }
+
+    #[tokio::test]
+    async fn numeric_groups_and_removal() {
+        let wynd = create_test_server();
+
+        let mut first = connect(&wynd).await;
+        let mut second = connect(&wynd).await;
+
+        wynd.add_to_group(7, 0).await;
+        wynd.add_to_group(7, 1).await;
+        assert_eq!(wynd.group_members(7).await, vec![0, 1]);
+
+        wynd.remove_from_group(7, 1).await;
+        assert_eq!(wynd.group_members(7).await, vec![0]);
+
+        assert_eq!(wynd.group_broadcast(7, "shard 7").await, 1);
+        assert_eq!(recv_text(&mut first).await.as_deref(), Some("shard 7"));
+        assert_eq!(recv_text(&mut second).await, None);
+
+        wynd.remove_from_group(7, 0).await;
+        assert!(wynd.groups.lock().await.is_empty());
+        assert_eq!(wynd.group_broadcast(7, "nobody").await, 0);
+    }
+
+    #[tokio::test]
+    async fn handle_can_join_groups() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                handle.add_to_group("mobile").await;
+            })
+            .await;
+        });
+
+        let mut client = connect(&wynd).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(wynd.group_members("mobile").await, vec![0]);
+        assert_eq!(
+            wynd.group_broadcast_binary("mobile", vec![1, 2, 3]).await,
+            1
+        );
+
+        let _ = client.send(Message::Close(None)).await;
+    }
+
+    #[tokio::test]
+    async fn disconnect_removes_connection_from_groups() {
+        let wynd = create_test_server();
+
+        let mut client = connect(&wynd).await;
+        wynd.add_to_group("eu-west", 0).await;
+
+        client.send(Message::Close(None)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(wynd.group_members("eu-west").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_to_user_reaches_every_connection_of_that_user() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                handle.set_user_id(msg.data).await;
+            });
+        });
+
+        let mut phone = connect(&wynd).await;
+        let mut laptop = connect(&wynd).await;
+        let mut other = connect(&wynd).await;
+
+        phone.send(Message::text("alice")).await.unwrap();
+        laptop.send(Message::text("alice")).await.unwrap();
+        other.send(Message::text("bob")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(wynd.send_to_user("alice", "hi alice").await, 2);
+        assert_eq!(recv_text(&mut phone).await.as_deref(), Some("hi alice"));
+        assert_eq!(recv_text(&mut laptop).await.as_deref(), Some("hi alice"));
+        assert_eq!(recv_text(&mut other).await, None);
+
+        laptop.send(Message::Close(None)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(wynd.send_to_user("alice", "still here?").await, 1);
+        assert_eq!(recv_text(&mut phone).await.as_deref(), Some("still here?"));
+        assert_eq!(wynd.send_to_user("carol", "nobody").await, 0);
+    }
+
+    #[tokio::test]
+    async fn send_to_addr_reaches_every_connection_from_that_ip() {
+        let wynd = create_test_server();
+        let office = IpAddr::from([10, 0, 0, 7]);
+
+        let mut first = connect_to(&wynd, "/", SocketAddr::new(office, 40001)).await;
+        let mut second = connect_to(&wynd, "/", SocketAddr::new(office, 40002)).await;
+        let mut elsewhere = connect_to(&wynd, "/", peer_addr(40003)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(wynd.send_to_addr(office, "office notice").await, 2);
+        assert_eq!(
+            recv_text(&mut first).await.as_deref(),
+            Some("office notice")
+        );
+        assert_eq!(
+            recv_text(&mut second).await.as_deref(),
+            Some("office notice")
+        );
+        assert_eq!(recv_text(&mut elsewhere).await, None);
+        assert_eq!(
+            wynd.send_to_addr(IpAddr::from([192, 0, 2, 1]), "nobody")
+                .await,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn tag_counts_follow_tags_and_disconnects() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                match msg.data.split_once(':') {
+                    Some(("untag", tag)) => handle.remove_tag(tag).await,
+                    Some(("tag", tag)) => handle.add_tag(tag).await,
+                    _ => {}
+                }
+                let _ = handle.send_text("ok").await;
+            });
+        });
+
+        let mut phone = connect(&wynd).await;
+        let mut tablet = connect(&wynd).await;
+        let mut browser = connect(&wynd).await;
+        for (client, command) in [
+            (&mut phone, "tag:mobile"),
+            (&mut tablet, "tag:mobile"),
+            (&mut browser, "tag:web"),
+        ] {
+            client.send(Message::text(command)).await.unwrap();
+            assert_eq!(recv_text(client).await.as_deref(), Some("ok"));
+        }
+        for command in ["tag:beta", "tag:mobile"] {
+            phone.send(Message::text(command)).await.unwrap();
+            assert_eq!(recv_text(&mut phone).await.as_deref(), Some("ok"));
+        }
+
+        assert_eq!(
+            wynd.tag_counts().await,
+            HashMap::from([
+                ("mobile".to_string(), 2),
+                ("beta".to_string(), 1),
+                ("web".to_string(), 1),
+            ])
+        );
+
+        phone.send(Message::text("untag:beta")).await.unwrap();
+        assert_eq!(recv_text(&mut phone).await.as_deref(), Some("ok"));
+        tablet.send(Message::Close(None)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(
+            wynd.tag_counts().await,
+            HashMap::from([("mobile".to_string(), 1), ("web".to_string(), 1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn disconnect_cleanup_runs_alongside_an_application_close_handler() {
+        let (closed_tx, mut closed_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(move |conn| {
+            let closed_tx = closed_tx.clone();
+            async move {
+                conn.on_close(move |event| {
+                    let _ = closed_tx.send(event.code);
+                    async {}
+                });
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        wynd.add_to_group("g", 0).await;
+        assert_eq!(wynd.clients_count().await, 1);
+
+        client.close(None).await.unwrap();
+        let code = tokio::time::timeout(Duration::from_secs(1), closed_rx.recv())
+            .await
+            .expect("application close handler did not run");
+        assert_eq!(code, Some(1005));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(wynd.clients_count().await, 0);
+        assert!(wynd.group_members("g").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn dropped_connections_are_cleaned_up_without_a_close_handshake() {
+        let (closed_tx, mut closed_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(move |conn| {
+            let closed_tx = closed_tx.clone();
+            async move {
+                conn.on_open(|handle| async move {
+                    handle.set_user_id("alice").await;
+                    handle.add_tag("beta").await;
+                })
+                .await;
+                conn.on_close(move |event| {
+                    let _ = closed_tx.send(event.cause());
+                    async {}
+                });
+            }
+        });
+
+        let client = connect(&wynd).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        wynd.add_to_group("g", 0).await;
+        assert_eq!(wynd.clients_count().await, 1);
+        assert_eq!(wynd.users.lock().await.len(), 1);
+
+        // The peer vanishes without sending a close frame.
+        drop(client);
+        let cause = tokio::time::timeout(Duration::from_secs(1), closed_rx.recv())
+            .await
+            .expect("close handler did not run");
+        assert_eq!(cause, Some(CloseCause::Abnormal));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(wynd.clients_count().await, 0);
+        assert!(wynd.group_members("g").await.is_empty());
+        assert!(wynd.users.lock().await.is_empty());
+        assert!(wynd.tag_counts().await.is_empty());
+    }
+}
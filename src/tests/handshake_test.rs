@@ -0,0 +1,299 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::io::DuplexStream;
+    use tokio_tungstenite::{
+        client_async,
+        tungstenite::{
+            client::IntoClientRequest,
+            http::{HeaderMap, HeaderValue, Response, StatusCode},
+            Error,
+        },
+    };
+
+    use crate::tests::utils::{peer_addr, TestClient};
+    use crate::wynd::{negotiated_extensions, request_headers, Wynd};
+    use crate::IpNet;
+
+    /// Performs a handshake offering `protocols` and returns the server's response.
+    async fn handshake_offering(
+        wynd: &Wynd<DuplexStream>,
+        protocols: &str,
+    ) -> Response<Option<Vec<u8>>> {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let mut request = "ws://localhost/".into_client_request().unwrap();
+        request.headers_mut().insert(
+            "sec-websocket-protocol",
+            HeaderValue::from_str(protocols).unwrap(),
+        );
+
+        let (server, client) = tokio::join!(
+            wynd.handle_connection(server_io, peer_addr(1)),
+            client_async(request, client_io)
+        );
+        server.expect("server side of the handshake should succeed");
+        client
+            .expect("client side of the handshake should succeed")
+            .1
+    }
+
+    #[tokio::test]
+    async fn echoes_first_offered_subprotocol() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.echo_subprotocol(true);
+
+        let response = handshake_offering(&wynd, "graphql-ws, chat").await;
+
+        assert_eq!(
+            response.headers().get("sec-websocket-protocol"),
+            Some(&HeaderValue::from_static("graphql-ws"))
+        );
+    }
+
+    #[tokio::test]
+    async fn strict_client_rejects_handshake_without_echo() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let mut request = "ws://localhost/".into_client_request().unwrap();
+        request.headers_mut().insert(
+            "sec-websocket-protocol",
+            HeaderValue::from_static("graphql-ws"),
+        );
+
+        let (server, client) = tokio::join!(
+            wynd.handle_connection(server_io, peer_addr(1)),
+            client_async(request, client_io)
+        );
+
+        // Echoing is opt-in, so the server answers without the header and a
+        // client that offered a protocol aborts.
+        assert!(server.is_ok());
+        assert!(client.is_err());
+    }
+
+    /// Attempts a handshake from `addr` and returns the client if both sides
+    /// succeeded.
+    ///
+    /// Keep the client alive for as long as the connection should stay
+    /// registered: dropping it disconnects.
+    async fn handshake_from(wynd: &Wynd<DuplexStream>, addr: SocketAddr) -> Option<TestClient> {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let (server, client) = tokio::join!(
+            wynd.handle_connection(server_io, addr),
+            client_async("ws://localhost/", client_io)
+        );
+        match (server, client) {
+            (Ok(_), Ok((client, _))) => Some(client),
+            _ => None,
+        }
+    }
+
+    fn networks(cidrs: &[&str]) -> Vec<IpNet> {
+        cidrs.iter().map(|cidr| cidr.parse().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn allowlist_admits_only_listed_networks() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_ip_allowlist(networks(&["10.0.0.0/8", "2001:db8::/32"]));
+
+        let mut admitted = Vec::new();
+        for addr in [
+            "10.1.2.3:5000",
+            "[2001:db8::1]:5000",
+            "[::ffff:10.9.9.9]:5000",
+        ] {
+            let client = handshake_from(&wynd, addr.parse().unwrap()).await;
+            admitted.push(client.expect("listed network is admitted"));
+        }
+        assert!(handshake_from(&wynd, "192.168.1.1:5000".parse().unwrap())
+            .await
+            .is_none());
+        assert_eq!(wynd.clients.lock().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn blocklist_refuses_listed_networks() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_ip_allowlist(networks(&["10.0.0.0/8"]));
+        wynd.set_ip_blocklist(networks(&["10.6.6.0/24"]));
+
+        assert!(handshake_from(&wynd, "10.6.6.6:5000".parse().unwrap())
+            .await
+            .is_none());
+        assert!(handshake_from(&wynd, "10.6.7.1:5000".parse().unwrap())
+            .await
+            .is_some());
+
+        let mut open: Wynd<DuplexStream> = Wynd::new();
+        open.set_ip_blocklist(networks(&["203.0.113.7/32"]));
+        assert!(handshake_from(&open, "203.0.113.7:5000".parse().unwrap())
+            .await
+            .is_none());
+        assert!(handshake_from(&open, peer_addr(1)).await.is_some());
+    }
+
+    /// Connects a client offering the given `Sec-WebSocket-Extensions` header,
+    /// if any, and returns the server's `compression_enabled` flag.
+    async fn compression_flag(wynd: &Wynd<DuplexStream>, extensions: Option<&str>) -> bool {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let mut request = "ws://localhost/".into_client_request().unwrap();
+        if let Some(extensions) = extensions {
+            request.headers_mut().insert(
+                "sec-websocket-extensions",
+                HeaderValue::from_str(extensions).unwrap(),
+            );
+        }
+
+        let (server, client) = tokio::join!(
+            wynd.handle_connection(server_io, peer_addr(1)),
+            client_async(request, client_io)
+        );
+        server.expect("server side of the handshake should succeed");
+        client.expect("client side of the handshake should succeed");
+
+        let clients = wynd.clients.lock().await;
+        let newest = clients.keys().max().unwrap();
+        clients[newest].0.compression_enabled()
+    }
+
+    #[tokio::test]
+    async fn compression_flag_reflects_negotiation() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+
+        assert!(!compression_flag(&wynd, None).await);
+        // The server does not accept permessage-deflate yet, so an offer from
+        // the client is not negotiated either.
+        assert!(!compression_flag(&wynd, Some("permessage-deflate; client_max_window_bits")).await);
+    }
+
+    #[tokio::test]
+    async fn negotiated_extensions_reflect_the_handshake() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+
+        let newest_extensions = || async {
+            let clients = wynd.clients.lock().await;
+            let newest = clients.keys().max().unwrap();
+            clients[newest].0.negotiated_extensions()
+        };
+
+        assert!(!compression_flag(&wynd, None).await);
+        assert!(newest_extensions().await.is_empty());
+
+        // permessage-deflate is declined, so an offer is not listed either.
+        assert!(!compression_flag(&wynd, Some("permessage-deflate; client_max_window_bits")).await);
+        assert!(newest_extensions().await.is_empty());
+    }
+
+    #[test]
+    fn negotiated_extensions_lists_accepted_extension_names() {
+        let mut headers = HeaderMap::new();
+        assert!(negotiated_extensions(&headers).is_empty());
+
+        headers.append(
+            "sec-websocket-extensions",
+            HeaderValue::from_static("permessage-deflate; server_no_context_takeover, x-trace"),
+        );
+        headers.append(
+            "sec-websocket-extensions",
+            HeaderValue::from_static("x-other"),
+        );
+        assert_eq!(
+            negotiated_extensions(&headers),
+            ["permessage-deflate", "x-trace", "x-other"]
+        );
+    }
+
+    /// Attempts a handshake sending `version` as `Sec-WebSocket-Version`.
+    async fn handshake_with_version(
+        wynd: &Wynd<DuplexStream>,
+        version: &'static str,
+    ) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let mut request = "ws://localhost/".into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("sec-websocket-version", HeaderValue::from_static(version));
+
+        let (server, client) = tokio::join!(
+            wynd.handle_connection(server_io, peer_addr(1)),
+            client_async(request, client_io)
+        );
+        assert_eq!(server.is_ok(), client.is_ok());
+        client.map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn ws_version_is_exposed_on_the_connection() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_required_ws_version(13);
+
+        handshake_with_version(&wynd, "13").await.unwrap();
+
+        let clients = wynd.clients.lock().await;
+        let (connection, _) = clients.values().next().unwrap();
+        assert_eq!(connection.ws_version(), 13);
+    }
+
+    #[tokio::test]
+    async fn unsupported_ws_version_is_refused() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_required_ws_version(13);
+
+        assert!(handshake_with_version(&wynd, "8").await.is_err());
+
+        // A pinned version other than 13 refuses RFC 6455 clients with 426.
+        let mut pinned: Wynd<DuplexStream> = Wynd::new();
+        pinned.set_required_ws_version(14);
+        let Err(Error::Http(response)) = handshake_with_version(&pinned, "13").await else {
+            panic!("handshake should be refused");
+        };
+        assert_eq!(response.status(), StatusCode::UPGRADE_REQUIRED);
+        assert_eq!(
+            response.headers().get("sec-websocket-version"),
+            Some(&HeaderValue::from_static("14"))
+        );
+
+        assert!(wynd.clients.lock().await.is_empty());
+        assert!(pinned.clients.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn handshake_headers_are_exposed_on_connection_and_handle() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        let mut request = "ws://localhost/".into_client_request().unwrap();
+        let headers = request.headers_mut();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7"));
+        headers.insert("origin", HeaderValue::from_static("https://example.com"));
+
+        let (server, client) = tokio::join!(
+            wynd.handle_connection(server_io, peer_addr(1)),
+            client_async(request, client_io)
+        );
+        server.unwrap();
+        client.unwrap();
+
+        let clients = wynd.clients.lock().await;
+        let (connection, handle) = clients.values().next().unwrap();
+        assert_eq!(connection.header("X-Forwarded-For"), Some("203.0.113.7"));
+        assert_eq!(handle.header("Origin"), Some("https://example.com"));
+        assert_eq!(handle.headers(), connection.headers());
+        assert_eq!(handle.header("authorization"), None);
+    }
+
+    #[test]
+    fn request_headers_lowercases_names_and_joins_repeats() {
+        let mut headers = HeaderMap::new();
+        headers.append("Cookie", HeaderValue::from_static("a=1"));
+        headers.append("cookie", HeaderValue::from_static("b=2"));
+        headers.insert("X-Request-Id", HeaderValue::from_static("abc"));
+
+        let map = request_headers(&headers);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["cookie"], "a=1, b=2");
+        assert_eq!(map["x-request-id"], "abc");
+    }
+}
@@ -69,7 +69,7 @@ mod tests {
         assert_eq!(wynd.middlewares.len(), 1);
 
         // Sanity‑check that the flag is still false until the chain is executed.
-        assert_eq!(*flag.lock().await, false);
+        assert!(!(*flag.lock().await));
     }
 
     #[tokio::test]
@@ -1,4 +1,10 @@
+mod broadcast_test;
 mod conn_test;
+mod group_test;
+mod handshake_test;
 mod middleware_test;
+mod room_test;
+mod typed_test;
 mod types_test;
+mod utils;
 mod wynd_test;
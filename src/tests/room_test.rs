@@ -0,0 +1,1003 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use futures::SinkExt;
+    use tokio::io::DuplexStream;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::conn::ConnState;
+    use crate::room::{Room, RoomDefinition, RoomSnapshot};
+    use crate::tests::utils::{connect_to, peer_addr, recv_binary, recv_text, TestClient};
+    use crate::types::RoomError;
+    use crate::wynd::Wynd;
+
+    #[tokio::test]
+    async fn export_and_reimport_room_definitions() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.persist_room("lobby").await;
+        assert!(
+            wynd.set_room_metadata("lobby", "topic", "General chat")
+                .await
+        );
+        assert!(!wynd.set_room_metadata("missing", "topic", "Nothing").await);
+
+        let snapshot = wynd.export_rooms().await;
+        assert_eq!(
+            snapshot,
+            RoomSnapshot {
+                rooms: vec![RoomDefinition {
+                    name: "lobby".to_string(),
+                    persistent: true,
+                    members: Vec::new(),
+                    metadata: HashMap::from([("topic".to_string(), "General chat".to_string())]),
+                }],
+            }
+        );
+
+        let restored: Wynd<DuplexStream> = Wynd::new();
+        assert_eq!(restored.import_rooms(snapshot.clone()).await, 1);
+        assert_eq!(restored.export_rooms().await, snapshot);
+    }
+
+    #[tokio::test]
+    async fn import_skips_non_persistent_rooms_and_merges_metadata() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.persist_room("lobby").await;
+        wynd.set_room_metadata("lobby", "topic", "General chat")
+            .await;
+
+        let snapshot = RoomSnapshot {
+            rooms: vec![
+                RoomDefinition {
+                    name: "lobby".to_string(),
+                    persistent: true,
+                    members: vec![3, 4],
+                    metadata: HashMap::from([("owner".to_string(), "ops".to_string())]),
+                },
+                RoomDefinition {
+                    name: "scratch".to_string(),
+                    persistent: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        assert_eq!(wynd.import_rooms(snapshot).await, 1);
+        assert_eq!(wynd.room_metadata("scratch").await, None);
+
+        let metadata = wynd.room_metadata("lobby").await.unwrap();
+        assert_eq!(
+            metadata.get("topic").map(String::as_str),
+            Some("General chat")
+        );
+        assert_eq!(metadata.get("owner").map(String::as_str), Some("ops"));
+
+        // Member ids are informational only; live connections are not restored.
+        assert!(wynd.export_rooms().await.rooms[0].members.is_empty());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn snapshot_json_round_trip() {
+        let snapshot = RoomSnapshot {
+            rooms: vec![RoomDefinition {
+                name: "lobby".to_string(),
+                persistent: true,
+                members: vec![1],
+                metadata: HashMap::from([("topic".to_string(), "General chat".to_string())]),
+            }],
+        };
+
+        let json = snapshot.to_json().unwrap();
+        assert_eq!(RoomSnapshot::from_json(&json).unwrap(), snapshot);
+
+        let minimal =
+            RoomSnapshot::from_json(r#"{"rooms":[{"name":"lobby","persistent":true}]}"#).unwrap();
+        assert_eq!(minimal.rooms[0].name, "lobby");
+        assert!(minimal.rooms[0].metadata.is_empty());
+    }
+
+    #[tokio::test]
+    async fn late_joiner_receives_room_history() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("chat").await;
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                let _ = handle.to("chat").emit_text(msg.data).await;
+            });
+        });
+        wynd.start_room_processor();
+        wynd.set_room_history("chat", 2).await;
+
+        let mut sender = connect_to(&wynd, "/", peer_addr(1)).await;
+        for line in ["one", "two", "three"] {
+            sender.send(Message::text(line)).await.unwrap();
+            assert_eq!(recv_text(&mut sender).await.as_deref(), Some(line));
+        }
+
+        let mut late = connect_to(&wynd, "/", peer_addr(2)).await;
+
+        assert_eq!(recv_text(&mut late).await.as_deref(), Some("two"));
+        assert_eq!(recv_text(&mut late).await.as_deref(), Some("three"));
+        assert_eq!(recv_text(&mut late).await, None);
+    }
+
+    #[tokio::test]
+    async fn joiner_receives_nothing_without_room_history() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("chat").await;
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                let _ = handle.to("chat").emit_text(msg.data).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut sender = connect_to(&wynd, "/", peer_addr(1)).await;
+        sender.send(Message::text("hello")).await.unwrap();
+        assert_eq!(recv_text(&mut sender).await.as_deref(), Some("hello"));
+
+        let mut late = connect_to(&wynd, "/", peer_addr(2)).await;
+
+        assert_eq!(recv_text(&mut late).await, None);
+    }
+
+    #[tokio::test]
+    async fn room_count_tracks_joins_and_leaves() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                match msg.data.split_once(':') {
+                    Some(("join", "a")) => handle.join("a").await.unwrap(),
+                    Some(("join", "b")) => handle.join("b").await.unwrap(),
+                    Some(("leave", "a")) => handle.leave("a").await.unwrap(),
+                    Some(("leave", "b")) => handle.leave("b").await.unwrap(),
+                    _ => {}
+                }
+                let reply = format!(
+                    "{} {}",
+                    handle.room_count().await,
+                    handle.in_any_room().await
+                );
+                let _ = handle.send_text(reply).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        for (command, expected) in [
+            ("count", "0 false"),
+            ("join:a", "1 true"),
+            ("join:b", "2 true"),
+            ("join:a", "2 true"),
+            ("leave:a", "1 true"),
+            ("leave:b", "0 false"),
+        ] {
+            client.send(Message::text(command)).await.unwrap();
+            assert_eq!(recv_text(&mut client).await.as_deref(), Some(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn back_to_back_joined_rooms_calls_do_not_block() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_room_response_capacity(1);
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_, handle| async move {
+                handle.join("a").await.unwrap();
+                let first = handle.joined_rooms().await;
+                let second = handle.joined_rooms().await;
+                let clone = (*handle).clone();
+                let concurrent = futures::future::join_all(
+                    (0..8).map(|_| async { handle.joined_rooms().await.len() }),
+                )
+                .await;
+                let from_clone = clone.joined_rooms().await;
+                let reply = format!("{:?} {:?} {:?} {:?}", first, second, concurrent, from_clone);
+                let _ = handle.send_text(reply).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        client.send(Message::text("go")).await.unwrap();
+
+        assert_eq!(
+            recv_text(&mut client).await.as_deref(),
+            Some(r#"["a"] ["a"] [1, 1, 1, 1, 1, 1, 1, 1] ["a"]"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn move_to_leaves_source_and_joins_destination() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("lobby").await;
+            })
+            .await;
+            conn.on_text(|_, handle| async move {
+                handle.move_to("lobby", "game").await.unwrap();
+                let rooms = handle.joined_rooms().await;
+                let _ = handle.send_text(rooms.join(",")).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        client.send(Message::text("start")).await.unwrap();
+
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("game"));
+        let snapshot = wynd.export_rooms().await;
+        let names: Vec<&str> = snapshot.rooms.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["game"]);
+    }
+
+    #[tokio::test]
+    async fn room_users_collapses_connections_of_the_same_user() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                handle.set_user_id(msg.data).await;
+                handle.join("lobby").await.unwrap();
+                let _ = handle.send_text("joined").await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut phone = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut laptop = connect_to(&wynd, "/", peer_addr(2)).await;
+        for client in [&mut phone, &mut laptop] {
+            client.send(Message::text("alice")).await.unwrap();
+            assert_eq!(recv_text(client).await.as_deref(), Some("joined"));
+        }
+
+        assert_eq!(wynd.room_members("lobby").await, vec![0, 1]);
+        assert_eq!(wynd.room_users("lobby").await, vec!["alice".to_string()]);
+        assert!(wynd.room_users("missing").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn for_each_room_reaches_members_of_every_room() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                match msg.data.as_str() {
+                    "lobby" => handle.join("lobby").await.unwrap(),
+                    "game" => handle.join("game").await.unwrap(),
+                    _ => {}
+                }
+                let _ = handle.send_text("joined").await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut second = connect_to(&wynd, "/", peer_addr(2)).await;
+        let mut third = connect_to(&wynd, "/", peer_addr(3)).await;
+        for (client, room) in [
+            (&mut first, "lobby"),
+            (&mut second, "lobby"),
+            (&mut third, "game"),
+        ] {
+            client.send(Message::text(room)).await.unwrap();
+            assert_eq!(recv_text(client).await.as_deref(), Some("joined"));
+        }
+
+        let visited = std::sync::Mutex::new(Vec::new());
+        wynd.for_each_room(|room| {
+            let visited = &visited;
+            async move {
+                visited
+                    .lock()
+                    .unwrap()
+                    .push((room.name().to_string(), room.member_count()));
+                room.text("maintenance at 02:00").await;
+            }
+        })
+        .await;
+
+        let mut visited = visited.into_inner().unwrap();
+        visited.sort();
+        assert_eq!(visited, [("game".to_string(), 1), ("lobby".to_string(), 2)]);
+        for client in [&mut first, &mut second, &mut third] {
+            assert_eq!(
+                recv_text(client).await.as_deref(),
+                Some("maintenance at 02:00")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn delayed_room_text_arrives_after_the_delay() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("game").await;
+            })
+            .await;
+            conn.on_text(|_, handle| async move {
+                handle
+                    .to("game")
+                    .text_delayed("game starts now", Duration::from_millis(300));
+                let _ = handle.send_text("scheduled").await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut host = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut player = connect_to(&wynd, "/", peer_addr(2)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let scheduled_at = Instant::now();
+        host.send(Message::text("start")).await.unwrap();
+        assert_eq!(recv_text(&mut host).await.as_deref(), Some("scheduled"));
+
+        assert_eq!(
+            recv_text(&mut player).await.as_deref(),
+            Some("game starts now")
+        );
+        assert!(scheduled_at.elapsed() >= Duration::from_millis(300));
+        assert_eq!(recv_text(&mut host).await, None);
+    }
+
+    #[tokio::test]
+    async fn room_rate_limit_drops_excess_messages_only_in_that_room() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("noisy").await;
+                let _ = handle.join("quiet").await;
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                match msg.data.split_once(':') {
+                    Some(("noisy", n)) => handle.to("noisy").text(n).await.unwrap(),
+                    Some(("quiet", n)) => handle.to("quiet").text(n).await.unwrap(),
+                    _ => {}
+                }
+            });
+        });
+        wynd.start_room_processor();
+        wynd.set_room_rate_limit("noisy", 3, Duration::from_secs(60))
+            .await;
+
+        let mut sender = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut receiver = connect_to(&wynd, "/", peer_addr(2)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        for n in 0..6 {
+            sender
+                .send(Message::text(format!("noisy:{n}")))
+                .await
+                .unwrap();
+            sender
+                .send(Message::text(format!("quiet:{n}")))
+                .await
+                .unwrap();
+        }
+
+        let mut received = Vec::new();
+        while let Some(text) = recv_text(&mut receiver).await {
+            received.push(text);
+        }
+        let expected: Vec<String> = ["0", "0", "1", "1", "2", "2", "3", "4", "5"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn text_sequence_stays_in_order_for_every_member() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("news").await;
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                match msg.data.as_str() {
+                    "announce" => handle
+                        .to("news")
+                        .text_sequence(&["line 1", "line 2", "line 3"])
+                        .await
+                        .unwrap(),
+                    other => handle.to("news").text(other).await.unwrap(),
+                }
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut announcer = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut chatter = connect_to(&wynd, "/", peer_addr(2)).await;
+        let mut listener = connect_to(&wynd, "/", peer_addr(3)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        announcer.send(Message::text("announce")).await.unwrap();
+        chatter.send(Message::text("chatter")).await.unwrap();
+
+        let lines = ["line 1", "line 2", "line 3"];
+        let mut heard = Vec::new();
+        while let Some(text) = recv_text(&mut listener).await {
+            heard.push(text);
+        }
+        assert_eq!(heard.len(), 4);
+        assert!(heard.windows(3).any(|w| w == lines));
+        assert!(heard.contains(&"chatter".to_string()));
+
+        for line in lines {
+            assert_eq!(recv_text(&mut chatter).await.as_deref(), Some(line));
+        }
+        assert_eq!(recv_text(&mut announcer).await.as_deref(), Some("chatter"));
+        assert_eq!(recv_text(&mut announcer).await, None);
+    }
+
+    /// Server whose clients join "lobby" by sending "join" and post anything
+    /// else to it, getting "ok" or the room error back.
+    fn lobby_server(require_membership: bool) -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.require_membership_to_send(require_membership);
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let reply = if msg.data == "join" {
+                    handle.join("lobby").await.map(|_| "joined".to_string())
+                } else {
+                    handle
+                        .to("lobby")
+                        .text(msg.data)
+                        .await
+                        .map(|_| "ok".to_string())
+                };
+                let _ = handle
+                    .send_text(&reply.unwrap_or_else(|e| e.to_string()))
+                    .await;
+            });
+        });
+        wynd.start_room_processor();
+        wynd
+    }
+
+    #[tokio::test]
+    async fn non_member_send_is_rejected_when_membership_required() {
+        let wynd = lobby_server(true);
+        let mut member = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut listener = connect_to(&wynd, "/", peer_addr(2)).await;
+        let mut outsider = connect_to(&wynd, "/", peer_addr(3)).await;
+
+        for client in [&mut member, &mut listener] {
+            client.send(Message::text("join")).await.unwrap();
+            assert_eq!(recv_text(client).await.as_deref(), Some("joined"));
+        }
+
+        outsider.send(Message::text("spam")).await.unwrap();
+        assert_eq!(
+            recv_text(&mut outsider).await,
+            Some(RoomError::NotAMember("lobby".to_string()).to_string())
+        );
+        assert_eq!(recv_text(&mut listener).await, None);
+
+        member.send(Message::text("hello")).await.unwrap();
+        assert_eq!(recv_text(&mut member).await.as_deref(), Some("ok"));
+        assert_eq!(recv_text(&mut listener).await.as_deref(), Some("hello"));
+    }
+
+    #[tokio::test]
+    async fn non_member_send_is_dropped_without_stopping_the_room_processor() {
+        let wynd = lobby_server(false);
+        let mut member = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut listener = connect_to(&wynd, "/", peer_addr(2)).await;
+        let mut outsider = connect_to(&wynd, "/", peer_addr(3)).await;
+
+        for client in [&mut member, &mut listener] {
+            client.send(Message::text("join")).await.unwrap();
+            assert_eq!(recv_text(client).await.as_deref(), Some("joined"));
+        }
+
+        outsider.send(Message::text("spam")).await.unwrap();
+        assert_eq!(recv_text(&mut outsider).await.as_deref(), Some("ok"));
+        assert_eq!(recv_text(&mut listener).await, None);
+
+        member.send(Message::text("hello")).await.unwrap();
+        assert_eq!(recv_text(&mut member).await.as_deref(), Some("ok"));
+        assert_eq!(recv_text(&mut listener).await.as_deref(), Some("hello"));
+    }
+
+    /// Server whose clients join "party" on open and relay each command to it
+    /// through the room method of the same name.
+    fn party_server() -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("party").await;
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                let room = handle.to("party");
+                let payload = format!("{} from {}", msg.data, handle.id());
+                match msg.data.as_str() {
+                    "text" => room.text(payload).await.unwrap(),
+                    "emit_text" => room.emit_text(payload).await.unwrap(),
+                    "binary" => room.binary(payload.into_bytes()).await.unwrap(),
+                    "emit_binary" => room.emit_binary(payload.into_bytes()).await.unwrap(),
+                    _ => {}
+                }
+            });
+        });
+        wynd.start_room_processor();
+        wynd
+    }
+
+    async fn party_of_three(wynd: &Wynd<DuplexStream>) -> [TestClient; 3] {
+        let clients = [
+            connect_to(wynd, "/", peer_addr(1)).await,
+            connect_to(wynd, "/", peer_addr(2)).await,
+            connect_to(wynd, "/", peer_addr(3)).await,
+        ];
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        clients
+    }
+
+    #[tokio::test]
+    async fn room_text_excludes_the_sender() {
+        let wynd = party_server();
+        let [mut sender, mut first, mut second] = party_of_three(&wynd).await;
+
+        sender.send(Message::text("text")).await.unwrap();
+
+        let first_heard = recv_text(&mut first).await.expect("first member hears it");
+        assert!(first_heard.starts_with("text from "));
+        assert_eq!(recv_text(&mut second).await, Some(first_heard));
+        assert_eq!(recv_text(&mut sender).await, None);
+    }
+
+    #[tokio::test]
+    async fn room_emit_text_includes_the_sender() {
+        let wynd = party_server();
+        let [mut sender, mut first, mut second] = party_of_three(&wynd).await;
+
+        sender.send(Message::text("emit_text")).await.unwrap();
+
+        let heard = recv_text(&mut sender)
+            .await
+            .expect("sender hears its own emit");
+        assert!(heard.starts_with("emit_text from "));
+        assert_eq!(recv_text(&mut first).await.as_ref(), Some(&heard));
+        assert_eq!(recv_text(&mut second).await.as_ref(), Some(&heard));
+    }
+
+    #[tokio::test]
+    async fn room_binary_excludes_the_sender() {
+        let wynd = party_server();
+        let [mut sender, mut first, mut second] = party_of_three(&wynd).await;
+
+        sender.send(Message::text("binary")).await.unwrap();
+
+        let first_heard = recv_binary(&mut first)
+            .await
+            .expect("first member hears it");
+        assert!(first_heard.starts_with(b"binary from "));
+        assert_eq!(recv_binary(&mut second).await, Some(first_heard));
+        assert_eq!(recv_binary(&mut sender).await, None);
+    }
+
+    #[tokio::test]
+    async fn room_emit_binary_includes_the_sender() {
+        let wynd = party_server();
+        let [mut sender, mut first, mut second] = party_of_three(&wynd).await;
+
+        sender.send(Message::text("emit_binary")).await.unwrap();
+
+        let heard = recv_binary(&mut sender)
+            .await
+            .expect("sender hears its own emit");
+        assert!(heard.starts_with(b"emit_binary from "));
+        assert_eq!(recv_binary(&mut first).await.as_ref(), Some(&heard));
+        assert_eq!(recv_binary(&mut second).await.as_ref(), Some(&heard));
+    }
+
+    #[tokio::test]
+    async fn join_is_rejected_once_max_rooms_exist() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_max_rooms(2);
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let reply = match handle.join(msg.data.as_str()).await {
+                    Ok(()) => format!("joined {}", msg.data),
+                    Err(e) => e.to_string(),
+                };
+                let _ = handle.send_text(&reply).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut clients = [
+            connect_to(&wynd, "/", peer_addr(1)).await,
+            connect_to(&wynd, "/", peer_addr(2)).await,
+        ];
+
+        for (client, room) in [(0, "a"), (0, "b"), (1, "a")] {
+            let client = &mut clients[client];
+            client.send(Message::text(room)).await.unwrap();
+            assert_eq!(recv_text(client).await, Some(format!("joined {}", room)));
+        }
+
+        let [_, mut second] = clients;
+        second.send(Message::text("c")).await.unwrap();
+        assert_eq!(
+            recv_text(&mut second).await,
+            Some(RoomError::CapacityExceeded("c".to_string()).to_string())
+        );
+        assert!(wynd.room_members("c").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn move_to_is_rejected_once_max_rooms_exist() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_max_rooms(2);
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let reply = match msg.data.split_once(' ') {
+                    Some((from, to)) => match handle.move_to(from, to).await {
+                        Ok(()) => format!("moved to {}", to),
+                        Err(e) => e.to_string(),
+                    },
+                    None => {
+                        handle.join(msg.data.as_str()).await.unwrap();
+                        format!("joined {}", msg.data)
+                    }
+                };
+                let _ = handle.send_text(&reply).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut other = connect_to(&wynd, "/", peer_addr(2)).await;
+        other.send(Message::text("a")).await.unwrap();
+        assert_eq!(recv_text(&mut other).await.as_deref(), Some("joined a"));
+        for (request, reply) in [
+            ("a", "joined a"),
+            ("b", "joined b"),
+            (
+                "a c",
+                &RoomError::CapacityExceeded("c".to_string()).to_string(),
+            ),
+        ] {
+            client.send(Message::text(request)).await.unwrap();
+            assert_eq!(recv_text(&mut client).await.as_deref(), Some(reply));
+        }
+        // The refused move leaves the connection where it was.
+        assert!(wynd.room_members("c").await.is_empty());
+        assert_eq!(wynd.room_members("a").await.len(), 2);
+
+        client.send(Message::text("a b")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("moved to b"));
+    }
+
+    #[tokio::test]
+    async fn move_to_from_a_room_it_empties_succeeds_at_max_rooms() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_max_rooms(1);
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let reply = match handle.move_to("a", msg.data.as_str()).await {
+                    Ok(()) => format!("moved to {}", msg.data),
+                    Err(e) => e.to_string(),
+                };
+                let _ = handle.send_text(&reply).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        // Not in `a` yet, so joining `a` creates the only room allowed.
+        client.send(Message::text("a")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("moved to a"));
+
+        // The client is the last member of `a`, whose slot goes to `b`.
+        client.send(Message::text("b")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("moved to b"));
+        assert!(wynd.room_members("a").await.is_empty());
+        assert_eq!(wynd.room_members("b").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn room_member_count_and_ids_come_from_the_processor() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let room = handle.to("lobby");
+                let reply = match msg.data.as_str() {
+                    "join" => {
+                        handle.join("lobby").await.unwrap();
+                        "joined".to_string()
+                    }
+                    "leave" => {
+                        handle.leave("lobby").await.unwrap();
+                        "left".to_string()
+                    }
+                    _ => format!(
+                        "{} {:?}",
+                        room.member_count().await.unwrap(),
+                        room.member_ids().await.unwrap()
+                    ),
+                };
+                let _ = handle.send_text(&reply).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut second = connect_to(&wynd, "/", peer_addr(2)).await;
+
+        first.send(Message::text("count")).await.unwrap();
+        assert_eq!(recv_text(&mut first).await.as_deref(), Some("0 []"));
+
+        for client in [&mut first, &mut second] {
+            client.send(Message::text("join")).await.unwrap();
+            assert_eq!(recv_text(client).await.as_deref(), Some("joined"));
+        }
+        let ids = wynd.room_members("lobby").await;
+        assert_eq!(ids.len(), 2);
+        second.send(Message::text("count")).await.unwrap();
+        assert_eq!(recv_text(&mut second).await, Some(format!("2 {:?}", ids)));
+
+        first.send(Message::text("leave")).await.unwrap();
+        assert_eq!(recv_text(&mut first).await.as_deref(), Some("left"));
+        second.send(Message::text("count")).await.unwrap();
+        assert_eq!(
+            recv_text(&mut second).await,
+            Some(format!("1 {:?}", wynd.room_members("lobby").await))
+        );
+    }
+
+    #[tokio::test]
+    async fn in_room_reflects_joins_and_leaves() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                match msg.data.as_str() {
+                    "join" => handle.join("lobby").await.unwrap(),
+                    "leave" => handle.leave("lobby").await.unwrap(),
+                    _ => {}
+                }
+                let lobby = String::from("lobby");
+                let reply = format!(
+                    "{} {}",
+                    handle.in_room(&lobby).await,
+                    handle.in_room("games").await
+                );
+                let _ = handle.send_text(&reply).await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        for (command, expected) in [
+            ("check", "false false"),
+            ("join", "true false"),
+            ("leave", "false false"),
+        ] {
+            client.send(Message::text(command)).await.unwrap();
+            assert_eq!(recv_text(&mut client).await.as_deref(), Some(expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn rooms_can_be_named_at_runtime() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                if let Some(id) = msg.data.strip_prefix("join:") {
+                    let room = format!("match-{}", id);
+                    handle.join(room.as_str()).await.unwrap();
+                    let _ = handle.send_text(&room).await;
+                } else if let Some((id, text)) = msg.data.split_once(' ') {
+                    handle.to(format!("match-{}", id)).text(text).await.unwrap();
+                }
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut second = connect_to(&wynd, "/", peer_addr(2)).await;
+        let mut other = connect_to(&wynd, "/", peer_addr(3)).await;
+        for (client, id) in [(&mut first, 7), (&mut second, 7), (&mut other, 8)] {
+            client
+                .send(Message::text(format!("join:{}", id)))
+                .await
+                .unwrap();
+            assert_eq!(recv_text(client).await, Some(format!("match-{}", id)));
+        }
+
+        first.send(Message::text("7 hello")).await.unwrap();
+        assert_eq!(recv_text(&mut second).await.as_deref(), Some("hello"));
+        assert_eq!(recv_text(&mut other).await, None);
+        assert_eq!(wynd.room_members("match-7").await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn connection_info_reflects_rooms_tags_and_state() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_msg, handle| async move {
+                handle.join("lobby").await.unwrap();
+                handle.add_tag("web").await;
+                let _ = handle.send_text("joined").await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        let before = Instant::now();
+        let id = wynd.client_ids().await[0];
+        let info = wynd.connection_info(id).await.unwrap();
+        assert_eq!(info.id, id);
+        assert_eq!(info.addr, peer_addr(1));
+        assert_eq!(info.state, ConnState::OPEN);
+        assert!(info.connected_at <= before);
+        assert!(info.rooms.is_empty());
+        assert!(info.tags.is_empty());
+
+        client.send(Message::text("join")).await.unwrap();
+        assert_eq!(recv_text(&mut client).await.as_deref(), Some("joined"));
+
+        let info = wynd.connection_info(id).await.unwrap();
+        assert_eq!(info.rooms, vec!["lobby".to_string()]);
+        assert_eq!(info.tags, vec!["web".to_string()]);
+        assert!(wynd.connection_info(id + 1).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn room_text_and_binary_reach_every_member() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+        let mut first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut second = connect_to(&wynd, "/", peer_addr(2)).await;
+
+        let mut room = Room::named("lobby");
+        for (id, (_, handle)) in wynd.clients.lock().await.iter() {
+            room.room_clients.insert(*id, (**handle).clone());
+        }
+        assert_eq!(room.room_clients.len(), 2);
+
+        room.text("hello").await;
+        room.binary(vec![1, 2, 3]).await;
+
+        for client in [&mut first, &mut second] {
+            assert_eq!(recv_text(client).await.as_deref(), Some("hello"));
+            assert_eq!(recv_binary(client).await, Some(vec![1, 2, 3]));
+        }
+    }
+
+    #[tokio::test]
+    async fn flooding_a_room_runs_the_flood_handler_once() {
+        let (flood_tx, mut flood_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_room_flood_limit(5, Duration::from_secs(10));
+        wynd.on_room_flood(move |room| {
+            let _ = flood_tx.send(room.to_string());
+        });
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                handle.join("lobby").await.unwrap();
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                handle.to("lobby").text(msg.data).await.unwrap();
+                let _ = handle.send_text("sent").await;
+            });
+        });
+        wynd.start_room_processor();
+
+        let mut client = connect_to(&wynd, "/", peer_addr(1)).await;
+        for i in 0..8 {
+            client.send(Message::text(i.to_string())).await.unwrap();
+            assert_eq!(recv_text(&mut client).await.as_deref(), Some("sent"));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            if i < 5 {
+                // The first five messages are within the limit.
+                assert!(flood_rx.try_recv().is_err());
+            } else if i == 5 {
+                // The sixth floods the room.
+                let room = tokio::time::timeout(Duration::from_millis(500), flood_rx.recv())
+                    .await
+                    .expect("flood handler did not run");
+                assert_eq!(room.as_deref(), Some("lobby"));
+            }
+        }
+        // Later messages in the same flood do not fire again.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(flood_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn room_operations_before_listen_fail_instead_of_hanging() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+        let _client = connect_to(&wynd, "/", peer_addr(1)).await;
+        let handle = (*wynd.clients.lock().await[&0].1).clone();
+
+        let attempt = tokio::time::timeout(Duration::from_secs(1), async {
+            (
+                handle.join("lobby").await,
+                handle.leave("lobby").await,
+                handle.joined_rooms().await,
+                handle.in_room("lobby").await,
+            )
+        })
+        .await
+        .expect("room operations should not wait for a processor");
+
+        assert_eq!(attempt.0, Err(RoomError::ChannelClosed));
+        assert_eq!(attempt.1, Err(RoomError::ChannelClosed));
+        assert!(attempt.2.is_empty());
+        assert!(!attempt.3);
+    }
+
+    /// Starts a server where clients connecting with `?join=1` join "lobby",
+    /// and every text is sent to the lobby with `text_counted` and then
+    /// `emit_text_counted`, replying to the sender with both counts.
+    fn counting_lobby() -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            let joins = conn.query_param::<u8>("join") == Some(1);
+            conn.on_open(move |handle| async move {
+                if joins {
+                    handle.join("lobby").await.unwrap();
+                }
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                let lobby = handle.to("lobby");
+                let text = lobby.text_counted(msg.data.clone()).await.unwrap();
+                let emit = lobby.emit_text_counted(msg.data).await.unwrap();
+                let _ = handle.send_text(format!("{},{}", text, emit)).await;
+            });
+        });
+        wynd.start_room_processor();
+        wynd
+    }
+
+    #[tokio::test]
+    async fn counted_room_texts_exclude_or_include_a_member_sender() {
+        let wynd = counting_lobby();
+        let mut sender = connect_to(&wynd, "/?join=1", peer_addr(1)).await;
+        let mut others = vec![
+            connect_to(&wynd, "/?join=1", peer_addr(2)).await,
+            connect_to(&wynd, "/?join=1", peer_addr(3)).await,
+        ];
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        sender.send(Message::text("hi")).await.unwrap();
+
+        // `text` reached the two other members, `emit_text` all three.
+        assert_eq!(recv_text(&mut sender).await.as_deref(), Some("hi"));
+        assert_eq!(recv_text(&mut sender).await.as_deref(), Some("2,3"));
+        for other in &mut others {
+            assert_eq!(recv_text(other).await.as_deref(), Some("hi"));
+            assert_eq!(recv_text(other).await.as_deref(), Some("hi"));
+        }
+    }
+
+    #[tokio::test]
+    async fn counted_room_texts_from_a_non_member_reach_nobody() {
+        let wynd = counting_lobby();
+        let mut outsider = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut member = connect_to(&wynd, "/?join=1", peer_addr(2)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        outsider.send(Message::text("hi")).await.unwrap();
+
+        assert_eq!(recv_text(&mut outsider).await.as_deref(), Some("0,0"));
+        assert_eq!(recv_text(&mut member).await, None);
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+#[cfg(feature = "json")]
+mod tests {
+    use futures::SinkExt;
+    use serde::{Deserialize, Serialize};
+    use tokio::io::DuplexStream;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    use crate::tests::utils::{connect, recv_text};
+    use crate::typed::Message;
+    use crate::wynd::Wynd;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum ChatMsg {
+        Join { name: String },
+        Say { from: String, text: String },
+    }
+
+    impl Message for ChatMsg {}
+
+    /// Helper function to create a server that answers every `Join` with a
+    /// greeting and echoes every `Say`
+    fn create_chat_server() -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+
+        wynd.on_connection(|conn| async move {
+            conn.typed::<ChatMsg>()
+                .on_message(|msg, handle| async move {
+                    let reply = match msg {
+                        ChatMsg::Join { name } => ChatMsg::Say {
+                            from: "server".to_string(),
+                            text: format!("welcome {}", name),
+                        },
+                        say => say,
+                    };
+                    handle.send(&reply).await.unwrap();
+                });
+        });
+
+        wynd
+    }
+
+    #[test]
+    fn test_chat_msg_round_trips_through_json() {
+        let msg = ChatMsg::Join {
+            name: "ada".to_string(),
+        };
+        let encoded = msg.encode().unwrap();
+        assert_eq!(encoded, r#"{"type":"join","name":"ada"}"#);
+        assert_eq!(ChatMsg::decode(&encoded).unwrap(), msg);
+
+        let err = ChatMsg::decode("not json").unwrap_err();
+        assert_eq!(err.code(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_typed_messages_are_received_and_sent() {
+        let wynd = create_chat_server();
+        let mut client = connect(&wynd).await;
+
+        let join = ChatMsg::Join {
+            name: "ada".to_string(),
+        };
+        client
+            .send(WsMessage::text(join.encode().unwrap()))
+            .await
+            .unwrap();
+        let reply = ChatMsg::decode(&recv_text(&mut client).await.unwrap()).unwrap();
+        assert_eq!(
+            reply,
+            ChatMsg::Say {
+                from: "server".to_string(),
+                text: "welcome ada".to_string(),
+            }
+        );
+
+        let say = ChatMsg::Say {
+            from: "ada".to_string(),
+            text: "hello".to_string(),
+        };
+        client
+            .send(WsMessage::text(say.encode().unwrap()))
+            .await
+            .unwrap();
+        let reply = ChatMsg::decode(&recv_text(&mut client).await.unwrap()).unwrap();
+        assert_eq!(reply, say);
+    }
+
+    #[tokio::test]
+    async fn test_typed_parse_failure_is_reported_to_client() {
+        let mut wynd = create_chat_server();
+        wynd.set_auto_error_reply(true);
+        let mut client = connect(&wynd).await;
+
+        client
+            .send(WsMessage::text(r#"{"type":"shout"}"#))
+            .await
+            .unwrap();
+
+        let reply = recv_text(&mut client).await.unwrap();
+        let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+        assert_eq!(reply["code"], 400);
+        assert!(reply["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid message"));
+
+        // The connection keeps working after a bad frame.
+        let say = ChatMsg::Say {
+            from: "ada".to_string(),
+            text: "still here".to_string(),
+        };
+        client
+            .send(WsMessage::text(say.encode().unwrap()))
+            .await
+            .unwrap();
+        let reply = ChatMsg::decode(&recv_text(&mut client).await.unwrap()).unwrap();
+        assert_eq!(reply, say);
+    }
+}
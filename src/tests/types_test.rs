@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use crate::types::WyndError;
+    use crate::types::{CloseCodeKind, CloseEvent, ConnectionErrorKind, WyndError};
 
     #[test]
     fn test_wynd_error_new() {
@@ -32,7 +32,7 @@ mod tests {
         let error = WyndError::new(error_message);
 
         // Test direct deref
-        let deref_result: &str = &*error;
+        let deref_result: &str = &error;
         assert_eq!(deref_result, "Connection failed");
 
         // Test that we can use string methods through deref
@@ -107,14 +107,79 @@ mod tests {
 
         // Test pattern matching on the dereferenced string
         match &*error {
-            "Pattern test" => assert!(true),
+            "Pattern test" => {}
             _ => unreachable!("Pattern matching failed"),
         }
 
         // Test with starts_with pattern
         match &*error {
-            s if s.starts_with("Pattern") => assert!(true),
+            s if s.starts_with("Pattern") => {}
             _ => unreachable!("Pattern prefix matching failed"),
         }
     }
+
+    #[test]
+    fn test_wynd_error_classifies_websocket_errors() {
+        use std::error::Error as _;
+        use tokio_tungstenite::tungstenite::Error;
+
+        let closed = WyndError::from(Error::ConnectionClosed);
+        assert!(matches!(closed, WyndError::ConnectionClosed));
+        assert_eq!(&*closed, "connection is closed");
+        assert!(closed.source().is_none());
+
+        let failed = WyndError::from(Error::Io(std::io::Error::other("disk full")));
+        assert!(matches!(failed, WyndError::WriteFailed { .. }));
+        assert!(failed.contains("disk full"));
+        assert!(failed.source().is_some());
+
+        assert_eq!(format!("{}", WyndError::Backpressure), "send queue is full");
+        assert!(WyndError::Backpressure.is_backpressure());
+    }
+
+    #[test]
+    fn test_close_event_classifies_application_code() {
+        let event = CloseEvent::new(4001, "session expired".to_string());
+
+        assert!(event.is_application_code());
+        assert_eq!(event.kind(), CloseCodeKind::Application);
+    }
+
+    #[test]
+    fn test_close_event_classifies_other_ranges() {
+        let kind = |code| CloseEvent::new(code, String::new()).kind();
+
+        assert_eq!(kind(1000), CloseCodeKind::Protocol);
+        assert_eq!(kind(1013), CloseCodeKind::Protocol);
+        assert_eq!(kind(2000), CloseCodeKind::Reserved);
+        assert_eq!(kind(3000), CloseCodeKind::Registered);
+        assert_eq!(kind(4999), CloseCodeKind::Application);
+        assert_eq!(kind(5000), CloseCodeKind::Invalid);
+        assert_eq!(kind(999), CloseCodeKind::Invalid);
+        assert!(!CloseEvent::new(1000, String::new()).is_application_code());
+    }
+
+    #[test]
+    fn test_connection_error_kind_classifies_tungstenite_errors() {
+        use tokio_tungstenite::tungstenite::error::{CapacityError, Error, ProtocolError};
+
+        let kind = |e: Error| ConnectionErrorKind::from(&e);
+        assert_eq!(
+            kind(Error::Protocol(ProtocolError::UnmaskedFrameFromClient)),
+            ConnectionErrorKind::Protocol
+        );
+        assert_eq!(
+            kind(Error::Io(std::io::ErrorKind::ConnectionReset.into())),
+            ConnectionErrorKind::Io
+        );
+        assert_eq!(kind(Error::Utf8("bad".into())), ConnectionErrorKind::Utf8);
+        assert_eq!(
+            kind(Error::Capacity(CapacityError::MessageTooLong {
+                size: 2,
+                max_size: 1
+            })),
+            ConnectionErrorKind::Capacity
+        );
+        assert_eq!(kind(Error::AlreadyClosed), ConnectionErrorKind::Other);
+    }
 }
@@ -0,0 +1,60 @@
+//! Helpers for driving a `Wynd` server over in-memory streams.
+//!
+//! Connections are backed by `tokio::io::duplex` pipes, so tests can exercise
+//! the full handshake and message loop without binding real TCP ports.
+#![cfg(test)]
+
+use std::{net::SocketAddr, time::Duration};
+
+use futures::StreamExt;
+use tokio::{io::DuplexStream, time::timeout};
+use tokio_tungstenite::{client_async, tungstenite::Message, WebSocketStream};
+
+use crate::wynd::Wynd;
+
+/// Client side of an in-memory WebSocket connection.
+pub(crate) type TestClient = WebSocketStream<DuplexStream>;
+
+/// Connects a new in-memory client to `wynd` from the default peer address.
+pub(crate) async fn connect(wynd: &Wynd<DuplexStream>) -> TestClient {
+    connect_to(wynd, "/", peer_addr(1)).await
+}
+
+/// Connects a new in-memory client to `wynd`, requesting `path` from `addr`.
+pub(crate) async fn connect_to(
+    wynd: &Wynd<DuplexStream>,
+    path: &str,
+    addr: SocketAddr,
+) -> TestClient {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let url = format!("ws://localhost{}", path);
+    let (server, client) = tokio::join!(
+        wynd.handle_connection(server_io, addr),
+        client_async(url, client_io)
+    );
+    server.expect("server side of the handshake should succeed");
+    client
+        .expect("client side of the handshake should succeed")
+        .0
+}
+
+/// Returns a loopback peer address on the given port.
+pub(crate) fn peer_addr(port: u16) -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], port))
+}
+
+/// Waits briefly for the next message and returns it if it is a text frame.
+pub(crate) async fn recv_text(client: &mut TestClient) -> Option<String> {
+    match timeout(Duration::from_millis(500), client.next()).await {
+        Ok(Some(Ok(Message::Text(text)))) => Some(text.to_string()),
+        _ => None,
+    }
+}
+
+/// Waits briefly for the next message and returns it if it is a binary frame.
+pub(crate) async fn recv_binary(client: &mut TestClient) -> Option<Vec<u8>> {
+    match timeout(Duration::from_millis(500), client.next()).await {
+        Ok(Some(Ok(Message::Binary(data)))) => Some(data.to_vec()),
+        _ => None,
+    }
+}
@@ -4,11 +4,18 @@ mod tests {
     use std::net::SocketAddr;
     use std::sync::atomic::Ordering;
     use std::sync::{Arc, Mutex};
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
+    use tokio::io::DuplexStream;
     use tokio::time::timeout;
-    use tokio_tungstenite::{connect_async, tungstenite::Message};
+    use tokio_tungstenite::{
+        connect_async,
+        tungstenite::{protocol::frame::coding::CloseCode, Message},
+    };
 
-    use crate::wynd::{Standalone, Wynd};
+    use crate::affinity::ConnectionAffinity;
+    use crate::tests::utils::{connect, connect_to, peer_addr, recv_text};
+    use crate::types::{UpgradeRejection, WyndError};
+    use crate::wynd::{EvictionPolicy, Standalone, Wynd};
 
     /// Helper function to create a test server with basic handlers
     fn create_test_server() -> Wynd<Standalone> {
@@ -37,7 +44,7 @@ mod tests {
         let wynd: Wynd<Standalone> = Wynd::new();
 
         // Verify initial state
-        assert!(wynd.connection_handler.is_none());
+        assert!(wynd.connection_handlers.is_empty());
         assert!(wynd.error_handler.is_none());
         assert!(wynd.close_handler.is_none());
         assert_eq!(wynd.next_connection_id.load(Ordering::Relaxed), 0);
@@ -52,7 +59,7 @@ mod tests {
         let mut wynd: Wynd<Standalone> = Wynd::new();
 
         // Initially no handler
-        assert!(wynd.connection_handler.is_none());
+        assert!(wynd.connection_handlers.is_empty());
 
         // Register handler
         wynd.on_connection(|_conn| async move {
@@ -60,7 +67,56 @@ mod tests {
         });
 
         // Handler should be registered
-        assert!(wynd.connection_handler.is_some());
+        assert_eq!(wynd.connection_handlers.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_connection_handlers_run_in_registration_order() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        for name in ["log", "auth", "app"] {
+            let order = Arc::clone(&order);
+            wynd.on_connection(move |_conn| {
+                let order = Arc::clone(&order);
+                async move {
+                    // Later handlers must still wait for this one.
+                    if name == "log" {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                    }
+                    order.lock().unwrap().push(name);
+                }
+            });
+        }
+
+        let _client = connect(&wynd).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(*order.lock().unwrap(), vec!["log", "auth", "app"]);
+    }
+
+    #[tokio::test]
+    async fn test_connection_handler_closing_skips_later_handlers() {
+        let ran_app = Arc::new(Mutex::new(false));
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.close_with(1008, "unauthorized").await.unwrap();
+        });
+        let app_flag = Arc::clone(&ran_app);
+        wynd.on_connection(move |_conn| {
+            let app_flag = Arc::clone(&app_flag);
+            async move {
+                *app_flag.lock().unwrap() = true;
+            }
+        });
+
+        let mut client = connect(&wynd).await;
+        match timeout(Duration::from_millis(500), client.next()).await {
+            Ok(Some(Ok(Message::Close(Some(frame))))) => {
+                assert_eq!(frame.code, CloseCode::Policy);
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+        assert!(!*ran_app.lock().unwrap());
     }
 
     #[test]
@@ -183,14 +239,10 @@ mod tests {
                     .await
                     .is_ok()
                 {
-                    if let Ok(Some(response)) =
+                    if let Ok(Some(Ok(Message::Text(text)))) =
                         timeout(Duration::from_millis(500), ws_stream.next()).await
                     {
-                        if let Ok(msg) = response {
-                            if let Message::Text(text) = msg {
-                                assert_eq!(text, format!("Echo: {}", test_message));
-                            }
-                        }
+                        assert_eq!(text, format!("Echo: {}", test_message));
                     }
                 }
             }
@@ -253,4 +305,1012 @@ mod tests {
         let called = close_called.lock().unwrap();
         assert!(*called);
     }
+
+    /// Creates an in-memory server whose connection handler sleeps for `delay`.
+    fn create_slow_server(delay: Duration) -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(move |_conn| async move {
+            tokio::time::sleep(delay).await;
+        });
+        wynd
+    }
+
+    #[tokio::test]
+    async fn test_slow_connection_handlers_run_concurrently() {
+        let delay = Duration::from_millis(200);
+        let wynd = create_slow_server(delay);
+
+        let started = Instant::now();
+        let _clients = tokio::join!(connect(&wynd), connect(&wynd), connect(&wynd));
+
+        assert!(started.elapsed() < delay * 2);
+        assert_eq!(wynd.clients.lock().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_handlers_bounds_handler_execution() {
+        let delay = Duration::from_millis(100);
+        let mut wynd = create_slow_server(delay);
+        wynd.set_max_concurrent_handlers(1);
+
+        let started = Instant::now();
+        let _clients = tokio::join!(connect(&wynd), connect(&wynd), connect(&wynd));
+
+        assert!(started.elapsed() >= delay * 3);
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_handlers_bounds_message_handlers() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.set_max_concurrent_handlers(2);
+        let running = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let peak = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let (running_clone, peak_clone) = (Arc::clone(&running), Arc::clone(&peak));
+        wynd.on_connection(move |conn| {
+            let (running, peak) = (Arc::clone(&running_clone), Arc::clone(&peak_clone));
+            async move {
+                conn.on_text(move |msg, handle| {
+                    let (running, peak) = (Arc::clone(&running), Arc::clone(&peak));
+                    async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(30)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        let _ = handle.send_text(msg.data).await;
+                    }
+                });
+            }
+        });
+
+        let mut clients = Vec::new();
+        for n in 0..6 {
+            clients.push(connect_to(&wynd, "/", peer_addr(n)).await);
+        }
+        for client in clients.iter_mut() {
+            for text in ["a", "b"] {
+                client.send(Message::text(text)).await.unwrap();
+            }
+        }
+
+        for client in clients.iter_mut() {
+            for expected in ["a", "b"] {
+                let reply = timeout(Duration::from_secs(2), client.next())
+                    .await
+                    .expect("handler never ran");
+                assert_eq!(reply.unwrap().unwrap(), Message::text(expected));
+            }
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_pressure_evicts_most_idle_connection() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        wynd.set_memory_pressure_policy(2, EvictionPolicy::OldestIdle);
+
+        let mut first = connect(&wynd).await;
+        let mut idle = connect(&wynd).await;
+
+        // Activity on the first connection leaves the second one as the most idle.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        first.send(Message::text("still here")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let _newcomer = connect(&wynd).await;
+
+        let mut ids: Vec<u64> = wynd.clients.lock().await.keys().copied().collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![0, 2]);
+
+        match timeout(Duration::from_millis(500), idle.next()).await {
+            Ok(Some(Ok(Message::Close(Some(frame))))) => {
+                assert_eq!(u16::from(frame.code), 1013);
+            }
+            other => panic!("expected a 1013 close frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_pressure_cap_holds_for_concurrent_accepts() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        wynd.set_memory_pressure_policy(2, EvictionPolicy::OldestIdle);
+
+        let clients =
+            futures::future::join_all((0..8).map(|n| connect_to(&wynd, "/", peer_addr(n)))).await;
+
+        assert_eq!(clients.len(), 8);
+        assert_eq!(wynd.clients.lock().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_evicted_connection_is_cleaned_up_and_stopped() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        wynd.set_memory_pressure_policy(1, EvictionPolicy::OldestIdle);
+
+        // The evicted client never reads, so it never answers the close.
+        let _evicted = connect(&wynd).await;
+        wynd.add_to_group("g", 0).await;
+        let handle = Arc::clone(&wynd.clients.lock().await[&0].1);
+
+        let _newcomer = connect(&wynd).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(wynd.group_members("g").await.is_empty());
+        assert!(!handle.task.get().unwrap().is_finished());
+
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        assert!(handle.task.get().unwrap().is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_drain_with_notice_sends_notice_before_close() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+
+        let mut first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut second = connect_to(&wynd, "/", peer_addr(2)).await;
+
+        wynd.drain_with_notice(r#"{"type":"draining"}"#).await;
+
+        for client in [&mut first, &mut second] {
+            let notice = timeout(Duration::from_millis(500), client.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            assert_eq!(notice, Message::text(r#"{"type":"draining"}"#));
+
+            let close = timeout(Duration::from_millis(500), client.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            match close {
+                Message::Close(Some(frame)) => assert_eq!(frame.code, CloseCode::Away),
+                other => panic!("expected a close frame, got {:?}", other),
+            }
+        }
+        assert!(wynd.is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_fast_close_all_closes_every_client_within_timeout() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+
+        let mut clients = Vec::new();
+        for n in 0..200 {
+            clients.push(connect_to(&wynd, "/", peer_addr(n)).await);
+        }
+
+        assert!(matches!(
+            wynd.fast_close_all(1006, "restarting", Duration::from_secs(1))
+                .await,
+            Err(WyndError::InvalidPayload(_))
+        ));
+        assert!(!wynd.is_draining());
+
+        let started = Instant::now();
+        let closed = wynd
+            .fast_close_all(1012, "restarting", Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(closed, 200);
+        assert!(wynd.is_draining());
+
+        for client in clients.iter_mut() {
+            match timeout(Duration::from_millis(500), client.next()).await {
+                Ok(Some(Ok(Message::Close(Some(frame))))) => {
+                    assert_eq!(frame.code, CloseCode::Restart);
+                    assert_eq!(frame.reason, "restarting");
+                }
+                other => panic!("expected a close frame, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_draining_server_refuses_new_connections() {
+        let wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.drain().await;
+
+        let (_client_io, server_io) = tokio::io::duplex(1024);
+        let result = wynd.handle_connection(server_io, peer_addr(1)).await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bind_failure_exposes_io_error_as_source() {
+        let occupied = tokio::net::TcpListener::bind("0.0.0.0:0").await.unwrap();
+        let port = occupied.local_addr().unwrap().port();
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        let err = wynd.listen(port, || {}).await.unwrap_err();
+
+        let err = err
+            .downcast_ref::<crate::types::WyndError>()
+            .expect("bind failures are reported as WyndError");
+        let source = std::error::Error::source(err).expect("WyndError keeps its source");
+        let io_err = source
+            .downcast_ref::<std::io::Error>()
+            .expect("the source is the io error");
+        assert_eq!(io_err.kind(), std::io::ErrorKind::AddrInUse);
+    }
+
+    #[tokio::test]
+    async fn test_listen_without_connection_handler_fails() {
+        let wynd: Wynd<Standalone> = Wynd::new();
+        let listening = Arc::new(Mutex::new(false));
+        let listening_clone = Arc::clone(&listening);
+
+        let err = timeout(
+            Duration::from_millis(500),
+            wynd.listen(0, move || *listening_clone.lock().unwrap() = true),
+        )
+        .await
+        .expect("listen should fail instead of serving")
+        .unwrap_err();
+
+        assert!(err.downcast_ref::<crate::types::WyndError>().is_some());
+        assert!(err.to_string().contains("on_connection"));
+        assert!(!*listening.lock().unwrap());
+
+        let Err(err) = Wynd::<Standalone>::new().spawn_on(0, &tokio::runtime::Handle::current())
+        else {
+            panic!("spawn_on should fail without a connection handler");
+        };
+        assert!(err.to_string().contains("on_connection"));
+    }
+
+    #[tokio::test]
+    async fn test_injected_id_generator_assigns_ids() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let sequence = Arc::new(Mutex::new(vec![7, 42, 1000].into_iter()));
+        wynd.set_id_generator(Arc::new(move || sequence.lock().unwrap().next().unwrap()));
+
+        let _first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let _second = connect_to(&wynd, "/", peer_addr(2)).await;
+        let _third = connect_to(&wynd, "/", peer_addr(3)).await;
+
+        let mut ids: Vec<u64> = wynd.clients.lock().await.keys().copied().collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![7, 42, 1000]);
+    }
+
+    #[test]
+    fn test_spawn_on_runs_server_on_given_runtime() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let _ = handle.send_text(format!("echo: {}", msg.data)).await;
+            });
+        });
+
+        // Called outside the runtime; the accept loop runs on it in the background.
+        let server = wynd.spawn_on(0, runtime.handle()).unwrap();
+        let url = format!("ws://127.0.0.1:{}/", server.local_addr().port());
+
+        runtime.block_on(async move {
+            let (mut client, _) = connect_async(url).await.unwrap();
+            client.send(Message::text("hi")).await.unwrap();
+            let reply = timeout(Duration::from_millis(500), client.next())
+                .await
+                .expect("no reply from spawned server");
+            assert_eq!(reply.unwrap().unwrap(), Message::text("echo: hi"));
+            assert_eq!(server.server().clients.lock().await.len(), 1);
+
+            server.shutdown().await;
+            let close = timeout(Duration::from_millis(500), client.next())
+                .await
+                .expect("shutdown did not close the client");
+            assert!(matches!(close, Some(Ok(Message::Close(_)))));
+        });
+    }
+
+    #[tokio::test]
+    async fn test_health_path_answers_plain_http_on_the_websocket_port() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.set_health_path("/healthz");
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let _ = handle.send_text(msg.data).await;
+            });
+        });
+        let server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+        let port = server.local_addr().port();
+
+        let mut probe = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        probe
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        timeout(
+            Duration::from_millis(500),
+            probe.read_to_string(&mut response),
+        )
+        .await
+        .expect("health check was not answered")
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("\r\n\r\nOK"));
+        assert!(server.server().clients.lock().await.is_empty());
+
+        // WebSocket clients still connect on the same port.
+        let (mut client, _) = connect_async(format!("ws://127.0.0.1:{}/", port))
+            .await
+            .unwrap();
+        client.send(Message::text("ping")).await.unwrap();
+        let reply = timeout(Duration::from_millis(500), client.next())
+            .await
+            .expect("no reply over WebSocket");
+        assert_eq!(reply.unwrap().unwrap(), Message::text("ping"));
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_health_path_is_not_answered_for_blocked_peers() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.set_health_path("/healthz");
+        wynd.set_ip_blocklist(vec!["127.0.0.1/32".parse().unwrap()]);
+        wynd.on_connection(|_| async move {});
+        let server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+        let port = server.local_addr().port();
+
+        let mut probe = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        probe
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        let read = timeout(Duration::from_millis(500), probe.read_to_end(&mut response))
+            .await
+            .expect("blocked peer was not dropped");
+        assert!(matches!(read, Ok(0) | Err(_)), "{:?}", response);
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_request_split_across_writes_reaches_the_hook() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_upgrade(|_| async move { Err(UpgradeRejection::new(401, "unauthorized")) });
+        wynd.on_connection(|_| async move {});
+        let server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+        let port = server.local_addr().port();
+
+        let mut slow = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        slow.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        slow.write_all(
+            b"Upgrade: websocket\r\nSec-WebSocket-Version: 13\r\n\
+              Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        )
+        .await
+        .unwrap();
+        let mut response = String::new();
+        timeout(
+            Duration::from_millis(500),
+            slow.read_to_string(&mut response),
+        )
+        .await
+        .expect("rejection was not sent")
+        .unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+            "{}",
+            response
+        );
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_hook_rejects_before_a_connection_exists() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_upgrade(|headers| async move {
+            match headers.get("authorization").map(String::as_str) {
+                Some("Bearer secret") => Ok(()),
+                _ => Err(UpgradeRejection::new(401, "unauthorized")),
+            }
+        });
+        wynd.on_connection(|_| async move {});
+        let server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+        let port = server.local_addr().port();
+
+        let mut intruder = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        intruder
+            .write_all(
+                b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\n\
+                  Upgrade: websocket\r\nSec-WebSocket-Version: 13\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        timeout(
+            Duration::from_millis(500),
+            intruder.read_to_string(&mut response),
+        )
+        .await
+        .expect("rejection was not sent")
+        .unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+            "{}",
+            response
+        );
+        assert!(response.ends_with("\r\n\r\nunauthorized"));
+        assert!(server.server().client_ids().await.is_empty());
+
+        let mut request = format!("ws://127.0.0.1:{}/", port)
+            .into_client_request()
+            .unwrap();
+        request
+            .headers_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        let (_client, _) = connect_async(request).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // The refused request did not use up an ID.
+        assert_eq!(server.server().client_ids().await, vec![0]);
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_uptime_counts_from_server_start() {
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        assert_eq!(wynd.uptime(), None);
+        assert_eq!(wynd.started_at(), None);
+        assert_eq!(wynd.metrics().await.uptime, None);
+
+        let before = std::time::SystemTime::now();
+        let server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let uptime = server.server().uptime().unwrap();
+        assert!(uptime >= Duration::from_millis(20), "{:?}", uptime);
+        assert!(server.server().started_at().unwrap() >= before);
+        let reported = server.server().metrics().await.uptime.unwrap();
+        assert!(reported >= uptime);
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_listen_on_binds_given_address_and_reports_it() {
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let _ = handle.send_text(msg.data).await;
+            });
+        });
+        let shutdown = wynd.shutdown_handle();
+        let (bound_tx, bound_rx) = tokio::sync::oneshot::channel();
+
+        let server = tokio::spawn(async move {
+            wynd.listen_on("0.0.0.0:0", move |addr| {
+                let _ = bound_tx.send(addr);
+            })
+            .await
+            .is_ok()
+        });
+        let addr = timeout(Duration::from_secs(1), bound_rx)
+            .await
+            .expect("on_listening was not called")
+            .unwrap();
+        assert!(addr.ip().is_unspecified());
+        assert_ne!(addr.port(), 0);
+
+        let (mut client, _) = connect_async(format!("ws://127.0.0.1:{}/", addr.port()))
+            .await
+            .expect("client should connect");
+        client.send(Message::text("ping")).await.unwrap();
+        let reply = timeout(Duration::from_millis(500), client.next())
+            .await
+            .expect("no echo received");
+        assert_eq!(reply.unwrap().unwrap(), Message::text("ping"));
+
+        shutdown.shutdown();
+        let _ = timeout(Duration::from_millis(500), client.next()).await;
+        let _ = timeout(Duration::from_millis(500), client.next()).await;
+        assert!(timeout(Duration::from_secs(1), server)
+            .await
+            .unwrap()
+            .unwrap());
+    }
+
+    /// Starts `wynd` and checks that a client that connects and sends
+    /// nothing is dropped once the handshake timeout passes.
+    async fn assert_idle_client_dropped(mut wynd: Wynd<Standalone>) {
+        use tokio::io::AsyncReadExt;
+
+        wynd.on_connection(|_| async move {});
+        let server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+
+        let mut idle = tokio::net::TcpStream::connect(server.local_addr())
+            .await
+            .unwrap();
+        let mut byte = [0u8; 1];
+        let read = timeout(Duration::from_secs(15), idle.read(&mut byte))
+            .await
+            .expect("idle client was not dropped");
+        assert!(matches!(read, Ok(0) | Err(_)));
+        assert_eq!(server.server().metrics().await.handshake_failures, 1);
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_idle_clients_are_dropped_after_the_handshake_timeout() {
+        assert_idle_client_dropped(Wynd::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_idle_clients_are_dropped_when_a_health_path_is_set() {
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.set_health_path("/healthz");
+        assert_idle_client_dropped(wynd).await;
+    }
+
+    #[tokio::test]
+    async fn test_oversized_upgrade_headers_are_rejected() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.set_max_header_size(1024);
+        wynd.on_connection(|_| async move {});
+        let server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+        let port = server.local_addr().port();
+
+        let mut attacker = tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .unwrap();
+        let request = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\n\
+             Upgrade: websocket\r\nSec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(4096)
+        );
+        attacker.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        timeout(
+            Duration::from_millis(500),
+            attacker.read_to_string(&mut response),
+        )
+        .await
+        .expect("rejection was not sent")
+        .unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"),
+            "{}",
+            response
+        );
+        assert!(server.server().client_ids().await.is_empty());
+        assert_eq!(server.server().metrics().await.handshake_failures, 1);
+
+        // Requests within the limit are still upgraded.
+        let (_client, _) = connect_async(format!("ws://127.0.0.1:{}/", port))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(server.server().client_ids().await, vec![0]);
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_rebind_moves_listener_and_keeps_existing_connections() {
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|msg, handle| async move {
+                let _ = handle.send_text(msg.data).await;
+            });
+        });
+        let mut server = wynd
+            .spawn_on(0, &tokio::runtime::Handle::current())
+            .unwrap();
+        let old_port = server.local_addr().port();
+
+        let (mut old_client, _) = connect_async(format!("ws://127.0.0.1:{}/", old_port))
+            .await
+            .unwrap();
+
+        server.rebind(0).unwrap();
+        let new_port = server.local_addr().port();
+        assert_ne!(new_port, old_port);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The connection made before the rebind still works.
+        old_client.send(Message::text("still here")).await.unwrap();
+        let reply = timeout(Duration::from_millis(500), old_client.next())
+            .await
+            .expect("old connection stopped responding");
+        assert_eq!(reply.unwrap().unwrap(), Message::text("still here"));
+
+        // New connections land on the new port; the old one is closed.
+        let (mut new_client, _) = connect_async(format!("ws://127.0.0.1:{}/", new_port))
+            .await
+            .unwrap();
+        new_client.send(Message::text("hello")).await.unwrap();
+        let reply = timeout(Duration::from_millis(500), new_client.next())
+            .await
+            .expect("new connection got no reply");
+        assert_eq!(reply.unwrap().unwrap(), Message::text("hello"));
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", old_port))
+            .await
+            .is_err());
+        assert_eq!(server.server().clients.lock().await.len(), 2);
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_setup_time_is_recorded_when_connection_opens() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        let recorded = Arc::new(Mutex::new(None));
+
+        let recorded_clone = Arc::clone(&recorded);
+        wynd.on_connection(move |conn| {
+            let recorded = Arc::clone(&recorded_clone);
+            async move {
+                conn.on_open(move |handle| {
+                    let recorded = Arc::clone(&recorded);
+                    async move {
+                        *recorded.lock().unwrap() = Some(handle.setup_time());
+                    }
+                })
+                .await;
+            }
+        });
+
+        assert_eq!(wynd.setup_time_histogram().count, 0);
+        let _client = connect(&wynd).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let setup = recorded
+            .lock()
+            .unwrap()
+            .expect("on_open should have run")
+            .expect("setup time should be recorded before on_open");
+        assert!(setup > Duration::ZERO);
+        assert!(setup < Duration::from_secs(5));
+
+        let histogram = wynd.setup_time_histogram();
+        assert_eq!(histogram.count, 1);
+        assert_eq!(
+            histogram.sum,
+            Duration::from_micros(setup.as_micros() as u64)
+        );
+        let bucketed: u64 = histogram.buckets.iter().map(|(_, count)| count).sum();
+        assert_eq!(bucketed + histogram.overflow, 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_handle_closes_clients_and_stops_listen() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let mut wynd: Wynd<Standalone> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        wynd.set_shutdown_timeout(Duration::from_secs(2));
+        let shutdown = wynd.shutdown_handle();
+        assert!(!shutdown.is_shutdown_requested());
+
+        let server = tokio::spawn(async move { wynd.listen(port, || {}).await.is_ok() });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let url = format!("ws://127.0.0.1:{}", port);
+        let (mut client, _) = connect_async(&url).await.expect("client should connect");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        shutdown.shutdown();
+        assert!(shutdown.is_shutdown_requested());
+
+        match timeout(Duration::from_millis(500), client.next()).await {
+            Ok(Some(Ok(Message::Close(Some(frame))))) => {
+                assert_eq!(frame.code, CloseCode::Away);
+            }
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+        // Reading on completes the closing handshake.
+        let _ = timeout(Duration::from_millis(500), client.next()).await;
+
+        let returned = timeout(Duration::from_secs(1), server)
+            .await
+            .expect("listen should return once clients disconnect")
+            .unwrap();
+        assert!(returned);
+
+        assert!(
+            timeout(Duration::from_millis(500), connect_async(&url))
+                .await
+                .map_or(true, |result| result.is_err()),
+            "no new connections should be accepted after shutdown"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_close_handler_runs_once_on_graceful_shutdown() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        let counted = Arc::clone(&calls);
+        wynd.on_close(move || {
+            counted.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let mut client = connect(&wynd).await;
+        let closing = tokio::spawn(async move {
+            // Answer the close frame so the server sees the client leave.
+            while let Some(Ok(_)) = client.next().await {}
+        });
+
+        timeout(Duration::from_secs(1), wynd.shut_down_gracefully())
+            .await
+            .expect("shutdown should finish once the client leaves");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(wynd.client_ids().await.is_empty());
+        let _ = closing.await;
+
+        // Dropping the shut-down server does not run the handler again.
+        drop(wynd);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_client_accessors_count_list_and_target_clients() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+
+        assert_eq!(wynd.clients_count().await, 0);
+        assert!(wynd.client_ids().await.is_empty());
+
+        let mut clients = Vec::new();
+        for n in 0..3 {
+            clients.push(connect_to(&wynd, "/", peer_addr(n)).await);
+        }
+
+        assert_eq!(wynd.clients_count().await, 3);
+        let ids = wynd.client_ids().await;
+        assert_eq!(ids.len(), 3);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let target = ids[1];
+        let sent = wynd
+            .with_client(target, |handle| async move {
+                assert_eq!(handle.id(), target);
+                handle.send_text("just you").await.is_ok()
+            })
+            .await;
+        assert_eq!(sent, Some(true));
+        assert_eq!(
+            recv_text(&mut clients[1]).await.as_deref(),
+            Some("just you")
+        );
+        assert_eq!(recv_text(&mut clients[0]).await, None);
+        assert_eq!(recv_text(&mut clients[2]).await, None);
+
+        let missing = wynd.with_client(u64::MAX, |_handle| async move {}).await;
+        assert!(missing.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_aborts_stuck_connection_after_timeout() {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|_conn| async move {});
+        wynd.set_shutdown_timeout(Duration::from_millis(200));
+
+        // The client never reads, so a large write fills the in-memory pipe
+        // and holds the connection's writer forever.
+        let _stuck = connect(&wynd).await;
+        let id = wynd.client_ids().await[0];
+        let handle = wynd
+            .with_client(id, |handle| async move { handle })
+            .await
+            .unwrap();
+        let message_loop = Arc::clone(&handle.task);
+        let writer = tokio::spawn(async move { handle.send_binary(vec![0; 1 << 20]).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!writer.is_finished());
+
+        let started = Instant::now();
+        timeout(Duration::from_secs(2), wynd.shut_down_gracefully())
+            .await
+            .expect("shutdown should not hang on a stuck client");
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_secs(1));
+        assert_eq!(wynd.clients_count().await, 0);
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(message_loop.get().unwrap().is_finished());
+    }
+
+    fn thread_echo_server() -> Wynd<DuplexStream> {
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_msg, handle| async move {
+                let thread = std::thread::current();
+                let _ = handle.send_text(thread.name().unwrap_or("")).await;
+            });
+        });
+        wynd
+    }
+
+    #[tokio::test]
+    async fn test_hash_by_id_affinity_pins_message_loops_to_workers() {
+        let mut wynd = thread_echo_server();
+        wynd.set_connection_affinity(ConnectionAffinity::HashById { workers: 2 })
+            .unwrap();
+        assert_eq!(wynd.connection_workers(), 2);
+
+        let mut first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut second = connect_to(&wynd, "/", peer_addr(2)).await;
+        for _ in 0..3 {
+            first.send(Message::text("where")).await.unwrap();
+            second.send(Message::text("where")).await.unwrap();
+            assert_eq!(
+                recv_text(&mut first).await.as_deref(),
+                Some("wynd-worker-0")
+            );
+            assert_eq!(
+                recv_text(&mut second).await.as_deref(),
+                Some("wynd-worker-1")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_affinity_uses_the_calling_runtime() {
+        let mut wynd = thread_echo_server();
+        wynd.set_connection_affinity(ConnectionAffinity::Shared)
+            .unwrap();
+        assert_eq!(wynd.connection_workers(), 0);
+
+        let mut client = connect(&wynd).await;
+        client.send(Message::text("where")).await.unwrap();
+        let thread = recv_text(&mut client).await.unwrap();
+        assert!(!thread.starts_with("wynd-worker"));
+    }
+
+    #[tokio::test]
+    async fn test_on_metrics_reports_growing_message_counts() {
+        let (metrics_tx, mut metrics_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_text(|_msg, _handle| async move {});
+        });
+        wynd.on_metrics(Duration::from_millis(50), move |metrics| {
+            let _ = metrics_tx.send(metrics);
+        });
+        wynd.start_metrics_reporter();
+
+        let mut client = connect(&wynd).await;
+        let mut snapshots = Vec::new();
+        for round in 1..=2 {
+            for _ in 0..5 {
+                client.send(Message::text("tick")).await.unwrap();
+            }
+            // Wait for a snapshot that has seen this round's messages.
+            loop {
+                let metrics = timeout(Duration::from_millis(500), metrics_rx.recv())
+                    .await
+                    .expect("on_metrics did not fire")
+                    .unwrap();
+                if metrics.messages_received >= 5 * round {
+                    snapshots.push(metrics);
+                    break;
+                }
+            }
+        }
+
+        assert_eq!(snapshots[0].messages_received, 5);
+        assert_eq!(snapshots[1].messages_received, 10);
+        assert_eq!(snapshots[1].connections, 1);
+        assert_eq!(snapshots[1].connections_opened, 1);
+        assert_eq!(snapshots[1].bytes_received, 40);
+        assert_eq!(wynd.metrics().await, snapshots[1]);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_accepts_handshake_failures_and_room_messages() {
+        use tokio::io::AsyncWriteExt;
+
+        let mut wynd: Wynd<DuplexStream> = Wynd::new();
+        wynd.on_connection(|conn| async move {
+            conn.on_open(|handle| async move {
+                let _ = handle.join("lobby").await;
+            })
+            .await;
+            conn.on_text(|msg, handle| async move {
+                let room = handle.to("lobby");
+                match msg.data.as_str() {
+                    "sequence" => room.text_sequence(&["a", "b", "c"]).await.unwrap(),
+                    _ => room.text(msg.data).await.unwrap(),
+                }
+            });
+        });
+        wynd.start_room_processor();
+        let metrics = wynd.metrics_handle();
+
+        let mut first = connect_to(&wynd, "/", peer_addr(1)).await;
+        let mut second = connect_to(&wynd, "/", peer_addr(2)).await;
+
+        // A plain HTTP request is not a WebSocket handshake.
+        let (mut client_io, server_io) = tokio::io::duplex(1024);
+        client_io
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        assert!(wynd
+            .handle_connection(server_io, peer_addr(3))
+            .await
+            .is_err());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        first.send(Message::text("hello")).await.unwrap();
+        assert_eq!(recv_text(&mut second).await.as_deref(), Some("hello"));
+        second.send(Message::text("sequence")).await.unwrap();
+        for expected in ["a", "b", "c"] {
+            assert_eq!(recv_text(&mut first).await.as_deref(), Some(expected));
+        }
+
+        let snapshot = metrics.snapshot().await;
+        assert_eq!(snapshot.connections, 2);
+        assert_eq!(snapshot.connections_accepted, 2);
+        assert_eq!(snapshot.handshake_failures, 1);
+        assert_eq!(snapshot.room_messages, 4);
+        assert_eq!(wynd.metrics().await, snapshot);
+    }
 }
@@ -0,0 +1,200 @@
+//! Strongly-typed messaging on top of text frames.
+//!
+//! A [`Message`] type decides how it is encoded on the wire (JSON by
+//! default). [`Connection::typed`] registers handlers that receive decoded
+//! values, and [`TypedHandle::send`] encodes values before sending them.
+//! Frames that fail to decode are reported like errors from
+//! [`Connection::on_text_try`] handlers.
+
+use std::{fmt::Debug, future::Future, marker::PhantomData, ops::Deref, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    conn::Connection,
+    handle::ConnectionHandle,
+    types::{HandlerError, WyndError},
+};
+
+/// A message type exchanged over a [`TypedConnection`].
+///
+/// The default methods encode and decode JSON with `serde_json`, so most
+/// types only need an empty `impl`. Override them to use another text format.
+///
+/// ## Example
+///
+/// ```rust
+/// use serde::{Deserialize, Serialize};
+/// use wynd::typed::Message;
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// #[serde(tag = "type")]
+/// enum ChatMsg {
+///     Join { name: String },
+///     Say { text: String },
+/// }
+///
+/// impl Message for ChatMsg {}
+///
+/// let msg = ChatMsg::Say { text: "hi".to_string() };
+/// let encoded = msg.encode().unwrap();
+/// assert_eq!(ChatMsg::decode(&encoded).unwrap(), msg);
+/// ```
+pub trait Message: Serialize + DeserializeOwned + Send + 'static {
+    /// Encodes the message as a text frame payload.
+    fn encode(&self) -> Result<String, HandlerError> {
+        serde_json::to_string(self)
+            .map_err(|e| HandlerError::new(format!("failed to encode message: {}", e)))
+    }
+
+    /// Decodes a message from a text frame payload.
+    ///
+    /// The returned error is reported to the client or logged, so it defaults
+    /// to code `400`.
+    fn decode(text: &str) -> Result<Self, HandlerError> {
+        serde_json::from_str(text)
+            .map_err(|e| HandlerError::new(format!("invalid message: {}", e)).with_code(400))
+    }
+}
+
+/// Typed view of a [`Connection`], created by [`Connection::typed`].
+pub struct TypedConnection<'a, M, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    conn: &'a Connection<T>,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<'a, M, T> TypedConnection<'a, M, T>
+where
+    M: Message,
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    pub(crate) fn new(conn: &'a Connection<T>) -> Self {
+        Self {
+            conn,
+            _message: PhantomData,
+        }
+    }
+
+    /// Registers a handler for decoded messages.
+    ///
+    /// Replaces any handler registered with `on_text` or `on_text_try`.
+    /// Frames that fail to decode never reach the handler; their error is sent
+    /// to the client as JSON when `Wynd::set_auto_error_reply` is enabled, and
+    /// logged otherwise.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes the decoded message and a [`TypedHandle`]
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use serde::{Deserialize, Serialize};
+    /// use wynd::typed::Message;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Ping {
+    ///     seq: u32,
+    /// }
+    ///
+    /// impl Message for Ping {}
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.typed::<Ping>().on_message(|ping, handle| async move {
+    ///         let _ = handle.send(&Ping { seq: ping.seq + 1 }).await;
+    ///     });
+    /// });
+    /// ```
+    pub fn on_message<F, Fut>(&self, handler: F)
+    where
+        F: Fn(M, TypedHandle<M, T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.conn.on_text_try(move |msg, handle| {
+            let handler = Arc::clone(&handler);
+            async move {
+                let message = M::decode(&msg.data)?;
+                handler(message, TypedHandle::new(handle)).await;
+                Ok(())
+            }
+        });
+    }
+}
+
+/// A [`ConnectionHandle`] that sends values of a [`Message`] type.
+///
+/// Dereferences to the underlying handle, so the untyped API stays available.
+pub struct TypedHandle<M, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    handle: Arc<ConnectionHandle<T>>,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M, T> TypedHandle<M, T>
+where
+    M: Message,
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    /// Wraps a connection handle.
+    pub fn new(handle: Arc<ConnectionHandle<T>>) -> Self {
+        Self {
+            handle,
+            _message: PhantomData,
+        }
+    }
+
+    /// Encodes a message and sends it as a text frame.
+    ///
+    /// ## Parameters
+    ///
+    /// - `message`: The message to send
+    ///
+    /// ## Returns
+    ///
+    /// Returns [`WyndError::InvalidPayload`] if the message cannot be
+    /// encoded, or the error of the underlying send.
+    pub async fn send(&self, message: &M) -> Result<(), WyndError> {
+        let text = message
+            .encode()
+            .map_err(|e| WyndError::InvalidPayload(e.to_string()))?;
+        self.handle.send_text(text).await
+    }
+
+    /// Returns the underlying connection handle.
+    pub fn inner(&self) -> &Arc<ConnectionHandle<T>> {
+        &self.handle
+    }
+}
+
+impl<M, T> Clone for TypedHandle<M, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            handle: Arc::clone(&self.handle),
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<M, T> Deref for TypedHandle<M, T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    type Target = ConnectionHandle<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
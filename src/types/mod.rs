@@ -8,9 +8,16 @@
 
 use std::{
     fmt::{Debug, Display},
-    ops::Deref,
+    net::SocketAddr,
+    ops::{BitOr, Deref},
+    time::Instant,
 };
 
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Error as TungsteniteError;
+
+use crate::conn::ConnState;
+
 /// Represents a text message event received from a WebSocket client.
 ///
 /// This event is triggered when a text message is received from the client.
@@ -122,6 +129,180 @@ impl BinaryMessageEvent {
     }
 }
 
+/// A text or binary message received from a WebSocket client.
+///
+/// Passed to handlers registered with
+/// [`Connection::on_message`](crate::conn::Connection::on_message), which
+/// handle both kinds of data message in one place.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::types::IncomingMessage;
+/// use wynd::wynd::{Wynd, Standalone};
+///
+/// let mut wynd: Wynd<Standalone> = Wynd::new();
+///
+/// wynd.on_connection(|conn| async move {
+///     conn.on_message(|msg, _handle| async move {
+///         match msg {
+///             IncomingMessage::Text(text) => println!("text: {}", text),
+///             IncomingMessage::Binary(data) => println!("{} bytes", data.len()),
+///         }
+///     });
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncomingMessage {
+    /// A UTF-8 text message.
+    Text(String),
+    /// A binary message.
+    Binary(Vec<u8>),
+}
+
+impl IncomingMessage {
+    /// Returns the message payload as bytes, whatever its kind.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Binary(data) => data,
+        }
+    }
+}
+
+/// Represents a pong frame received from the client.
+///
+/// Pongs answer pings sent by the server, so the arrival time can be compared
+/// with the time a ping was sent to measure round-trip latency.
+///
+/// ## Example
+///
+/// ```rust
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Instant;
+/// use wynd::wynd::{Wynd, Standalone};
+///
+/// let mut wynd: Wynd<Standalone> = Wynd::new();
+///
+/// wynd.on_connection(|conn| async move {
+///     let sent_at = Arc::new(Mutex::new(None::<Instant>));
+///
+///     let pinged = Arc::clone(&sent_at);
+///     conn.on_pong(move |event, handle| {
+///         let pinged = Arc::clone(&pinged);
+///         async move {
+///             if let Some(sent) = *pinged.lock().unwrap() {
+///                 let rtt = event.received_at.duration_since(sent);
+///                 println!("Connection {} round trip: {:?}", handle.id(), rtt);
+///             }
+///         }
+///     });
+///
+///     conn.on_open(move |handle| {
+///         let sent_at = Arc::clone(&sent_at);
+///         async move {
+///             *sent_at.lock().unwrap() = Some(Instant::now());
+///             let _ = handle.send_ping(Vec::new()).await;
+///         }
+///     })
+///     .await;
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct PongEvent {
+    /// The pong payload, normally echoing the payload of a ping.
+    pub data: Vec<u8>,
+    /// When the pong frame was read from the socket.
+    pub received_at: Instant,
+}
+
+impl PongEvent {
+    /// Creates a pong event for a frame that arrived now.
+    pub(crate) fn new<T: Into<Vec<u8>>>(data: T) -> Self {
+        Self {
+            data: data.into(),
+            received_at: Instant::now(),
+        }
+    }
+}
+
+/// A connection-level error reported to
+/// [`Connection::on_error`](crate::conn::Connection::on_error).
+///
+/// Fired when reading from the socket fails, right before the connection is
+/// closed, so apps can log a structured record of why it ended.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::types::ConnectionErrorKind;
+/// use wynd::wynd::{Wynd, Standalone};
+///
+/// let mut wynd: Wynd<Standalone> = Wynd::new();
+///
+/// wynd.on_connection(|conn| async move {
+///     conn.on_error(|err, handle| async move {
+///         if err.kind == ConnectionErrorKind::Utf8 {
+///             eprintln!("client {} sent invalid UTF-8: {}", handle.id(), err.message);
+///         }
+///     });
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorEvent {
+    /// What kind of failure occurred.
+    pub kind: ConnectionErrorKind,
+    /// The underlying error's message.
+    pub message: String,
+}
+
+impl ErrorEvent {
+    /// Creates an error event from a WebSocket read error.
+    pub(crate) fn new(error: &TungsteniteError) -> Self {
+        Self {
+            kind: ConnectionErrorKind::from(error),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Classification of a connection-level error.
+///
+/// - `Protocol`: The client broke the WebSocket protocol
+/// - `Io`: Reading from the socket failed
+/// - `Utf8`: A text message was not valid UTF-8
+/// - `Capacity`: A message or frame exceeded the server's size limit
+/// - `Other`: Any other failure, e.g. TLS errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionErrorKind {
+    /// The client broke the WebSocket protocol.
+    Protocol,
+    /// Reading from the socket failed.
+    Io,
+    /// A text message was not valid UTF-8.
+    Utf8,
+    /// A message or frame exceeded the server's size limit.
+    Capacity,
+    /// Any other failure.
+    Other,
+}
+
+impl From<&TungsteniteError> for ConnectionErrorKind {
+    fn from(error: &TungsteniteError) -> Self {
+        match error {
+            TungsteniteError::Protocol(_)
+            | TungsteniteError::AttackAttempt
+            | TungsteniteError::HttpFormat(_) => ConnectionErrorKind::Protocol,
+            TungsteniteError::Io(_) => ConnectionErrorKind::Io,
+            TungsteniteError::Utf8(_) => ConnectionErrorKind::Utf8,
+            TungsteniteError::Capacity(_) | TungsteniteError::WriteBufferFull(_) => {
+                ConnectionErrorKind::Capacity
+            }
+            _ => ConnectionErrorKind::Other,
+        }
+    }
+}
+
 /// Represents a WebSocket connection close event.
 ///
 /// This event is triggered when a WebSocket connection is closed,
@@ -177,14 +358,134 @@ pub struct CloseEvent {
     /// The WebSocket close code indicating the reason for closure.
     pub code: u16,
     /// A human-readable description of the closure reason.
+    ///
+    /// Reasons sent by the client have control characters stripped and are
+    /// truncated to the configured maximum length.
     pub reason: String,
+    /// The range the close code belongs to.
+    kind: CloseCodeKind,
+    /// What caused the connection to close.
+    cause: CloseCause,
 }
 
 impl Clone for CloseEvent {
     fn clone(&self) -> Self {
         CloseEvent {
-            code: self.code.clone(),
+            code: self.code,
             reason: self.reason.clone(),
+            kind: self.kind,
+            cause: self.cause,
+        }
+    }
+}
+
+/// A set of data message kinds a connection accepts.
+///
+/// Combine kinds with `|`, for example `MessageKinds::TEXT | MessageKinds::BINARY`.
+/// Used with [`Connection::accept_only`](crate::conn::Connection::accept_only).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageKinds(u8);
+
+impl MessageKinds {
+    /// UTF-8 text messages.
+    pub const TEXT: Self = Self(1);
+    /// Binary messages.
+    pub const BINARY: Self = Self(1 << 1);
+    /// Both text and binary messages.
+    pub const ALL: Self = Self(Self::TEXT.0 | Self::BINARY.0);
+
+    /// Returns `true` if every kind in `other` is also in `self`.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub(crate) fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub(crate) fn from_bits(bits: u8) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+}
+
+impl Default for MessageKinds {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl BitOr for MessageKinds {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// What caused a connection to close.
+///
+/// Lets apps tell a client that said goodbye apart from a connection whose
+/// network died.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseCause {
+    /// The client sent a close frame.
+    Remote,
+    /// The client did not answer a keepalive ping in time.
+    ///
+    /// Reported with code `1006` and reason `"ping timeout"`.
+    PingTimeout,
+    /// The client exceeded the server's total bytes limit for a connection.
+    ///
+    /// Reported with code `1009` and reason `"total bytes limit exceeded"`.
+    TotalBytesExceeded,
+    /// The client sent a message kind the connection does not accept.
+    ///
+    /// Reported with code `1003` and reason `"unsupported data"`.
+    UnsupportedData,
+    /// The client sent a message or frame over the server's size limit.
+    ///
+    /// Reported with code `1009` and reason `"message too large"`.
+    MessageTooLarge,
+    /// The connection ended without a close frame, e.g. the client's
+    /// network dropped or it broke the protocol.
+    ///
+    /// Reported with code `1006` and reason `"abnormal closure"`.
+    Abnormal,
+}
+
+/// The range a WebSocket close code belongs to.
+///
+/// RFC 6455 splits close codes into ranges owned by different parties. This
+/// lets apps tell their own codes apart from codes sent by the protocol or
+/// by libraries.
+///
+/// - `Protocol`: Codes defined by the WebSocket protocol (`1000`–`1015`)
+/// - `Reserved`: Codes reserved for future protocol revisions (`1016`–`2999`)
+/// - `Registered`: Codes registered for libraries and frameworks (`3000`–`3999`)
+/// - `Application`: Private codes for applications (`4000`–`4999`)
+/// - `Invalid`: Codes outside of every valid range, or not assigned by the protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseCodeKind {
+    /// A code defined by the WebSocket protocol.
+    Protocol,
+    /// A code reserved for future protocol revisions.
+    Reserved,
+    /// A code registered for use by libraries and frameworks.
+    Registered,
+    /// A private code for use by applications.
+    Application,
+    /// A code that is not valid in a close frame.
+    Invalid,
+}
+
+impl From<CloseCode> for CloseCodeKind {
+    fn from(code: CloseCode) -> Self {
+        match code {
+            CloseCode::Reserved(_) => CloseCodeKind::Reserved,
+            CloseCode::Iana(_) => CloseCodeKind::Registered,
+            CloseCode::Library(_) => CloseCodeKind::Application,
+            CloseCode::Bad(_) => CloseCodeKind::Invalid,
+            _ => CloseCodeKind::Protocol,
         }
     }
 }
@@ -201,7 +502,112 @@ impl CloseEvent {
     ///
     /// Returns a new `CloseEvent` with the provided code and reason.
     pub(crate) fn new(code: u16, reason: String) -> Self {
-        Self { code, reason }
+        Self::from_close_code(CloseCode::from(code), reason)
+    }
+
+    /// Creates a close event from a close code as received in a close frame.
+    pub(crate) fn from_close_code(code: CloseCode, reason: String) -> Self {
+        Self {
+            code: code.into(),
+            reason,
+            kind: CloseCodeKind::from(code),
+            cause: CloseCause::Remote,
+        }
+    }
+
+    /// Longest close reason, in characters, kept by default.
+    ///
+    /// Matches the 123 bytes a close frame can carry.
+    pub(crate) const MAX_REASON_LEN: usize = 123;
+
+    /// Creates a close event from a close frame sent by the client.
+    ///
+    /// The reason is untrusted, so control characters are stripped and it is
+    /// truncated to `max_len` characters before it reaches handlers or logs.
+    pub(crate) fn from_remote(code: CloseCode, reason: &str, max_len: usize) -> Self {
+        let reason = reason
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(max_len)
+            .collect();
+        Self::from_close_code(code, reason)
+    }
+
+    /// Creates the close event reported when a keepalive ping goes unanswered.
+    pub(crate) fn ping_timeout() -> Self {
+        Self {
+            cause: CloseCause::PingTimeout,
+            ..Self::new(1006, "ping timeout".to_string())
+        }
+    }
+
+    /// Creates the close event reported when a client sends a message kind
+    /// the connection does not accept.
+    pub(crate) fn unsupported_data() -> Self {
+        Self {
+            cause: CloseCause::UnsupportedData,
+            ..Self::new(1003, "unsupported data".to_string())
+        }
+    }
+
+    /// Creates the close event reported when a client sends a message or
+    /// frame over the configured size limit.
+    pub(crate) fn message_too_large() -> Self {
+        Self {
+            cause: CloseCause::MessageTooLarge,
+            ..Self::new(1009, "message too large".to_string())
+        }
+    }
+
+    /// Creates the close event reported when a client exceeds its total bytes limit.
+    pub(crate) fn total_bytes_exceeded() -> Self {
+        Self {
+            cause: CloseCause::TotalBytesExceeded,
+            ..Self::new(1009, "total bytes limit exceeded".to_string())
+        }
+    }
+
+    /// Creates the close event reported when the connection ends without a
+    /// close frame.
+    pub(crate) fn abnormal() -> Self {
+        Self {
+            cause: CloseCause::Abnormal,
+            ..Self::new(1006, "abnormal closure".to_string())
+        }
+    }
+
+    /// Returns what caused the connection to close.
+    pub fn cause(&self) -> CloseCause {
+        self.cause
+    }
+
+    /// Returns the range the close code belongs to.
+    pub fn kind(&self) -> CloseCodeKind {
+        self.kind
+    }
+
+    /// Returns `true` if the close code is an application code (`4000`–`4999`).
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.on_connection(|conn| async move {
+    ///         conn.on_close(|event| async move {
+    ///             if event.is_application_code() {
+    ///                 println!("App-defined close {}: {}", event.code, event.reason);
+    ///             }
+    ///         });
+    ///     });
+    /// }
+    /// ```
+    pub fn is_application_code(&self) -> bool {
+        self.kind == CloseCodeKind::Application
     }
 }
 
@@ -215,11 +621,16 @@ impl Display for CloseEvent {
     }
 }
 
-/// Represents a Wynd server error.
+/// Error type for server-level failures and for sends on a connection.
+///
+/// Server errors, such as connection acceptance failures or WebSocket
+/// handshake errors, are reported as [`WyndError::Server`]. Send methods on
+/// [`ConnectionHandle`](crate::handle::ConnectionHandle) return the other
+/// variants, so callers can tell a client that went away from a failed write
+/// or a payload that was rejected before sending.
 ///
-/// This type is used to represent errors that occur at the server level,
-/// such as connection acceptance failures, WebSocket handshake errors,
-/// or other server-related issues.
+/// Every variant dereferences to its message, which is also its `Display`
+/// output.
 ///
 /// ## Example
 ///
@@ -241,22 +652,54 @@ impl Display for CloseEvent {
 ///         }
 ///     });
 ///
+///     wynd.on_connection(|conn| async move {
+///         conn.on_text(|msg, handle| async move {
+///             match handle.send_text(msg.data).await {
+///                 Ok(()) | Err(WyndError::ConnectionClosed) => {}
+///                 Err(e) => eprintln!("echo failed: {}", e),
+///             }
+///         });
+///     });
+///
 ///     wynd.listen(8080, || {
 ///         println!("Server listening on port 8080");
 ///     });
 /// }
 /// ```
 #[derive(Debug)]
-pub struct WyndError {
-    /// The internal error message.
-    inner: String,
+pub enum WyndError {
+    /// A server-level failure, such as a bind, accept or handshake error.
+    Server {
+        /// What went wrong.
+        message: String,
+        /// The underlying error, if this error wraps one.
+        source: Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    },
+    /// The connection is closed or closing, so nothing was sent.
+    ConnectionClosed,
+    /// Writing to the connection failed.
+    WriteFailed {
+        /// The underlying error's message.
+        message: String,
+        /// The underlying error.
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+    /// The payload was rejected before anything was sent.
+    InvalidPayload(String),
+    /// The connection's send queue is full, so the frame was not queued.
+    Backpressure,
 }
 
 impl Deref for WyndError {
     type Target = str;
 
     fn deref(&self) -> &Self::Target {
-        &self.inner
+        match self {
+            Self::Server { message, .. } | Self::WriteFailed { message, .. } => message,
+            Self::ConnectionClosed => "connection is closed",
+            Self::InvalidPayload(message) => message,
+            Self::Backpressure => "send queue is full",
+        }
     }
 }
 
@@ -269,16 +712,411 @@ impl WyndError {
     ///
     /// ## Returns
     ///
-    /// Returns a new `WyndError` with the provided message.
+    /// Returns a new [`WyndError::Server`] with the provided message.
     pub(crate) fn new(err: String) -> Self {
-        Self { inner: err }
+        Self::Server {
+            message: err,
+            source: None,
+        }
+    }
+
+    /// Creates a Wynd error that wraps an underlying error.
+    ///
+    /// The wrapped error is returned by [`std::error::Error::source`], so
+    /// error reporters such as `anyhow` show the full chain.
+    pub(crate) fn with_source<E>(err: String, source: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Server {
+            message: err,
+            source: Some(Box::new(source)),
+        }
+    }
+
+    /// Creates a [`WyndError::WriteFailed`] wrapping the error that stopped a write.
+    pub(crate) fn write_failed<E>(source: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::WriteFailed {
+            message: source.to_string(),
+            source: Box::new(source),
+        }
+    }
+
+    /// Returns `true` if a send was rejected because the connection's send
+    /// queue is full.
+    ///
+    /// Only connections with a send queue (see
+    /// [`Wynd::with_send_queue_capacity`](crate::wynd::Wynd::with_send_queue_capacity))
+    /// report backpressure. The message was not sent; the caller may drop it,
+    /// retry later or close the slow connection.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.with_send_queue_capacity(64);
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if let Err(e) = handle.send_text(msg.data).await {
+    ///             if e.is_backpressure() {
+    ///                 eprintln!("client {} is too slow", handle.id());
+    ///             }
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn is_backpressure(&self) -> bool {
+        matches!(self, Self::Backpressure)
     }
 }
 
 impl Display for WyndError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.inner)
+        write!(f, "{}", &**self)
+    }
+}
+
+impl std::error::Error for WyndError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Server { source, .. } => source
+                .as_deref()
+                .map(|source| source as &(dyn std::error::Error + 'static)),
+            Self::WriteFailed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for WyndError {
+    /// Classifies a WebSocket error from a send: errors caused by the peer
+    /// going away become [`WyndError::ConnectionClosed`], anything else
+    /// [`WyndError::WriteFailed`].
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        if is_connection_gone(&error) {
+            Self::ConnectionClosed
+        } else {
+            Self::write_failed(error)
+        }
+    }
+}
+
+/// Returns `true` if a WebSocket error only means the peer has gone away.
+pub(crate) fn is_connection_gone(error: &tokio_tungstenite::tungstenite::Error) -> bool {
+    use tokio_tungstenite::tungstenite::error::{Error, ProtocolError};
+
+    match error {
+        Error::ConnectionClosed | Error::AlreadyClosed => true,
+        Error::Protocol(ProtocolError::SendAfterClosing) => true,
+        Error::Io(io) => matches!(
+            io.kind(),
+            std::io::ErrorKind::BrokenPipe
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::NotConnected
+                | std::io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
+
+/// Error returned by room operations such as
+/// [`ConnectionHandle::join`](crate::handle::ConnectionHandle::join) and
+/// [`RoomMethods::text`](crate::room::RoomMethods::text).
+///
+/// Room operations are carried out by the server's room processor, so most
+/// of them can only fail with [`RoomError::ChannelClosed`] when the request
+/// cannot be handed over. The other variants describe rejections by room
+/// features that enforce capacity or membership.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::types::RoomError;
+/// use wynd::wynd::{Wynd, Standalone};
+///
+/// let mut wynd: Wynd<Standalone> = Wynd::new();
+///
+/// wynd.on_connection(|conn| async move {
+///     conn.on_open(|handle| async move {
+///         match handle.join("lobby").await {
+///             Ok(()) => {}
+///             Err(RoomError::ChannelClosed) => eprintln!("server is shutting down"),
+///             Err(e) => eprintln!("could not join: {}", e),
+///         }
+///     })
+///     .await;
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoomError {
+    /// The room processor is not running, so the request could not be delivered.
+    ChannelClosed,
+    /// The named room does not exist.
+    RoomNotFound(String),
+    /// The named room or the server has no capacity for the request.
+    CapacityExceeded(String),
+    /// The connection is not a member of the named room.
+    NotAMember(String),
+}
+
+impl Display for RoomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ChannelClosed => write!(f, "room processor is not running"),
+            Self::RoomNotFound(room) => write!(f, "room {} does not exist", room),
+            Self::CapacityExceeded(room) => write!(f, "room {} is at capacity", room),
+            Self::NotAMember(room) => write!(f, "not a member of room {}", room),
+        }
+    }
+}
+
+impl std::error::Error for RoomError {}
+
+impl<T> From<tokio::sync::mpsc::error::SendError<T>> for RoomError {
+    fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
+        Self::ChannelClosed
+    }
+}
+
+impl From<tokio::sync::oneshot::error::RecvError> for RoomError {
+    fn from(_: tokio::sync::oneshot::error::RecvError) -> Self {
+        Self::ChannelClosed
+    }
+}
+
+/// Error returned by batched sends such as
+/// [`ConnectionHandle::send_many_text`](crate::handle::ConnectionHandle::send_many_text).
+///
+/// The batch stops at the first failure. [`sent`](Self::sent) tells how many
+/// frames were written to the connection before that, so the caller can
+/// resume from there. A batch rejected by a full send queue reports `0`.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::wynd::{Wynd, Standalone};
+///
+/// let mut wynd: Wynd<Standalone> = Wynd::new();
+///
+/// wynd.on_connection(|conn| async move {
+///     conn.on_open(|handle| async move {
+///         if let Err(e) = handle.send_many_text(&["welcome", "3 users online"]).await {
+///             eprintln!("sent {} frames before failing: {}", e.sent(), e);
+///         }
+///     })
+///     .await;
+/// });
+/// ```
+#[derive(Debug)]
+pub struct BatchSendError {
+    /// Number of frames written before the failure.
+    sent: usize,
+    /// The error that stopped the batch.
+    source: Box<dyn std::error::Error + Send + Sync + 'static>,
+}
+
+impl BatchSendError {
+    /// Creates a batch error after `sent` frames were written.
+    pub(crate) fn new(
+        sent: usize,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    ) -> Self {
+        Self {
+            sent,
+            source: source.into(),
+        }
+    }
+
+    /// Returns how many frames of the batch were written before the failure.
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+}
+
+impl Display for BatchSendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "batch send failed after {} frames: {}",
+            self.sent, self.source
+        )
+    }
+}
+
+impl std::error::Error for BatchSendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Error returned by fallible message handlers such as
+/// [`Connection::on_text_try`](crate::conn::Connection::on_text_try).
+///
+/// Carries a message and a numeric code. With the `json` feature and
+/// `Wynd::set_auto_error_reply` enabled, the error is sent back to the client
+/// as `{ "error": "...", "code": ... }`; otherwise it is logged.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::types::HandlerError;
+///
+/// let err = HandlerError::new("unknown method").with_code(404);
+///
+/// assert_eq!(err.message(), "unknown method");
+/// assert_eq!(err.code(), 404);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandlerError {
+    message: String,
+    code: u16,
+}
+
+impl HandlerError {
+    /// Code used when none is given.
+    pub const DEFAULT_CODE: u16 = 500;
+
+    /// Creates a handler error with [`HandlerError::DEFAULT_CODE`].
+    pub fn new<S>(message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            message: message.into(),
+            code: Self::DEFAULT_CODE,
+        }
+    }
+
+    /// Sets the code reported to the client.
+    pub fn with_code(mut self, code: u16) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the error code.
+    pub fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// Serializes the error as `{ "error": "...", "code": ... }`.
+    #[cfg(feature = "json")]
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::json!({ "error": self.message, "code": self.code }).to_string()
+    }
+}
+
+impl Display for HandlerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for HandlerError {}
+
+impl From<String> for HandlerError {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<&str> for HandlerError {
+    fn from(message: &str) -> Self {
+        Self::new(message)
     }
 }
 
-impl std::error::Error for WyndError {}
+/// HTTP response refusing a WebSocket upgrade, returned from a
+/// [`Wynd::on_upgrade`](crate::wynd::Wynd::on_upgrade) hook.
+///
+/// ## Example
+///
+/// ```rust
+/// use wynd::types::UpgradeRejection;
+///
+/// let rejection = UpgradeRejection::new(401, "missing token");
+///
+/// assert_eq!(rejection.status(), 401);
+/// assert_eq!(rejection.body(), "missing token");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeRejection {
+    status: u16,
+    body: String,
+}
+
+impl UpgradeRejection {
+    /// Creates a rejection answered with `status` and a plain text `body`.
+    pub fn new<S>(status: u16, body: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+
+    /// Returns the HTTP status code.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the response body.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Formats the rejection as a complete HTTP/1.1 response.
+    pub(crate) fn to_http(&self) -> String {
+        let reason = tokio_tungstenite::tungstenite::http::StatusCode::from_u16(self.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("");
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.body.len(),
+            self.body
+        )
+    }
+}
+
+impl Display for UpgradeRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "upgrade rejected with {}: {}", self.status, self.body)
+    }
+}
+
+/// Read-only snapshot of a single connection, returned by
+/// [`Wynd::connection_info`](crate::wynd::Wynd::connection_info).
+///
+/// The snapshot is taken when it is requested and does not change
+/// afterwards; request a new one to see later joins, leaves or tags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionInfo {
+    /// The connection's id.
+    pub id: u64,
+    /// The remote address of the client.
+    pub addr: SocketAddr,
+    /// The connection state when the snapshot was taken.
+    pub state: ConnState,
+    /// When the underlying TCP connection was accepted.
+    pub connected_at: Instant,
+    /// The rooms the connection is in, sorted by name.
+    pub rooms: Vec<String>,
+    /// The tags on the connection, sorted.
+    pub tags: Vec<String>,
+}
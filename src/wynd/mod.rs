@@ -55,27 +55,53 @@ use http_body_util::Full;
 use hyper_tungstenite::hyper;
 #[cfg(feature = "with-ripress")]
 use hyper_util::rt::TokioIo;
-use std::sync::Mutex;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc::Receiver;
+use tokio::sync::{watch, Semaphore, SemaphorePermit};
 
+use futures::StreamExt;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::future::Future;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, Interest};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::time::timeout;
-use tokio_tungstenite::accept_async;
+use tokio_tungstenite::accept_hdr_async_with_config;
+use tokio_tungstenite::tungstenite::handshake::machine::TryParse;
+use tokio_tungstenite::tungstenite::handshake::server::{
+    ErrorResponse, Request as HandshakeRequest, Response as HandshakeResponse,
+};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 
-use crate::conn::{ConnState, Connection};
-use crate::handle::{Broadcaster, ConnectionHandle};
+use crate::affinity::{ConnectionAffinity, WorkerPool};
+use crate::conn::{ConnState, Connection, Keepalive};
+use crate::group::GroupId;
+use crate::handle::{
+    check_close_frame, yield_periodically, BroadcastCoalescer, BroadcastPayload, Broadcaster,
+    ConnectionHandle, DEFAULT_BROADCAST_YIELD_INTERVAL, DEFAULT_ROOM_RESPONSE_CAPACITY,
+};
+use crate::metrics::{Counters, Histogram, HistogramSnapshot, WyndMetrics, SETUP_TIME_BUCKETS_MS};
 use crate::middleware::{self, Middleware, Next};
-use crate::room::{ClientInfo, Room, RoomEvents};
+use crate::room::{
+    ClientInfo, DeliveryReport, FloodWatch, RateLimit, Room, RoomEvents, RoomFloodHandler,
+    RoomSnapshot,
+};
+use crate::types::CloseEvent;
+use crate::types::ConnectionInfo;
+#[cfg(feature = "json")]
+use crate::types::HandlerError;
+use crate::types::RoomError;
+use crate::types::UpgradeRejection;
 use crate::types::WyndError;
-use crate::ClientRegistry;
+use crate::IpNet;
+use crate::{ClientRegistry, GroupRegistry, TagRegistry, UserRegistry};
 use std::fmt::Debug;
 
 #[cfg(feature = "with-ripress")]
@@ -86,11 +112,79 @@ use crate::context::Context;
 pub(crate) type ConnectionId = u64;
 pub(crate) type ConnectionIdCounter = AtomicU64;
 
+/// Maximum number of room actions [`Wynd::for_each_room`] runs at once.
+pub const FOR_EACH_ROOM_CONCURRENCY: usize = 8;
+
+/// How long [`Wynd::listen`] waits for clients to disconnect after a
+/// shutdown is requested, unless set with [`Wynd::set_shutdown_timeout`].
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a client has to send its upgrade request and complete the
+/// WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long an evicted connection has to finish its closing handshake
+/// before its message loop is stopped.
+const EVICTION_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest wait for more of a partial upgrade request before peeking again.
+///
+/// Waits normally end when the socket becomes readable; the bound covers a
+/// wakeup lost between a peek and clearing its readiness.
+const UPGRADE_PEEK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Largest upgrade request header block accepted by the standalone server,
+/// unless set with [`Wynd::set_max_header_size`].
+pub const DEFAULT_MAX_HEADER_SIZE: usize = 16 * 1024;
+
 /// Type alias for boxed futures used throughout the library.
 ///
 /// This ensures all futures are `Send` and can be stored in async contexts.
 pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
 
+/// Type alias for the server-level connection handler.
+///
+/// The handler receives every new `Connection` once the middleware chain has run.
+pub(crate) type ConnectionHandler<T> =
+    Box<dyn Fn(Arc<Connection<T>>) -> BoxFuture<()> + Send + Sync + 'static>;
+
+/// Hook deciding from the handshake request headers whether to upgrade.
+pub(crate) type UpgradeHook = Box<
+    dyn Fn(HashMap<String, String>) -> BoxFuture<Result<(), UpgradeRejection>>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+/// A connection and its handle, as kept in the client registry.
+type ClientEntry<T> = (Arc<Connection<T>>, Arc<ConnectionHandle<T>>);
+
+/// Callback receiving periodic metrics snapshots.
+pub(crate) type MetricsCallback = Arc<dyn Fn(WyndMetrics) + Send + Sync>;
+
+/// HTTP response sent through ripress.
+#[cfg(feature = "with-ripress")]
+pub(crate) type HttpResponse = hyper::Response<Full<hyper_tungstenite::hyper::body::Bytes>>;
+
+/// Future returned by the ripress handler for each request.
+#[cfg(feature = "with-ripress")]
+pub(crate) type HttpResponseFuture =
+    Pin<Box<dyn Future<Output = hyper::Result<HttpResponse>> + Send>>;
+
+/// Handler for plain HTTP requests that may answer before the WebSocket upgrade.
+#[cfg(feature = "with-ripress")]
+pub(crate) type RequestHandler =
+    Box<dyn Fn(Context) -> BoxFuture<Option<HttpResponse>> + Send + Sync + 'static>;
+
+/// Strategy for choosing which connection to evict when the server is full.
+///
+/// Used with [`Wynd::set_memory_pressure_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the connection that has gone longest without sending a message.
+    OldestIdle,
+}
+
 /// Main WebSocket server instance.
 ///
 /// `Wynd` is the primary type for creating and managing WebSocket servers.
@@ -132,36 +226,118 @@ pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>
 ///     });
 /// }
 /// ```
-
 pub struct Wynd<T>
 where
     T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
 {
-    /// Handler for new connections.
+    /// Handlers for new connections, in registration order.
     ///
-    /// This handler is called whenever a new WebSocket connection is established.
-    /// It receives a `Connection` instance that can be used to set up event handlers.
-    pub(crate) connection_handler:
-        Option<Box<dyn Fn(Arc<Connection<T>>) -> BoxFuture<()> + Send + Sync + 'static>>,
+    /// These handlers are called whenever a new WebSocket connection is established.
+    /// Each receives a `Connection` instance that can be used to set up event handlers.
+    pub(crate) connection_handlers: Vec<ConnectionHandler<T>>,
 
     #[cfg(feature = "with-ripress")]
-    pub(crate) request_handler: Option<
-        Box<
-            dyn Fn(
-                    Context,
-                ) -> BoxFuture<
-                    Option<hyper::Response<Full<hyper_tungstenite::hyper::body::Bytes>>>,
-                > + Send
-                + Sync
-                + 'static,
-        >,
-    >,
+    pub(crate) request_handler: Option<RequestHandler>,
 
     /// The address the server is listening on.
     pub(crate) addr: SocketAddr,
 
     room_event_channel_capacity: usize,
 
+    /// Whether to echo the first subprotocol offered by the client.
+    pub(crate) echo_subprotocol: bool,
+
+    /// Optional bound on concurrently running connection handlers.
+    handler_permits: Option<Arc<Semaphore>>,
+
+    /// Maximum number of connections and how to make room for new ones.
+    memory_pressure: Option<(usize, EvictionPolicy)>,
+
+    /// Shared coalescer for connection broadcasts, if a window is configured.
+    broadcast_coalescer: Option<Arc<BroadcastCoalescer>>,
+
+    /// Keepalive pings sent to every connection, if enabled.
+    keepalive: Option<Keepalive>,
+
+    /// Networks peers must belong to, if an allowlist is configured.
+    ip_allowlist: Option<Vec<IpNet>>,
+
+    /// Networks whose peers are refused.
+    ip_blocklist: Vec<IpNet>,
+
+    /// Whether handler errors and middleware rejections are sent to the client as JSON.
+    #[cfg(feature = "json")]
+    auto_error_reply: bool,
+
+    /// Set once the server starts draining; new connections are refused.
+    draining: AtomicBool,
+
+    /// Lifetime limit on bytes received per connection, if configured.
+    max_total_bytes: Option<u64>,
+
+    /// Longest close reason, in characters, kept from client close frames.
+    max_close_reason_len: usize,
+
+    /// Whether connections reply to pings before running `on_ping`.
+    auto_pong: bool,
+
+    /// Whether room messages from non-members fail with `RoomError::NotAMember`.
+    require_room_membership: bool,
+
+    /// Capacity of each connection's room response channel.
+    room_response_capacity: usize,
+
+    /// Capacity of each connection's outbound send queue, if enabled.
+    send_queue_capacity: Option<usize>,
+
+    /// Dedicated workers that message loops are pinned to, if enabled.
+    workers: Option<Arc<WorkerPool>>,
+
+    /// Largest incoming message accepted, in bytes, if limited by the server.
+    max_message_size: Option<usize>,
+
+    /// Largest incoming frame accepted, in bytes, if limited by the server.
+    max_frame_size: Option<usize>,
+    /// Largest upgrade request header block accepted, in bytes.
+    max_header_size: usize,
+
+    /// `Sec-WebSocket-Version` clients must send, if one is required.
+    required_ws_version: Option<u8>,
+
+    /// Sends between yields to the scheduler in broadcast and room fan-out.
+    broadcast_yield_interval: usize,
+
+    /// Most rooms that may exist at once, if limited.
+    max_rooms: Option<usize>,
+
+    /// Most messages a room may receive within a window before it counts as
+    /// flooded, if flood detection is enabled.
+    room_flood_limit: Option<(usize, Duration)>,
+
+    /// Handler run with a room's name when the room is flooded.
+    room_flood_handler: Option<RoomFloodHandler>,
+
+    /// Path answered with a plain `200 OK` instead of a WebSocket upgrade.
+    health_path: Option<String>,
+
+    /// Hook run on the handshake request before upgrading, if any.
+    upgrade_hook: Option<UpgradeHook>,
+
+    /// Time each connection took from accept until it opened.
+    setup_times: Arc<Histogram>,
+
+    /// Server-wide traffic counters.
+    counters: Arc<Counters>,
+
+    /// Callback receiving a metrics snapshot every interval, if registered.
+    metrics_reporter: Option<(Duration, MetricsCallback)>,
+
+    /// Set to `true` once a shutdown is requested through a [`ShutdownHandle`].
+    shutdown: Arc<watch::Sender<bool>>,
+
+    /// How long a graceful shutdown waits for clients to disconnect.
+    shutdown_timeout: Duration,
+
     /// Handler for server-level errors.
     ///
     /// This handler is called when server-level errors occur, such as
@@ -173,7 +349,10 @@ where
     ///
     /// This handler is called when the server is shutting down, either
     /// due to an error or when the `Wynd` instance is dropped.
-    pub(crate) close_handler: Option<Box<dyn Fn() -> () + Send + Sync + 'static>>,
+    pub(crate) close_handler: Option<Box<dyn Fn() + Send + Sync + 'static>>,
+
+    /// Whether the close handler has run, so it runs at most once.
+    close_handler_ran: AtomicBool,
 
     /// Atomic counter for generating unique connection IDs.
     ///
@@ -181,6 +360,9 @@ where
     /// debugging, and connection management.
     pub(crate) next_connection_id: ConnectionIdCounter,
 
+    /// Custom connection ID generator that replaces the counter, if set.
+    id_generator: Option<Arc<dyn Fn() -> ConnectionId + Send + Sync>>,
+
     /// Registry of active WebSocket connections.
     ///
     /// Each entry contains an Arc-wrapped Connection and its corresponding ConnectionHandle.
@@ -194,10 +376,29 @@ where
     /// Protected by a tokio Mutex for thread-safe access.
     pub(crate) rooms: Arc<tokio::sync::Mutex<Vec<Room<T>>>>,
 
-    /// Channel for receiving room events from all connections.
-    /// This is used by the room event processor task.
+    /// Registry of server-side connection groups.
+    ///
+    /// Maps each group to the ids of its member connections. Members are
+    /// removed automatically when their connection closes.
+    pub(crate) groups: GroupRegistry,
+
+    /// Index of user ids to the connections belonging to each user.
+    ///
+    /// Populated by [`ConnectionHandle::set_user_id`]; connections are
+    /// removed automatically when they close.
+    pub(crate) users: UserRegistry,
+
+    /// Index of tags to the connections carrying each tag.
+    ///
+    /// Populated by [`ConnectionHandle::add_tag`]; connections are removed
+    /// automatically when they close.
+    pub(crate) tags: TagRegistry,
+
+    /// Channel for sending room events to the room processor task.
+    ///
+    /// Until the server starts this is a closed channel, so room operations
+    /// fail with [`RoomError::ChannelClosed`] instead of waiting forever.
     room_sender: Arc<tokio::sync::mpsc::Sender<RoomEvents<T>>>,
-    _room_receiver: Arc<Mutex<tokio::sync::mpsc::Receiver<RoomEvents<T>>>>,
 
     pub(crate) middlewares: Vec<Middleware<T>>,
 }
@@ -214,7 +415,6 @@ where
 /// The concrete IO type when running Wynd as a standalone TCP WebSocket server.
 ///
 /// This alias selects `tokio::net::TcpStream` for the server transport.
-
 pub type Standalone = TcpStream;
 
 /// The concrete IO type when integrating Wynd with `ripress`.
@@ -222,7 +422,6 @@ pub type Standalone = TcpStream;
 /// This alias selects `TokioIo<hyper::upgrade::Upgraded>` for the server transport when
 /// the `with-ripress` feature is enabled.
 /// `TokioIo` is required to make `Upgraded` implement `AsyncRead` and `AsyncWrite`.
-
 #[cfg(feature = "with-ripress")]
 pub type WithRipress = TokioIo<hyper::upgrade::Upgraded>;
 
@@ -232,14 +431,19 @@ where
 {
     /// Ensures proper cleanup when the server is dropped.
     ///
-    /// Calls the close handler if one is registered, ensuring graceful shutdown.
+    /// Calls the close handler if one is registered and it has not already
+    /// run during a graceful shutdown.
     fn drop(&mut self) {
-        let close_handler = match self.close_handler.as_ref() {
-            None => return,
-            Some(handler) => handler,
-        };
+        self.run_close_handler();
+    }
+}
 
-        close_handler();
+impl<T> Default for Wynd<T>
+where
+    T: AsyncRead + Debug + AsyncWrite + Send + 'static + Unpin,
+{
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -250,7 +454,8 @@ where
     /// Creates a new WebSocket server instance.
     ///
     /// Returns a new `Wynd` instance with default settings. The server
-    /// will listen on localhost (127.0.0.1) when started.
+    /// will listen on every interface (`0.0.0.0`) when started with
+    /// [`listen`](Wynd::listen).
     ///
     /// ## Example
     ///
@@ -260,20 +465,54 @@ where
     /// let mut wynd: Wynd<Standalone> = Wynd::new();
     /// ```
     pub fn new() -> Self {
-        let (room_sender, room_receiver) = tokio::sync::mpsc::channel(100);
-
         Self {
             middlewares: Vec::new(),
-            connection_handler: None,
+            connection_handlers: Vec::new(),
             error_handler: None,
             close_handler: None,
+            close_handler_ran: AtomicBool::new(false),
             next_connection_id: ConnectionIdCounter::new(0),
+            id_generator: None,
             clients: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             addr: SocketAddr::from(([0, 0, 0, 0], 8080)),
             rooms: Arc::new(tokio::sync::Mutex::new(Vec::new())),
-            room_sender: Arc::new(room_sender),
+            groups: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            users: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            tags: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            room_sender: closed_room_channel(),
             room_event_channel_capacity: 100,
-            _room_receiver: Arc::new(Mutex::new(room_receiver)),
+            echo_subprotocol: false,
+            handler_permits: None,
+            memory_pressure: None,
+            broadcast_coalescer: None,
+            keepalive: None,
+            ip_allowlist: None,
+            ip_blocklist: Vec::new(),
+            #[cfg(feature = "json")]
+            auto_error_reply: false,
+            draining: AtomicBool::new(false),
+            max_total_bytes: None,
+            max_close_reason_len: CloseEvent::MAX_REASON_LEN,
+            auto_pong: true,
+            require_room_membership: false,
+            room_response_capacity: DEFAULT_ROOM_RESPONSE_CAPACITY,
+            send_queue_capacity: None,
+            workers: None,
+            max_message_size: None,
+            max_frame_size: None,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+            required_ws_version: None,
+            broadcast_yield_interval: DEFAULT_BROADCAST_YIELD_INTERVAL,
+            max_rooms: None,
+            room_flood_limit: None,
+            room_flood_handler: None,
+            health_path: None,
+            upgrade_hook: None,
+            setup_times: Arc::new(Histogram::new(&SETUP_TIME_BUCKETS_MS)),
+            counters: Arc::new(Counters::default()),
+            metrics_reporter: None,
+            shutdown: Arc::new(watch::channel(false).0),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
             #[cfg(feature = "with-ripress")]
             request_handler: None,
         }
@@ -293,66 +532,66 @@ where
     ///
     /// # Note
     ///
-    /// Changing the channel replaces both the sender and receiver, so any outstanding
-    /// events in the old channel will be dropped.
+    /// The channel is created when the server starts, so call this before
+    /// [`listen`](Self::listen).
     pub fn set_room_event_channel_capacity(&mut self, capacity: usize) {
         self.room_event_channel_capacity = capacity;
-
-        let (room_sender, room_receiver) = tokio::sync::mpsc::channel(capacity);
-        self.room_sender = Arc::new(room_sender);
-        self._room_receiver = Arc::new(Mutex::new(room_receiver));
     }
 
-    /// Registers a middleware function to be executed for each new connection.
+    /// Replaces the connection ID counter with a custom generator.
     ///
-    /// Middlewares are executed in the order they are registered, before the connection
-    /// handler is called. Each middleware receives the connection, handle, and a `Next`
-    /// object that can be used to call the next middleware in the chain.
+    /// By default IDs come from an atomic counter starting at `0`. Injecting
+    /// a generator makes IDs deterministic, so tests and snapshots can assert
+    /// on specific connection IDs. The generator must not return an ID that is
+    /// still in use by a live connection.
     ///
     /// ## Parameters
     ///
-    /// - `middleware`: An async closure that takes a connection, handle, and Next, and returns
-    ///   a result indicating whether to proceed or reject the connection
+    /// - `generator`: Called once per accepted connection to produce its ID
     ///
     /// ## Example
     ///
     /// ```rust
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
     /// use wynd::wynd::{Wynd, Standalone};
-    /// use wynd::Next;
     ///
     /// let mut wynd: Wynd<Standalone> = Wynd::new();
     ///
-    /// wynd.use_middleware(|conn, handle, next: Next<Standalone>| async move {
-    ///     // Do some validation or modification
-    ///     println!("Middleware executing for connection {}", conn.id());
-    ///     // Call the next middleware
-    ///     next.call(conn, handle).await
-    /// });
+    /// // Hand out 100, 101, 102, ...
+    /// let next = Arc::new(AtomicU64::new(100));
+    /// wynd.set_id_generator(Arc::new(move || next.fetch_add(1, Ordering::SeqCst)));
     /// ```
-    pub fn use_middleware<F, Fut>(&mut self, middleware: F) -> &mut Self
-    where
-        F: Fn(Arc<Connection<T>>, Arc<ConnectionHandle<T>>, Next<T>) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Result<(Arc<Connection<T>>, Arc<ConnectionHandle<T>>), String>>
-            + Send
-            + 'static,
-    {
-        let middleware = Middleware {
-            handler: Arc::new(move |conn, handle, next| Box::pin(middleware(conn, handle, next))),
-        };
+    pub fn set_id_generator(&mut self, generator: Arc<dyn Fn() -> u64 + Send + Sync>) {
+        self.id_generator = Some(generator);
+    }
 
-        self.middlewares.push(middleware);
-        self
+    /// Returns the ID for a new connection.
+    fn assign_connection_id(&self) -> ConnectionId {
+        match self.id_generator {
+            Some(ref generator) => generator(),
+            None => self.next_connection_id.fetch_add(1, Ordering::Relaxed),
+        }
     }
 
-    /// Registers a handler for new connections.
+    /// Limits how many handlers may run at the same time, server-wide.
     ///
-    /// This method sets up a handler that will be called whenever a new
-    /// WebSocket connection is established. The handler receives a `Connection`
-    /// instance that can be used to set up event handlers for that connection.
+    /// Connection handlers never block the accept loop or each other, and
+    /// every connection runs its message handlers independently, so slow or
+    /// CPU-bound handler work (e.g. database lookups) under load can still
+    /// exhaust resources. With a limit set, `on_connection` handlers and
+    /// `on_text`/`on_binary` handlers (including their `_try` and typed
+    /// variants) share one pool of slots and wait for a free one before
+    /// running.
+    ///
+    /// Per-connection ordering is unaffected: a connection still handles one
+    /// message at a time, in arrival order, and stops reading from its socket
+    /// while waiting for a slot. Handlers that wait on other connections'
+    /// handlers can deadlock with a low limit.
     ///
     /// ## Parameters
     ///
-    /// - `handler`: An async closure that takes a `Connection` and returns a future
+    /// - `limit`: The maximum number of handlers running at once
     ///
     /// ## Example
     ///
@@ -361,37 +600,62 @@ where
     ///
     /// let mut wynd: Wynd<Standalone> = Wynd::new();
     ///
-    /// wynd.on_connection(|conn| async move {
-    ///     println!("New connection: {}", conn.id());
-    ///     
-    ///     // Set up connection-specific handlers
-    ///     conn.on_open(|handle| async move {
-    ///         println!("Connection {} opened", handle.id());
-    ///     })
-    ///     .await;
+    /// wynd.set_max_concurrent_handlers(64);
+    /// ```
+    pub fn set_max_concurrent_handlers(&mut self, limit: usize) {
+        self.handler_permits = Some(Arc::new(Semaphore::new(limit)));
+    }
+
+    /// Pings every connection periodically and closes the ones that stop answering.
     ///
-    ///     conn.on_text(|msg, handle| async move {
-    ///         println!("Received: {}", msg.data);
+    /// The server sends a ping every `interval`. If the client does not answer
+    /// with a pong within `timeout`, the connection is closed and its `on_close`
+    /// handler receives code `1006` with reason `"ping timeout"` and
+    /// [`CloseCause::PingTimeout`](crate::types::CloseCause::PingTimeout), so
+    /// apps can tell a dead network apart from a client that said goodbye.
+    ///
+    /// Server-initiated pings are off unless this is called; pings sent by
+    /// clients are answered either way (see [`Wynd::disable_auto_pong`]).
+    ///
+    /// ## Parameters
+    ///
+    /// - `interval`: Time between pings
+    /// - `timeout`: How long to wait for a pong after each ping
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::types::CloseCause;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_keepalive(Duration::from_secs(30), Duration::from_secs(10));
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_close(|event| async move {
+    ///         if event.cause() == CloseCause::PingTimeout {
+    ///             println!("Connection lost");
+    ///         }
     ///     });
     /// });
     /// ```
-    pub fn on_connection<F, Fut>(&mut self, handler: F)
-    where
-        F: Fn(Arc<Connection<T>>) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = ()> + Send + 'static,
-    {
-        self.connection_handler = Some(Box::new(move |conn| Box::pin(handler(conn))));
+    pub fn set_keepalive(&mut self, interval: Duration, timeout: Duration) {
+        self.keepalive = Some(Keepalive { interval, timeout });
     }
 
-    /// Registers a handler for server-level errors.
-    ///
-    /// This method sets up a handler that will be called when server-level
-    /// errors occur, such as connection acceptance failures or WebSocket
-    /// handshake errors.
+    /// Stops connections from answering client pings on their own.
     ///
-    /// ## Parameters
+    /// By default every ping from a client is answered with a pong carrying
+    /// the same payload once `on_ping` has run. After this call the server only
+    /// reports pings to `on_ping`, which can reply with
+    /// [`ConnectionHandle::send_pong`](crate::handle::ConnectionHandle::send_pong).
     ///
-    /// - `handler`: An async closure that takes a `WyndError` and returns a future
+    /// The WebSocket protocol layer still queues a pong for every ping, as
+    /// RFC 6455 requires, and sends it unless a pong from the handler
+    /// replaces it first. Clients therefore keep getting an answer; this
+    /// setting decides who writes it.
     ///
     /// ## Example
     ///
@@ -399,569 +663,3360 @@ where
     /// use wynd::wynd::{Wynd, Standalone};
     ///
     /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.disable_auto_pong();
     ///
-    /// wynd.on_error(|err| async move {
-    ///     eprintln!("Server error: {}", err);
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_ping(|payload, handle| async move {
+    ///         println!("ping from {}: {:?}", handle.id(), payload);
+    ///     });
     /// });
     /// ```
-    pub fn on_error<F, Fut>(&mut self, handler: F)
-    where
-        F: Fn(WyndError) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = ()> + Send + 'static,
-    {
-        self.error_handler = Some(Box::new(move |err| Box::pin(handler(err))));
+    pub fn disable_auto_pong(&mut self) {
+        self.auto_pong = false;
     }
 
-    /// Registers a handler for server shutdown.
+    /// Makes room messages fail for senders that are not room members.
     ///
-    /// This method sets up a handler that will be called when the server
-    /// is shutting down, either due to an error or when the `Wynd` instance
-    /// is dropped.
+    /// The room processor never delivers a message from a connection that
+    /// has not joined the target room, but by default the sender is not told.
+    /// With membership required, `text`, `text_sequence`, `emit_text`,
+    /// `binary` and `emit_binary` on
+    /// [`ConnectionHandle::to`](crate::handle::ConnectionHandle::to) return
+    /// [`RoomError::NotAMember`](crate::types::RoomError::NotAMember)
+    /// instead, so a chat server can reject clients posting to rooms they
+    /// have not joined. Checking costs a round trip to the room processor
+    /// per message.
     ///
     /// ## Parameters
     ///
-    /// - `handler`: A closure that will be called during shutdown
+    /// - `required`: Whether senders must be members of the target room
     ///
     /// ## Example
     ///
     /// ```rust
+    /// use wynd::types::RoomError;
     /// use wynd::wynd::{Wynd, Standalone};
     ///
     /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.require_membership_to_send(true);
     ///
-    /// wynd.on_close(|| {
-    ///     println!("Server shutting down");
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text(|msg, handle| async move {
+    ///         if let Err(RoomError::NotAMember(room)) = handle.to("lobby").text(msg.data).await {
+    ///             let _ = handle.send_text(&format!("join {} first", room)).await;
+    ///         }
+    ///     });
     /// });
     /// ```
-    pub fn on_close<F>(&mut self, handler: F)
-    where
-        F: Fn() -> () + Send + Sync + 'static,
-    {
-        self.close_handler = Some(Box::new(move || handler()));
+    pub fn require_membership_to_send(&mut self, required: bool) {
+        self.require_room_membership = required;
     }
 
-    /// Starts the WebSocket server and begins listening for connections.
+    /// Only accepts connections from peers inside the given networks.
     ///
-    /// This method starts the server on the specified port and begins accepting
-    /// WebSocket connections. The server will run indefinitely until an error
-    /// occurs or the process is terminated.
+    /// The peer address is checked as soon as a connection is accepted, before
+    /// the WebSocket handshake, and disallowed connections are dropped right
+    /// away. The blocklist takes precedence over the allowlist. Applies to the
+    /// standalone server, where the peer address is known at accept time.
     ///
     /// ## Parameters
     ///
-    /// - `port`: The port number to listen on
-    /// - `on_listening`: A closure that will be called when the server starts listening
+    /// - `networks`: The CIDR networks that may connect
     ///
-    /// ## Returns
+    /// ## Example
     ///
-    /// Returns `Ok(())` if the server runs successfully, or an error if the
-    /// server fails to start or encounters a fatal error.
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    /// use wynd::IpNet;
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_ip_allowlist(vec![
+    ///     "10.0.0.0/8".parse::<IpNet>().unwrap(),
+    ///     "::1/128".parse::<IpNet>().unwrap(),
+    /// ]);
+    /// ```
+    pub fn set_ip_allowlist(&mut self, networks: Vec<IpNet>) {
+        self.ip_allowlist = Some(networks);
+    }
+
+    /// Refuses connections from peers inside the given networks.
+    ///
+    /// Like [`Wynd::set_ip_allowlist`], the check happens before the WebSocket
+    /// handshake.
+    ///
+    /// ## Parameters
+    ///
+    /// - `networks`: The CIDR networks that may not connect
     ///
     /// ## Example
     ///
     /// ```rust
     /// use wynd::wynd::{Wynd, Standalone};
+    /// use wynd::IpNet;
     ///
-    /// #[tokio::main]
-    /// async fn main() {
-    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
-    ///     
-    ///     // Set up handlers...
-    ///     
-    ///     wynd.listen(8080, || {
-    ///         println!("Server listening on port 8080");
-    ///     });
-    /// }
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_ip_blocklist(vec!["203.0.113.0/24".parse::<IpNet>().unwrap()]);
     /// ```
-    // listen is only meaningful when T = TcpStream; provided in a specialized impl below
+    pub fn set_ip_blocklist(&mut self, networks: Vec<IpNet>) {
+        self.ip_blocklist = networks;
+    }
 
-    /// Executes the middleware chain for a connection.
+    /// Closes connections that have received more than `n` bytes in total.
     ///
-    /// This method builds a chain of middleware handlers where each middleware
-    /// can call `next.call()` to proceed to the next middleware in the chain.
+    /// Per-message limits do not stop a client that drips many small messages
+    /// over a long-lived connection. With this limit, every inbound frame is
+    /// added to a per-connection counter and the connection is closed with
+    /// code `1009` once the counter exceeds `n`. The frame that crosses the
+    /// limit is not passed to any handler.
     ///
     /// ## Parameters
     ///
-    /// - `conn`: The connection to process through the middleware chain
-    /// - `handle`: The connection handle to process through the middleware chain
+    /// - `n`: The maximum number of bytes a connection may receive over its lifetime
     ///
-    /// ## Returns
+    /// ## Example
     ///
-    /// Returns the final connection and handle after all middlewares have processed,
-    /// or an error if any middleware fails.
-    async fn execute_middleware_chain(
-        &self,
-        conn: Arc<Connection<T>>,
-        handle: Arc<ConnectionHandle<T>>,
-    ) -> Result<(Arc<Connection<T>>, Arc<ConnectionHandle<T>>), String> {
-        if self.middlewares.is_empty() {
-            return Ok((conn, handle));
-        }
-
-        let mut next = middleware::Next::finalize();
-
-        let middlewares_arc = Arc::new(self.middlewares.clone());
-        let middleware_count = middlewares_arc.len();
-
-        for i in (0..middleware_count).rev() {
-            let current_next = next.clone();
-            let middlewares_clone = Arc::clone(&middlewares_arc);
-            let middleware_index = i;
-            next = middleware::Next::new(Arc::new(move |conn, handle| {
-                let current_next = current_next.clone();
-                let middlewares_clone = Arc::clone(&middlewares_clone);
-                let middleware_index = middleware_index;
-                Box::pin(async move {
-                    if middleware_index < middlewares_clone.len() {
-                        let middleware = &middlewares_clone[middleware_index];
-                        middleware.handle(conn, handle, current_next).await
-                    } else {
-                        current_next.call(conn, handle).await
-                    }
-                })
-            }));
-        }
-
-        next.call(conn, handle).await
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// // Close any connection that sends more than 64 MiB in total.
+    /// wynd.set_max_total_bytes_per_connection(64 * 1024 * 1024);
+    /// ```
+    pub fn set_max_total_bytes_per_connection(&mut self, n: u64) {
+        self.max_total_bytes = Some(n);
     }
 
-    /// This method performs the WebSocket handshake and creates a `Connection`
-    /// instance for the new connection. It then calls the connection handler
-    /// if one is registered.
+    /// Caps the length of close reasons received from clients.
+    ///
+    /// Close reasons are client-controlled, so they are always stripped of
+    /// control characters before being exposed as [`CloseEvent::reason`].
+    /// They are also truncated to `n` characters; the default is 123, the
+    /// most a close frame can carry.
     ///
     /// ## Parameters
     ///
-    /// - `stream`: The TCP stream for the connection
-    /// - `addr`: The remote address of the connection
+    /// - `n`: The maximum number of characters kept from a close reason
     ///
-    /// ## Returns
+    /// ## Example
     ///
-    /// Returns `Ok(())` if the connection is handled successfully, or an error
-    /// if the WebSocket handshake fails or other errors occur.
-    async fn handle_connection(
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// // Keep only a short prefix of whatever reason clients send.
+    /// wynd.set_max_close_reason_len(32);
+    /// ```
+    pub fn set_max_close_reason_len(&mut self, n: usize) {
+        self.max_close_reason_len = n;
+    }
+
+    /// Sets how many room query responses each connection can have queued.
+    ///
+    /// Queries such as [`ConnectionHandle::joined_rooms`] are answered by the
+    /// room processor through a per-connection channel. When that channel is
+    /// full, the processor hands the response to a background task instead of
+    /// waiting, so a slow reader never stalls other rooms. The default is
+    /// [`DEFAULT_ROOM_RESPONSE_CAPACITY`]; values below 1 are treated as 1.
+    ///
+    /// ## Parameters
+    ///
+    /// - `capacity`: The number of responses buffered per connection
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_room_response_capacity(64);
+    /// ```
+    pub fn set_room_response_capacity(&mut self, capacity: usize) {
+        self.room_response_capacity = capacity.max(1);
+    }
+
+    /// Gives every connection a bounded outbound queue of `capacity` frames.
+    ///
+    /// By default each send waits for the connection's writer, so a slow
+    /// client holds up every task sending to it, including broadcast loops.
+    /// With a queue, text and binary sends hand the frame to a per-connection
+    /// writer task and return at once. When the queue is full they fail with
+    /// [`WyndError::Backpressure`] instead of waiting. Broadcasts skip such
+    /// clients and log the failure.
+    ///
+    /// Control frames are written directly and may overtake queued frames.
+    /// Closing a connection first waits for its queue to be written, so a
+    /// message sent just before the close still arrives. Values below 1 are
+    /// treated as 1.
+    ///
+    /// ## Parameters
+    ///
+    /// - `capacity`: The number of frames queued per connection
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.with_send_queue_capacity(256);
+    /// ```
+    pub fn with_send_queue_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.send_queue_capacity = Some(capacity.max(1));
+        self
+    }
+
+    /// Limits the size of a complete incoming message to `bytes`.
+    ///
+    /// The limit is enforced while the message is read, before it is
+    /// buffered, so an oversized message never reaches `on_text` or
+    /// `on_binary`. A client that exceeds it is closed with code `1009`
+    /// (message too big) and `on_close` fires with
+    /// [`CloseCause::MessageTooLarge`](crate::types::CloseCause::MessageTooLarge).
+    /// Without a limit set here, tungstenite's default of 64 MiB applies.
+    ///
+    /// ## Parameters
+    ///
+    /// - `bytes`: The largest message accepted, summed over its fragments
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.with_max_message_size(1024 * 1024)
+    ///     .with_max_frame_size(256 * 1024);
+    /// ```
+    pub fn with_max_message_size(&mut self, bytes: usize) -> &mut Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
+
+    /// Limits the payload of a single incoming frame to `bytes`.
+    ///
+    /// Clients exceeding it are closed as described in
+    /// [`Wynd::with_max_message_size`]. Without a limit set here,
+    /// tungstenite's default of 16 MiB applies.
+    ///
+    /// ## Parameters
+    ///
+    /// - `bytes`: The largest frame payload accepted
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.with_max_frame_size(64 * 1024);
+    /// ```
+    pub fn with_max_frame_size(&mut self, bytes: usize) -> &mut Self {
+        self.max_frame_size = Some(bytes);
+        self
+    }
+
+    /// Limits the header block of an upgrade request to `bytes`.
+    ///
+    /// The standalone server peeks each request from the socket before the
+    /// handshake reads it, so a header block larger than the limit is never
+    /// buffered. Such requests are answered with `431 Request Header Fields
+    /// Too Large`, counted in
+    /// [`WyndMetrics::handshake_failures`](crate::metrics::WyndMetrics::handshake_failures)
+    /// and closed. Defaults to [`DEFAULT_MAX_HEADER_SIZE`]; tungstenite
+    /// refuses anything beyond 64 KiB regardless. Behind ripress, the HTTP
+    /// server's own limits apply instead.
+    ///
+    /// ## Parameters
+    ///
+    /// - `bytes`: The largest header block accepted, including the request line
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_max_header_size(8 * 1024);
+    /// ```
+    pub fn set_max_header_size(&mut self, bytes: usize) {
+        self.max_header_size = bytes;
+    }
+
+    /// Builds the WebSocket configuration used when accepting connections.
+    fn websocket_config(&self) -> Option<WebSocketConfig> {
+        if self.max_message_size.is_none() && self.max_frame_size.is_none() {
+            return None;
+        }
+        let mut config = WebSocketConfig::default();
+        if let Some(bytes) = self.max_message_size {
+            config = config.max_message_size(Some(bytes));
+        }
+        if let Some(bytes) = self.max_frame_size {
+            config = config.max_frame_size(Some(bytes));
+        }
+        Some(config)
+    }
+
+    /// Refuses handshakes whose `Sec-WebSocket-Version` is not `version`.
+    ///
+    /// Refused clients get `426 Upgrade Required` with a
+    /// `Sec-WebSocket-Version` header naming the required version, as RFC 6455
+    /// prescribes, and no connection is created. The standalone server already
+    /// refuses versions other than `13`, so this matters most behind ripress
+    /// or when logging legacy clients through
+    /// [`Connection::ws_version`](crate::conn::Connection::ws_version).
+    ///
+    /// ## Parameters
+    ///
+    /// - `version`: The protocol version clients must use, normally `13`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_required_ws_version(13);
+    /// ```
+    pub fn set_required_ws_version(&mut self, version: u8) {
+        self.required_ws_version = Some(version);
+    }
+
+    /// Returns whether a peer address passes the IP allowlist and blocklist.
+    fn is_ip_allowed(&self, ip: IpAddr) -> bool {
+        // IPv4 peers on dual-stack sockets show up as IPv4-mapped IPv6 addresses.
+        let ip = ip.to_canonical();
+        if self.ip_blocklist.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        match self.ip_allowlist {
+            Some(ref allowed) => allowed.iter().any(|net| net.contains(&ip)),
+            None => true,
+        }
+    }
+
+    /// Sets how many sends a broadcast or room fan-out makes before yielding
+    /// to the scheduler.
+    ///
+    /// Sends to healthy clients rarely have to wait, so a broadcast to tens of
+    /// thousands of clients could otherwise keep a runtime worker busy for the
+    /// whole loop and delay unrelated connections. Fan-out through
+    /// `handle.broadcast` and room messages yields once every `n` sends. The
+    /// default is [`DEFAULT_BROADCAST_YIELD_INTERVAL`]; `0` never yields.
+    ///
+    /// ## Parameters
+    ///
+    /// - `n`: The number of sends between yields
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_broadcast_yield_interval(16);
+    /// ```
+    pub fn set_broadcast_yield_interval(&mut self, n: usize) {
+        self.broadcast_yield_interval = n;
+    }
+
+    /// Limits how many rooms may exist at once.
+    ///
+    /// Rooms are created by the first join, so a server that lets clients
+    /// pick room names could otherwise be made to hold any number of them.
+    /// Once `n` rooms exist, joining a new room fails with
+    /// [`RoomError::CapacityExceeded`](crate::types::RoomError::CapacityExceeded)
+    /// while joins to existing rooms still succeed. Moves into a new room are
+    /// refused the same way and leave the connection where it was. Rooms are
+    /// unlimited by default. Call this before the server starts.
+    ///
+    /// ## Parameters
+    ///
+    /// - `n`: The maximum number of rooms
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::types::RoomError;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.set_max_rooms(1_000);
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_open(|handle| async move {
+    ///         if let Err(RoomError::CapacityExceeded(room)) = handle.join("lobby").await {
+    ///             let _ = handle.send_text(&format!("cannot open {}", room)).await;
+    ///         }
+    ///     })
+    ///     .await;
+    /// });
+    /// ```
+    pub fn set_max_rooms(&mut self, n: usize) {
+        self.max_rooms = Some(n);
+    }
+
+    /// Sets the flood threshold for rooms.
+    ///
+    /// A room that receives more than `max_msgs` messages within any
+    /// `window` counts as flooded and runs the handler registered with
+    /// [`Wynd::on_room_flood`]. Each room is tracked separately with a
+    /// sliding window over the messages actually delivered to it. Messages
+    /// are not dropped; use [`Wynd::set_room_rate_limit`] for that. Call this
+    /// before the server starts.
+    ///
+    /// ## Parameters
+    ///
+    /// - `max_msgs`: The most messages a room may receive within `window`
+    /// - `window`: The length of the sliding window
+    pub fn set_room_flood_limit(&mut self, max_msgs: usize, window: Duration) {
+        self.room_flood_limit = Some((max_msgs, window));
+    }
+
+    /// Registers a handler for flooded rooms.
+    ///
+    /// The handler runs with the room's name when a room first goes over the
+    /// threshold set with [`Wynd::set_room_flood_limit`], and again only
+    /// after the room has calmed down and floods once more. It runs on the
+    /// room processor, so it should return quickly; spawn a task for
+    /// anything slow.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: A closure that takes the flooded room's name
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// // More than 50 messages in 10 seconds is a flood.
+    /// wynd.set_room_flood_limit(50, Duration::from_secs(10));
+    /// wynd.on_room_flood(|room| {
+    ///     eprintln!("room {} is being flooded", room);
+    /// });
+    /// ```
+    pub fn on_room_flood<F>(&mut self, handler: F)
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.room_flood_handler = Some(Arc::new(handler));
+    }
+
+    /// Returns how long connections took to set up.
+    ///
+    /// Each connection records the time from the server accepting it until it
+    /// opened, covering the WebSocket handshake, middleware and the
+    /// `on_connection` handler. Connections that never open are not counted.
+    /// Individual connections expose the same value through
+    /// [`ConnectionHandle::setup_time`].
+    ///
+    /// ## Returns
+    ///
+    /// A [`HistogramSnapshot`] bucketed by [`SETUP_TIME_BUCKETS_MS`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// let setup = wynd.setup_time_histogram();
+    /// println!("{} connections, mean setup {:?}", setup.count, setup.mean());
+    /// ```
+    pub fn setup_time_histogram(&self) -> HistogramSnapshot {
+        self.setup_times.snapshot()
+    }
+
+    /// Returns when the server started listening.
+    ///
+    /// Set once when [`listen`](Wynd::listen), [`listen_on`](Wynd::listen_on)
+    /// or [`spawn_on`](Wynd::spawn_on) binds, or when the ripress handler is
+    /// created, and kept across [`SpawnedServer::rebind`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns `None` if the server has not started yet.
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.counters.started_at()
+    }
+
+    /// Returns how long the server has been running.
+    ///
+    /// Measured on a monotonic clock from the moment described in
+    /// [`started_at`](Wynd::started_at), so it is unaffected by changes to
+    /// the system time. Also reported as [`WyndMetrics::uptime`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns `None` if the server has not started yet.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let runtime = tokio::runtime::Runtime::new().unwrap();
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.on_connection(|_conn| async move {});
+    /// assert!(wynd.uptime().is_none());
+    ///
+    /// let server = wynd.spawn_on(0, runtime.handle()).unwrap();
+    /// println!("up for {:?}", server.server().uptime().unwrap());
+    /// runtime.block_on(server.shutdown());
+    /// ```
+    pub fn uptime(&self) -> Option<Duration> {
+        self.counters.uptime()
+    }
+
+    /// Returns a snapshot of the server's metrics.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     let metrics = wynd.metrics().await;
+    ///     println!("{} bytes received", metrics.bytes_received);
+    /// }
+    /// ```
+    pub async fn metrics(&self) -> WyndMetrics {
+        self.metrics_handle().snapshot().await
+    }
+
+    /// Returns a handle for reading the server's metrics from another task.
+    ///
+    /// Call this before [`Wynd::listen`], which consumes the server.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///     wynd.on_connection(|_conn| async move {});
+    ///
+    ///     let metrics = wynd.metrics_handle();
+    ///     tokio::spawn(async move {
+    ///         loop {
+    ///             tokio::time::sleep(Duration::from_secs(60)).await;
+    ///             let snapshot = metrics.snapshot().await;
+    ///             println!("{} handshake failures", snapshot.handshake_failures);
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn metrics_handle(&self) -> MetricsHandle<T> {
+        MetricsHandle {
+            clients: Arc::clone(&self.clients),
+            counters: Arc::clone(&self.counters),
+            setup_times: Arc::clone(&self.setup_times),
+        }
+    }
+
+    /// Calls `callback` with a metrics snapshot every `interval`.
+    ///
+    /// The reporter starts when the server starts listening and stops when
+    /// it shuts down, e.g. to push stats to a monitoring system without
+    /// writing a timer task. The first snapshot is taken one `interval`
+    /// after the server starts. The callback runs on the reporter task, so
+    /// it should return quickly; spawn a task for slow exports. Registering
+    /// another callback replaces the previous one.
+    ///
+    /// ## Parameters
+    ///
+    /// - `interval`: Time between snapshots
+    /// - `callback`: A closure that takes a [`WyndMetrics`] snapshot
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_metrics(Duration::from_secs(10), |metrics| {
+    ///     println!(
+    ///         "{} connected, {} messages received",
+    ///         metrics.connections, metrics.messages_received
+    ///     );
+    /// });
+    /// ```
+    pub fn on_metrics<F>(&mut self, interval: Duration, callback: F)
+    where
+        F: Fn(WyndMetrics) + Send + Sync + 'static,
+    {
+        self.metrics_reporter = Some((interval, Arc::new(callback)));
+    }
+
+    /// Spawns the task running the `on_metrics` callback, if one is registered.
+    pub(crate) fn start_metrics_reporter(&self) {
+        let Some((interval, ref callback)) = self.metrics_reporter else {
+            return;
+        };
+        // `tokio::time::interval` panics on a zero period.
+        let interval = interval.max(Duration::from_millis(1));
+        let callback = Arc::clone(callback);
+        let clients = Arc::clone(&self.clients);
+        let counters = Arc::clone(&self.counters);
+        let setup_times = Arc::clone(&self.setup_times);
+        let mut shutdown = self.shutdown.subscribe();
+
+        tokio::spawn(async move {
+            let mut ticks =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                tokio::select! {
+                    _ = ticks.tick() => {}
+                    // Also ends when the server is dropped.
+                    _ = shutdown.wait_for(|requested| *requested) => break,
+                }
+                let connections = clients.lock().await.len();
+                callback(WyndMetrics::collect(connections, &counters, &setup_times));
+            }
+        });
+    }
+
+    /// Coalesces rapid broadcasts made through `handle.broadcast`.
+    ///
+    /// Broadcasts are buffered for up to `window` and only the latest payload
+    /// is sent, replacing any payload buffered earlier in the same window.
+    /// Payloads are never concatenated. Buffering is per target: the sending
+    /// connection, whether the sender is included (`text` vs `emit_text`) and
+    /// whether the payload is text or binary. This suits high-frequency state
+    /// such as cursor positions, where only the latest value matters.
+    ///
+    /// Direct sends, room messages and group broadcasts are not coalesced.
+    ///
+    /// ## Parameters
+    ///
+    /// - `window`: How long broadcasts to the same target are buffered
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_broadcast_coalesce(Duration::from_millis(50));
+    /// ```
+    pub fn set_broadcast_coalesce(&mut self, window: Duration) {
+        self.broadcast_coalescer = Some(Arc::new(BroadcastCoalescer::new(window)));
+    }
+
+    /// Caps the number of connections by evicting existing ones.
+    ///
+    /// When the server already holds `max_connections` connections, admitting
+    /// a new one first evicts a connection chosen by `evict`. The evicted
+    /// client is removed from its rooms, groups, user and tags at once and
+    /// receives a close frame with code 1013 (Try Again Later); if it does
+    /// not finish closing within 5 seconds, its connection is dropped. This
+    /// suits servers that prefer dropping stale connections over rejecting
+    /// fresh ones.
+    ///
+    /// ## Parameters
+    ///
+    /// - `max_connections`: The number of connections kept before evicting
+    /// - `evict`: The policy that picks the connection to evict
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{EvictionPolicy, Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_memory_pressure_policy(10_000, EvictionPolicy::OldestIdle);
+    /// ```
+    pub fn set_memory_pressure_policy(&mut self, max_connections: usize, evict: EvictionPolicy) {
+        self.memory_pressure = Some((max_connections, evict));
+    }
+
+    /// Chooses where connections' message loops run.
+    ///
+    /// With [`ConnectionAffinity::HashById`] the server starts the given
+    /// number of dedicated single-threaded workers, and each connection's
+    /// message loop (and so every handler it runs) stays on the worker picked
+    /// by its id. This trades tokio's work stealing for fewer cross-core
+    /// cache misses in latency-sensitive deployments. Handlers on a pinned
+    /// connection run on a current-thread runtime, so they must not call
+    /// `tokio::task::block_in_place`. [`ConnectionAffinity::Shared`] is the
+    /// default. The workers stop when the server is dropped. Call this
+    /// before the server starts.
+    ///
+    /// ## Parameters
+    ///
+    /// - `strategy`: Where message loops run
+    ///
+    /// ## Returns
+    ///
+    /// Returns an error if a worker thread could not be started.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::affinity::ConnectionAffinity;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_connection_affinity(ConnectionAffinity::HashById { workers: 4 })
+    ///     .expect("worker threads should start");
+    /// ```
+    pub fn set_connection_affinity(
         &mut self,
+        strategy: ConnectionAffinity,
+    ) -> Result<(), WyndError> {
+        self.workers = match strategy {
+            ConnectionAffinity::Shared => None,
+            ConnectionAffinity::HashById { workers } => {
+                let pool = WorkerPool::new(workers).map_err(|e| {
+                    WyndError::with_source(format!("failed to start workers: {}", e), e)
+                })?;
+                Some(Arc::new(pool))
+            }
+        };
+        Ok(())
+    }
+
+    /// Returns how many dedicated workers connections are pinned to, or `0`
+    /// when message loops run on the shared runtime.
+    pub fn connection_workers(&self) -> usize {
+        self.workers.as_ref().map_or(0, |pool| pool.len())
+    }
+
+    /// Adds a connection to the client registry, evicting another one first
+    /// if the server is at capacity.
+    ///
+    /// The capacity check and the insert happen under one lock, so concurrent
+    /// accepts cannot push the server past its limit.
+    async fn register_client(
+        &self,
+        connection: &Arc<Connection<T>>,
+        handle: &Arc<ConnectionHandle<T>>,
+    ) {
+        let evicted = {
+            let mut clients = self.clients.lock().await;
+            let evicted = self.make_room_for_connection(&mut clients);
+            clients.insert(
+                connection.id(),
+                (Arc::clone(connection), Arc::clone(handle)),
+            );
+            evicted
+        };
+
+        if let Some((connection, handle)) = evicted {
+            // Close in the background so a slow client cannot hold up admission.
+            tokio::spawn(evict(connection, handle));
+        }
+    }
+
+    /// Removes a connection from `clients` if the server is at capacity.
+    ///
+    /// Returns the evicted connection, if any.
+    fn make_room_for_connection(
+        &self,
+        clients: &mut HashMap<ConnectionId, ClientEntry<T>>,
+    ) -> Option<ClientEntry<T>> {
+        let (max_connections, policy) = self.memory_pressure?;
+        if clients.len() < max_connections {
+            return None;
+        }
+        let victim = match policy {
+            EvictionPolicy::OldestIdle => clients
+                .iter()
+                .min_by_key(|(_, (_, handle))| handle.last_activity())
+                .map(|(id, _)| *id),
+        };
+        victim.and_then(|id| clients.remove(&id))
+    }
+
+    /// Runs the connection handlers in registration order, stopping early
+    /// once one of them closes the connection.
+    async fn run_connection_handlers(&self, connection: &Arc<Connection<T>>) {
+        if self.connection_handlers.is_empty() {
+            return;
+        }
+        let _permit = self.acquire_handler_permit().await;
+        for handler in &self.connection_handlers {
+            handler(Arc::clone(connection)).await;
+            if matches!(
+                connection.state().await,
+                ConnState::CLOSING | ConnState::CLOSED
+            ) {
+                break;
+            }
+        }
+    }
+
+    /// Waits for a free handler slot if a limit is configured.
+    async fn acquire_handler_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match self.handler_permits {
+            Some(ref permits) => permits.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Echoes the first `Sec-WebSocket-Protocol` offered by the client.
+    ///
+    /// Some strict clients and proxies abort the handshake when the server does
+    /// not answer with one of the subprotocols they offered. When enabled, the
+    /// handshake response carries the first offered protocol back, even though
+    /// no subprotocol dispatch takes place.
+    ///
+    /// ## Parameters
+    ///
+    /// - `enabled`: Whether to echo the offered subprotocol
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.echo_subprotocol(true);
+    /// ```
+    pub fn echo_subprotocol(&mut self, enabled: bool) {
+        self.echo_subprotocol = enabled;
+    }
+
+    /// Sends handler errors back to the client as structured JSON.
+    ///
+    /// When enabled, an error returned by an `on_text_try` or `on_binary_try`
+    /// handler is sent to the client as `{ "error": "...", "code": ... }`, and
+    /// a connection rejected by middleware receives
+    /// `{ "error": "<middleware error>", "code": 403 }` before it is closed.
+    /// When disabled, handler errors are only logged.
+    ///
+    /// ## Parameters
+    ///
+    /// - `enabled`: Whether to reply with structured errors
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::types::HandlerError;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.set_auto_error_reply(true);
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     conn.on_text_try(|msg, _handle| async move {
+    ///         Err(HandlerError::new(format!("unknown method: {}", msg.data)).with_code(404))
+    ///     });
+    /// });
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn set_auto_error_reply(&mut self, enabled: bool) {
+        self.auto_error_reply = enabled;
+    }
+
+    /// Builds the message sent to a client whose connection middleware rejected.
+    fn rejection_reply(
+        &self,
+        #[cfg_attr(not(feature = "json"), allow(unused_variables))] err: &str,
+        default: &str,
+    ) -> String {
+        #[cfg(feature = "json")]
+        if self.auto_error_reply {
+            return HandlerError::new(err).with_code(403).to_json();
+        }
+        default.to_string()
+    }
+
+    /// Registers a middleware function to be executed for each new connection.
+    ///
+    /// Middlewares are executed in the order they are registered, before the connection
+    /// handler is called. Each middleware receives the connection, handle, and a `Next`
+    /// object that can be used to call the next middleware in the chain.
+    ///
+    /// ## Parameters
+    ///
+    /// - `middleware`: An async closure that takes a connection, handle, and Next, and returns
+    ///   a result indicating whether to proceed or reject the connection
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    /// use wynd::Next;
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.use_middleware(|conn, handle, next: Next<Standalone>| async move {
+    ///     // Do some validation or modification
+    ///     println!("Middleware executing for connection {}", conn.id());
+    ///     // Call the next middleware
+    ///     next.call(conn, handle).await
+    /// });
+    /// ```
+    pub fn use_middleware<F, Fut>(&mut self, middleware: F) -> &mut Self
+    where
+        F: Fn(Arc<Connection<T>>, Arc<ConnectionHandle<T>>, Next<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(Arc<Connection<T>>, Arc<ConnectionHandle<T>>), String>>
+            + Send
+            + 'static,
+    {
+        let middleware = Middleware {
+            handler: Arc::new(move |conn, handle, next| Box::pin(middleware(conn, handle, next))),
+        };
+
+        self.middlewares.push(middleware);
+        self
+    }
+
+    /// Registers a handler for new connections.
+    ///
+    /// This method sets up a handler that will be called whenever a new
+    /// WebSocket connection is established. The handler receives a `Connection`
+    /// instance that can be used to set up event handlers for that connection.
+    ///
+    /// Each call adds another handler. For every connection, the handlers run
+    /// one at a time in registration order, each starting after the previous
+    /// one's future completed, so e.g. logging, authentication and business
+    /// logic can be registered separately. A handler that closes the
+    /// connection, e.g. with
+    /// [`Connection::close_with`](crate::conn::Connection::close_with),
+    /// stops the chain: the handlers after it are skipped.
+    /// Middleware added with [`Wynd::use_middleware`] runs before all of them.
+    ///
+    /// Inbound messages are only processed once every handler has returned,
+    /// so they should register handlers and return rather than run long-lived
+    /// work.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes a `Connection` and returns a future
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_connection(|conn| async move {
+    ///     println!("New connection: {}", conn.id());
+    ///     
+    ///     // Set up connection-specific handlers
+    ///     conn.on_open(|handle| async move {
+    ///         println!("Connection {} opened", handle.id());
+    ///     })
+    ///     .await;
+    ///
+    ///     conn.on_text(|msg, handle| async move {
+    ///         println!("Received: {}", msg.data);
+    ///     });
+    /// });
+    /// ```
+    pub fn on_connection<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn(Arc<Connection<T>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.connection_handlers
+            .push(Box::new(move |conn| Box::pin(handler(conn))));
+    }
+
+    /// Registers a handler for server-level errors.
+    ///
+    /// This method sets up a handler that will be called when server-level
+    /// errors occur, such as connection acceptance failures or WebSocket
+    /// handshake errors.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: An async closure that takes a `WyndError` and returns a future
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_error(|err| async move {
+    ///     eprintln!("Server error: {}", err);
+    /// });
+    /// ```
+    pub fn on_error<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn(WyndError) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.error_handler = Some(Box::new(move |err| Box::pin(handler(err))));
+    }
+
+    /// Registers a handler for server shutdown.
+    ///
+    /// The handler runs once, when a running server finishes its graceful
+    /// shutdown (after [`ShutdownHandle::shutdown`] or
+    /// [`SpawnedServer::shutdown`]) and every client has been closed. A
+    /// `Wynd` that is dropped without ever being shut down runs it on drop
+    /// instead. The handler is synchronous; do async cleanup after `listen`
+    /// returns.
+    ///
+    /// ## Parameters
+    ///
+    /// - `handler`: A closure that will be called during shutdown
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_close(|| {
+    ///     println!("Server shutting down");
+    /// });
+    /// ```
+    pub fn on_close<F>(&mut self, handler: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.close_handler = Some(Box::new(handler));
+    }
+
+    /// Adds a connection to a server-side group.
+    ///
+    /// Groups are lightweight routing primitives that are never echoed to
+    /// clients. A connection can belong to any number of groups and is removed
+    /// from all of them when it disconnects.
+    ///
+    /// ## Parameters
+    ///
+    /// - `group`: The group to add the connection to
+    /// - `connection_id`: The id of the connection to add
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.add_to_group("eu-west", 1).await;
+    ///     wynd.add_to_group(42, 1).await;
+    ///
+    ///     assert_eq!(wynd.group_members("eu-west").await, vec![1]);
+    /// }
+    /// ```
+    pub async fn add_to_group(&self, group: impl Into<GroupId>, connection_id: ConnectionId) {
+        let mut groups = self.groups.lock().await;
+        groups
+            .entry(group.into())
+            .or_default()
+            .insert(connection_id);
+    }
+
+    /// Removes a connection from a server-side group.
+    ///
+    /// The group itself is dropped once its last member is removed.
+    pub async fn remove_from_group(&self, group: impl Into<GroupId>, connection_id: ConnectionId) {
+        let group = group.into();
+        let mut groups = self.groups.lock().await;
+        if let Some(members) = groups.get_mut(&group) {
+            members.remove(&connection_id);
+            if members.is_empty() {
+                groups.remove(&group);
+            }
+        }
+    }
+
+    /// Returns the ids of all connections in a group, sorted ascending.
+    ///
+    /// Returns an empty vector if the group does not exist.
+    pub async fn group_members(&self, group: impl Into<GroupId>) -> Vec<ConnectionId> {
+        let groups = self.groups.lock().await;
+        let mut members: Vec<ConnectionId> = groups
+            .get(&group.into())
+            .map(|members| members.iter().copied().collect())
+            .unwrap_or_default();
+        members.sort_unstable();
+        members
+    }
+
+    /// Broadcasts a UTF-8 text message to every connection in a group.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of connections the message was delivered to.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     let delivered = wynd.group_broadcast("eu-west", "maintenance in 5 minutes").await;
+    ///     println!("Notified {} connections", delivered);
+    /// }
+    /// ```
+    pub async fn group_broadcast<S>(&self, group: impl Into<GroupId>, text: S) -> usize
+    where
+        S: Into<String>,
+    {
+        let payload: String = text.into();
+        let mut delivered = 0;
+        for h in self.group_recipients(group.into()).await {
+            match h.send_text(payload.clone()).await {
+                Ok(()) => delivered += 1,
+                Err(e) => eprintln!("Failed to broadcast to client {}: {}", h.id(), e),
+            }
+        }
+        delivered
+    }
+
+    /// Broadcasts a binary message to every connection in a group.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of connections the message was delivered to.
+    pub async fn group_broadcast_binary<B>(&self, group: impl Into<GroupId>, bytes: B) -> usize
+    where
+        B: Into<Vec<u8>>,
+    {
+        let payload = bytes.into();
+        let mut delivered = 0;
+        for h in self.group_recipients(group.into()).await {
+            match h.send_binary(payload.clone()).await {
+                Ok(()) => delivered += 1,
+                Err(e) => eprintln!("Failed to broadcast to client {}: {}", h.id(), e),
+            }
+        }
+        delivered
+    }
+
+    /// Snapshots the handles of every live connection in a group.
+    async fn group_recipients(&self, group: GroupId) -> Vec<Arc<ConnectionHandle<T>>> {
+        let members = {
+            let groups = self.groups.lock().await;
+            match groups.get(&group) {
+                Some(members) => members.clone(),
+                None => return Vec::new(),
+            }
+        };
+        let clients = self.clients.lock().await;
+        members
+            .iter()
+            .filter_map(|id| clients.get(id).map(|(_, h)| Arc::clone(h)))
+            .collect()
+    }
+
+    /// Returns how many connections carry each tag.
+    ///
+    /// Tags are added with [`ConnectionHandle::add_tag`]. Only tags carried
+    /// by at least one open connection are reported, so the result gives a
+    /// cheap segmented view of the connected population for dashboards.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     // e.g. {"mobile": 120, "web": 340}
+    ///     for (tag, count) in wynd.tag_counts().await {
+    ///         println!("{}: {}", tag, count);
+    ///     }
+    /// }
+    /// ```
+    pub async fn tag_counts(&self) -> HashMap<String, usize> {
+        let tags = self.tags.lock().await;
+        tags.iter()
+            .map(|(tag, connections)| (tag.clone(), connections.len()))
+            .collect()
+    }
+
+    /// Returns how many clients are connected.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     println!("{} clients online", wynd.clients_count().await);
+    /// }
+    /// ```
+    pub async fn clients_count(&self) -> usize {
+        self.clients.lock().await.len()
+    }
+
+    /// Returns the IDs of every connected client, in ascending order.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     for id in wynd.client_ids().await {
+    ///         println!("client {}", id);
+    ///     }
+    /// }
+    /// ```
+    pub async fn client_ids(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self.clients.lock().await.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Returns a read-only snapshot of client `id`, if it is connected.
+    ///
+    /// The snapshot combines the connection's address, state and accept time
+    /// with the rooms it is in and the tags it carries.
+    ///
+    /// ## Parameters
+    ///
+    /// - `id`: The connection ID of the client
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Some` with the snapshot, or `None` if no client with that ID
+    /// is connected.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     if let Some(info) = wynd.connection_info(1).await {
+    ///         println!("{} is in {:?}", info.addr, info.rooms);
+    ///     }
+    /// }
+    /// ```
+    pub async fn connection_info(&self, id: u64) -> Option<ConnectionInfo> {
+        let handle = {
+            let clients = self.clients.lock().await;
+            clients.get(&id).map(|(_, h)| Arc::clone(h))
+        }?;
+        let state = handle.state().await;
+        if state == ConnState::CLOSED {
+            return None;
+        }
+
+        let mut rooms: Vec<String> = {
+            let rooms = self.rooms.lock().await;
+            rooms
+                .iter()
+                .filter(|room| room.room_clients.contains_key(&id))
+                .map(|room| room.room_name.clone())
+                .collect()
+        };
+        rooms.sort_unstable();
+
+        let mut tags: Vec<String> = {
+            let tags = self.tags.lock().await;
+            tags.iter()
+                .filter(|(_, members)| members.contains(&id))
+                .map(|(tag, _)| tag.clone())
+                .collect()
+        };
+        tags.sort_unstable();
+
+        Some(ConnectionInfo {
+            id,
+            addr: handle.addr(),
+            state,
+            connected_at: handle.accepted_at,
+            rooms,
+            tags,
+        })
+    }
+
+    /// Runs `f` against the handle of client `id`, if it is connected.
+    ///
+    /// The registry lock is released before `f` runs, so `f` can send to the
+    /// client or call other server methods without blocking new connections.
+    ///
+    /// ## Parameters
+    ///
+    /// - `id`: The connection ID of the client
+    /// - `f`: The async closure to run with the client's handle
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Some` with the closure's output, or `None` if no client with
+    /// that ID is connected.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     let sent = wynd
+    ///         .with_client(7, |handle| async move {
+    ///             handle.send_text("hello from the admin").await.is_ok()
+    ///         })
+    ///         .await;
+    ///     if sent.is_none() {
+    ///         println!("client 7 is not connected");
+    ///     }
+    /// }
+    /// ```
+    pub async fn with_client<F, Fut, R>(&self, id: u64, f: F) -> Option<R>
+    where
+        F: FnOnce(Arc<ConnectionHandle<T>>) -> Fut,
+        Fut: Future<Output = R>,
+    {
+        let handle = {
+            let clients = self.clients.lock().await;
+            clients.get(&id).map(|(_, h)| Arc::clone(h))
+        }?;
+        Some(f(handle).await)
+    }
+
+    /// Sends a UTF-8 text message to every connection belonging to a user.
+    ///
+    /// Connections are associated with a user through
+    /// [`ConnectionHandle::set_user_id`], so a user signed in on several
+    /// devices or tabs receives the message on each of them.
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of connections the message was delivered to.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     let delivered = wynd.send_to_user("user-42", "You have a new message").await;
+    ///     println!("Delivered to {} devices", delivered);
+    /// }
+    /// ```
+    pub async fn send_to_user<S>(&self, uid: &str, text: S) -> usize
+    where
+        S: Into<String>,
+    {
+        let connections = {
+            let users = self.users.lock().await;
+            match users.get(uid) {
+                Some(connections) => connections.clone(),
+                None => return 0,
+            }
+        };
+        let recipients: Vec<Arc<ConnectionHandle<T>>> = {
+            let clients = self.clients.lock().await;
+            connections
+                .iter()
+                .filter_map(|id| clients.get(id).map(|(_, h)| Arc::clone(h)))
+                .collect()
+        };
+
+        let payload: String = text.into();
+        let mut delivered = 0;
+        for h in recipients {
+            match h.send_text(payload.clone()).await {
+                Ok(()) => delivered += 1,
+                Err(e) => eprintln!("Failed to send to client {}: {}", h.id(), e),
+            }
+        }
+        delivered
+    }
+
+    /// Sends a UTF-8 text message to every connection from an IP address.
+    ///
+    /// Matches the IP of each connection's [`ConnectionHandle::addr`] and
+    /// ignores the port, so all clients on one machine (or behind one NAT)
+    /// are reached. Useful for targeted notices or for debugging a specific
+    /// client machine.
+    ///
+    /// Standalone servers only: connections upgraded through ripress do not
+    /// know their peer address and report the server's address instead, so
+    /// this cannot single them out.
+    ///
+    /// ## Parameters
+    ///
+    /// - `ip`: The remote IP address to match
+    /// - `text`: The text message to send
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of connections the message was delivered to.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::net::{IpAddr, Ipv4Addr};
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+    ///     let delivered = wynd.send_to_addr(ip, "Please update your client").await;
+    ///     println!("Delivered to {} connections", delivered);
+    /// }
+    /// ```
+    pub async fn send_to_addr<S>(&self, ip: IpAddr, text: S) -> usize
+    where
+        S: Into<String>,
+    {
+        let recipients: Vec<Arc<ConnectionHandle<T>>> = self
+            .client_handles()
+            .await
+            .into_iter()
+            .filter(|h| h.addr().ip() == ip)
+            .collect();
+
+        let payload: String = text.into();
+        let mut delivered = 0;
+        for h in recipients {
+            match h.send_text(payload.clone()).await {
+                Ok(()) => delivered += 1,
+                Err(e) => eprintln!("Failed to send to client {}: {}", h.id(), e),
+            }
+        }
+        delivered
+    }
+
+    /// Starts draining the server.
+    ///
+    /// New connections are refused from now on and every connected client is
+    /// closed with code `1001` (going away) and reason `"server draining"`.
+    /// Use [`Wynd::drain_with_notice`] to warn clients before the close frame.
+    /// Close frames still pending after the shutdown timeout (see
+    /// [`Wynd::set_shutdown_timeout`]) are abandoned, so a stuck client cannot
+    /// hold up the drain.
+    pub async fn drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+
+        let close_all = async {
+            for handle in self.client_handles().await {
+                if let Err(e) = handle
+                    .send_close_frame(CloseCode::Away, "server draining")
+                    .await
+                {
+                    eprintln!(
+                        "Failed to close client {} while draining: {}",
+                        handle.id(),
+                        e
+                    );
+                }
+            }
+        };
+        if timeout(self.shutdown_timeout, close_all).await.is_err() {
+            eprintln!(
+                "Timed out draining clients after {:?}",
+                self.shutdown_timeout
+            );
+        }
+    }
+
+    /// Starts draining the server after sending every client a notice.
+    ///
+    /// The notice is sent as a text frame before any close frame, so
+    /// well-behaved clients can reconnect elsewhere on their own. It can be
+    /// plain text or JSON. Afterwards the server drains as in [`Wynd::drain`].
+    ///
+    /// ## Parameters
+    ///
+    /// - `notice`: The text sent to every connected client
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.drain_with_notice(r#"{"type":"server_draining"}"#).await;
+    /// }
+    /// ```
+    pub async fn drain_with_notice<S>(&self, notice: S)
+    where
+        S: Into<String>,
+    {
+        self.draining.store(true, Ordering::SeqCst);
+
+        let notice = notice.into();
+        for handle in self.client_handles().await {
+            if let Err(e) = handle.send_text(notice.clone()).await {
+                eprintln!(
+                    "Failed to send drain notice to client {}: {}",
+                    handle.id(),
+                    e
+                );
+            }
+        }
+
+        self.drain().await;
+    }
+
+    /// Starts draining the server, closing every client at once.
+    ///
+    /// Unlike [`Wynd::drain`], which closes clients one after another, this
+    /// sends all close frames concurrently and gives up after a single
+    /// `timeout` for the whole batch. Close frames to slow clients that are
+    /// still pending when the timeout expires are abandoned. Use it when
+    /// shutting down many connections and time is short. New connections are
+    /// refused from now on.
+    ///
+    /// ## Parameters
+    ///
+    /// - `code`: The close code, e.g. `1001` (going away) or `1012` (service
+    ///   restart); the same codes as [`ConnectionHandle::close_with`] are accepted
+    /// - `reason`: The close reason, at most 123 bytes
+    /// - `timeout`: How long to wait for all close frames to be sent
+    ///
+    /// ## Returns
+    ///
+    /// Returns how many close frames were sent before the timeout, or
+    /// [`WyndError::InvalidPayload`] without closing anything or starting to
+    /// drain if the code may not be sent or the reason is too long.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     let closed = wynd
+    ///         .fast_close_all(1012, "restarting", Duration::from_secs(2))
+    ///         .await
+    ///         .unwrap();
+    ///     println!("Closed {} connections", closed);
+    /// }
+    /// ```
+    pub async fn fast_close_all(
+        &self,
+        code: u16,
+        reason: &str,
+        timeout: Duration,
+    ) -> Result<usize, WyndError> {
+        check_close_frame(code, reason)?;
+        self.draining.store(true, Ordering::SeqCst);
+
+        let closed = AtomicUsize::new(0);
+        let handles = self.client_handles().await;
+        let sends = handles.iter().map(|handle| {
+            let closed = &closed;
+            async move {
+                match handle.send_close_frame(CloseCode::from(code), reason).await {
+                    Ok(()) => {
+                        closed.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => eprintln!("Failed to close client {}: {}", handle.id(), e),
+                }
+            }
+        });
+        if tokio::time::timeout(timeout, futures::future::join_all(sends))
+            .await
+            .is_err()
+        {
+            eprintln!("Timed out closing clients after {:?}", timeout);
+        }
+        Ok(closed.into_inner())
+    }
+
+    /// Returns `true` once [`Wynd::drain`], [`Wynd::drain_with_notice`] or
+    /// [`Wynd::fast_close_all`] was called.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Returns a handle that shuts down the server started with [`Wynd::listen`].
+    ///
+    /// Call this before `listen`, which consumes the server, and keep the
+    /// handle wherever shutdown is triggered, e.g. in a signal handler.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///     wynd.on_connection(|_conn| async move {});
+    ///
+    ///     let shutdown = wynd.shutdown_handle();
+    ///     tokio::spawn(async move {
+    ///         // e.g. tokio::signal::ctrl_c().await
+    ///         shutdown.shutdown();
+    ///     });
+    ///
+    ///     wynd.listen(0, || {}).await.unwrap();
+    /// }
+    /// ```
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            requested: Arc::clone(&self.shutdown),
+        }
+    }
+
+    /// Sets how long a graceful shutdown waits for clients to disconnect.
+    ///
+    /// Once a shutdown is requested through a [`ShutdownHandle`] or
+    /// [`SpawnedServer::shutdown`], every client is sent a close frame and
+    /// the server waits up to `timeout` for in-flight writes and closing
+    /// handshakes to finish. Connections still open after that are aborted,
+    /// so a stuck client cannot hang shutdown. [`Wynd::drain`] uses the same
+    /// bound for sending close frames. The default is
+    /// [`DEFAULT_SHUTDOWN_TIMEOUT`].
+    ///
+    /// ## Parameters
+    ///
+    /// - `timeout`: The longest time to wait for clients to disconnect
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_shutdown_timeout(Duration::from_secs(3));
+    /// ```
+    pub fn set_shutdown_timeout(&mut self, timeout: Duration) {
+        self.shutdown_timeout = timeout;
+    }
+
+    /// Closes every client with `1001` and waits for them to disconnect,
+    /// aborting the connections that remain after the shutdown timeout, then
+    /// runs the close handler.
+    pub(crate) async fn shut_down_gracefully(&self) {
+        let deadline = tokio::time::Instant::now() + self.shutdown_timeout;
+        // 1001 with a short reason always passes validation.
+        let _ = self
+            .fast_close_all(
+                u16::from(CloseCode::Away),
+                "server shutting down",
+                self.shutdown_timeout,
+            )
+            .await;
+
+        while !self.clients.lock().await.is_empty() {
+            if tokio::time::Instant::now() >= deadline {
+                let remaining: Vec<_> = self.clients.lock().await.drain().collect();
+                eprintln!(
+                    "Aborting {} connections still open after {:?}",
+                    remaining.len(),
+                    self.shutdown_timeout
+                );
+                for (_, (_, handle)) in remaining {
+                    handle.abort();
+                }
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        self.run_close_handler();
+    }
+
+    /// Runs the close handler unless it has already run.
+    fn run_close_handler(&self) {
+        if self.close_handler_ran.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if let Some(ref handler) = self.close_handler {
+            handler();
+        }
+    }
+
+    /// Returns the handles of every connected client.
+    async fn client_handles(&self) -> Vec<Arc<ConnectionHandle<T>>> {
+        let clients = self.clients.lock().await;
+        clients.values().map(|(_, h)| Arc::clone(h)).collect()
+    }
+
+    /// Marks a room as persistent, creating it if it does not exist yet.
+    ///
+    /// Persistent rooms are kept when their last member leaves and are
+    /// included in [`Wynd::export_rooms`] so they can be recreated on boot.
+    ///
+    /// ## Parameters
+    ///
+    /// - `room`: The room name
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.persist_room("lobby").await;
+    ///     wynd.set_room_metadata("lobby", "topic", "General chat").await;
+    /// }
+    /// ```
+    pub async fn persist_room(&self, room: &str) {
+        let mut rooms = self.rooms.lock().await;
+        match rooms.iter_mut().find(|r| r.room_name == room) {
+            Some(existing) => existing.persistent = true,
+            None => {
+                let mut created = Room::named(room);
+                created.persistent = true;
+                rooms.push(created);
+            }
+        }
+    }
+
+    /// Keeps the last `n` messages sent to a room and replays them to new members.
+    ///
+    /// Text and binary room messages are stored in a ring buffer bounded by
+    /// `n`. When a connection joins the room it first receives the buffered
+    /// messages, oldest first, so late joiners see recent context. The room is
+    /// created if it does not exist yet and is kept while history is enabled,
+    /// even with no members. Passing `0` disables history and clears the buffer.
+    ///
+    /// ## Parameters
+    ///
+    /// - `room`: The room name
+    /// - `n`: The number of messages to keep
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     // New members of "chat" see the last 50 chat lines.
+    ///     wynd.set_room_history("chat", 50).await;
+    /// }
+    /// ```
+    pub async fn set_room_history(&self, room: &str, n: usize) {
+        let mut rooms = self.rooms.lock().await;
+        match rooms.iter_mut().find(|r| r.room_name == room) {
+            Some(existing) => existing.set_history_limit(n),
+            None if n > 0 => {
+                let mut created = Room::named(room);
+                created.set_history_limit(n);
+                rooms.push(created);
+            }
+            None => {}
+        }
+    }
+
+    /// Limits how many messages a room accepts per time window.
+    ///
+    /// Each room has its own token bucket holding up to `max_msgs` messages
+    /// and refilling over `per`. Room messages sent while the bucket is empty
+    /// are dropped, so a single busy room cannot flood the room processor
+    /// that all rooms share. Rooms are unlimited by default; a room with a
+    /// limit is kept while empty. Pass `0` as `max_msgs` to remove the limit.
+    ///
+    /// ## Parameters
+    ///
+    /// - `room`: The room name
+    /// - `max_msgs`: How many messages the room accepts per window
+    /// - `per`: The length of the window
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     // At most 20 messages per second in the public lobby.
+    ///     wynd.set_room_rate_limit("lobby", 20, Duration::from_secs(1)).await;
+    /// }
+    /// ```
+    pub async fn set_room_rate_limit(&self, room: &str, max_msgs: u32, per: Duration) {
+        let limit = (max_msgs > 0).then(|| RateLimit::new(max_msgs, per));
+        let mut rooms = self.rooms.lock().await;
+        match rooms.iter_mut().find(|r| r.room_name == room) {
+            Some(existing) => existing.rate_limit = limit,
+            None if limit.is_some() => {
+                let mut created = Room::named(room);
+                created.rate_limit = limit;
+                rooms.push(created);
+            }
+            None => {}
+        }
+    }
+
+    /// Sets a metadata entry on an existing room.
+    ///
+    /// ## Parameters
+    ///
+    /// - `room`: The room name
+    /// - `key`: The metadata key
+    /// - `value`: The metadata value
+    ///
+    /// ## Returns
+    ///
+    /// Returns `true` if the room exists and the entry was set.
+    pub async fn set_room_metadata<K, V>(&self, room: &str, key: K, value: V) -> bool
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut rooms = self.rooms.lock().await;
+        match rooms.iter_mut().find(|r| r.room_name == room) {
+            Some(existing) => {
+                existing.metadata.insert(key.into(), value.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the metadata of a room, or `None` if the room does not exist.
+    pub async fn room_metadata(&self, room: &str) -> Option<HashMap<String, String>> {
+        let rooms = self.rooms.lock().await;
+        rooms
+            .iter()
+            .find(|r| r.room_name == room)
+            .map(|r| r.metadata.clone())
+    }
+
+    /// Returns the ids of all connections in a room, sorted ascending.
+    ///
+    /// Returns an empty vector if the room does not exist.
+    pub async fn room_members(&self, room: &str) -> Vec<ConnectionId> {
+        let rooms = self.rooms.lock().await;
+        rooms
+            .iter()
+            .find(|r| r.room_name == room)
+            .map(Room::member_ids)
+            .unwrap_or_default()
+    }
+
+    /// Returns the distinct user ids present in a room, sorted ascending.
+    ///
+    /// Unlike [`Wynd::room_members`], a user connected to the room from
+    /// several devices is listed once. Connections without a user id (see
+    /// [`ConnectionHandle::set_user_id`]) are not included.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     for user in wynd.room_users("lobby").await {
+    ///         println!("{} is online", user);
+    ///     }
+    /// }
+    /// ```
+    pub async fn room_users(&self, room: &str) -> Vec<String> {
+        let members = self.room_members(room).await;
+        let users = self.users.lock().await;
+        let mut present: Vec<String> = users
+            .iter()
+            .filter(|(_, connections)| members.iter().any(|id| connections.contains(id)))
+            .map(|(uid, _)| uid.clone())
+            .collect();
+        present.sort_unstable();
+        present
+    }
+
+    /// Runs an async action for every room.
+    ///
+    /// Each call receives a detached copy of the room carrying its name,
+    /// member count and [`Room::text`] / [`Room::binary`] for messaging its
+    /// members. The copies are taken up front, so rooms created or joined
+    /// while the actions run are not visited. At most
+    /// [`FOR_EACH_ROOM_CONCURRENCY`] actions run at once, so one slow room
+    /// does not hold up the rest.
+    ///
+    /// ## Parameters
+    ///
+    /// - `f`: The action to run for each room
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     wynd.for_each_room(|room| async move {
+    ///         println!("{} has {} members", room.name(), room.member_count());
+    ///         room.text("Maintenance at 02:00 UTC").await;
+    ///     })
+    ///     .await;
+    /// }
+    /// ```
+    pub async fn for_each_room<F, Fut>(&self, f: F)
+    where
+        F: Fn(Room<T>) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let rooms: Vec<Room<T>> = {
+            let rooms = self.rooms.lock().await;
+            rooms.iter().map(Room::detached).collect()
+        };
+        futures::stream::iter(rooms)
+            .for_each_concurrent(FOR_EACH_ROOM_CONCURRENCY, f)
+            .await;
+    }
+
+    /// Captures the current room state.
+    ///
+    /// The snapshot lists every room with its members, metadata and whether
+    /// it is persistent. With the `json` feature enabled it can be saved with
+    /// `RoomSnapshot::to_json` and reloaded later through [`Wynd::import_rooms`].
+    ///
+    /// ## Returns
+    ///
+    /// Returns a [`RoomSnapshot`] describing all rooms.
+    pub async fn export_rooms(&self) -> RoomSnapshot {
+        let rooms = self.rooms.lock().await;
+        RoomSnapshot {
+            rooms: rooms.iter().map(Room::definition).collect(),
+        }
+    }
+
+    /// Recreates the persistent rooms described by a snapshot.
+    ///
+    /// Live connections cannot be restored, so member ids are ignored and
+    /// non-persistent rooms are skipped. Metadata is merged into rooms that
+    /// already exist.
+    ///
+    /// ## Parameters
+    ///
+    /// - `snapshot`: A snapshot produced by [`Wynd::export_rooms`] or built by hand
+    ///
+    /// ## Returns
+    ///
+    /// Returns the number of persistent rooms that were imported.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::room::{RoomDefinition, RoomSnapshot};
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     let snapshot = RoomSnapshot {
+    ///         rooms: vec![RoomDefinition {
+    ///             name: "lobby".to_string(),
+    ///             persistent: true,
+    ///             ..Default::default()
+    ///         }],
+    ///     };
+    ///
+    ///     assert_eq!(wynd.import_rooms(snapshot).await, 1);
+    /// }
+    /// ```
+    pub async fn import_rooms(&self, snapshot: RoomSnapshot) -> usize {
+        let mut rooms = self.rooms.lock().await;
+        let mut imported = 0;
+
+        for definition in snapshot.rooms.into_iter().filter(|d| d.persistent) {
+            let index = match rooms.iter().position(|r| r.room_name == definition.name) {
+                Some(index) => index,
+                None => {
+                    rooms.push(Room::named(definition.name));
+                    rooms.len() - 1
+                }
+            };
+
+            let room = &mut rooms[index];
+            room.persistent = true;
+            room.metadata.extend(definition.metadata);
+            imported += 1;
+        }
+
+        imported
+    }
+
+    // listen is only meaningful when T = TcpStream; provided in a specialized impl below.
+
+    /// Executes the middleware chain for a connection.
+    ///
+    /// This method builds a chain of middleware handlers where each middleware
+    /// can call `next.call()` to proceed to the next middleware in the chain.
+    ///
+    /// ## Parameters
+    ///
+    /// - `conn`: The connection to process through the middleware chain
+    /// - `handle`: The connection handle to process through the middleware chain
+    ///
+    /// ## Returns
+    ///
+    /// Returns the final connection and handle after all middlewares have processed,
+    /// or an error if any middleware fails.
+    async fn execute_middleware_chain(
+        &self,
+        conn: Arc<Connection<T>>,
+        handle: Arc<ConnectionHandle<T>>,
+    ) -> Result<(Arc<Connection<T>>, Arc<ConnectionHandle<T>>), String> {
+        if self.middlewares.is_empty() {
+            return Ok((conn, handle));
+        }
+
+        let mut next = middleware::Next::finalize();
+
+        let middlewares_arc = Arc::new(self.middlewares.clone());
+        let middleware_count = middlewares_arc.len();
+
+        for i in (0..middleware_count).rev() {
+            let current_next = next.clone();
+            let middlewares_clone = Arc::clone(&middlewares_arc);
+            let middleware_index = i;
+            next = middleware::Next::new(Arc::new(move |conn, handle| {
+                let current_next = current_next.clone();
+                let middlewares_clone = Arc::clone(&middlewares_clone);
+                let middleware_index = middleware_index;
+                Box::pin(async move {
+                    if middleware_index < middlewares_clone.len() {
+                        let middleware = &middlewares_clone[middleware_index];
+                        middleware.handle(conn, handle, current_next).await
+                    } else {
+                        current_next.call(conn, handle).await
+                    }
+                })
+            }));
+        }
+
+        next.call(conn, handle).await
+    }
+
+    /// This method performs the WebSocket handshake and creates a `Connection`
+    /// instance for the new connection. It then calls the connection handler
+    /// if one is registered.
+    ///
+    /// ## Parameters
+    ///
+    /// - `stream`: The TCP stream for the connection
+    /// - `addr`: The remote address of the connection
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if the connection is handled successfully, or an error
+    /// if the WebSocket handshake fails or other errors occur.
+    pub(crate) async fn handle_connection(
+        &self,
         stream: T,
         addr: SocketAddr,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let websocket = match timeout(Duration::from_secs(10), accept_async(stream)).await {
-            Ok(res) => res?, // tungstenite::Result<_>
+        let accepted_at = Instant::now();
+
+        if self.is_draining() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("Connection from {} refused while draining", addr),
+            )
+            .into());
+        }
+
+        if !self.is_ip_allowed(addr.ip()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("Connection from {} refused by IP access list", addr.ip()),
+            )
+            .into());
+        }
+
+        let echo_subprotocol = self.echo_subprotocol;
+        let required_ws_version = self.required_ws_version;
+        let mut raw_query = String::new();
+        let mut headers = HashMap::new();
+        let mut extensions = Vec::new();
+        let mut ws_version = 0;
+        // The error type is dictated by tungstenite's handshake callback.
+        #[allow(clippy::result_large_err)]
+        let callback = |req: &HandshakeRequest,
+                        mut response: HandshakeResponse|
+         -> Result<HandshakeResponse, ErrorResponse> {
+            if let Some(query) = req.uri().query() {
+                raw_query = query.to_string();
+            }
+            headers = request_headers(req.headers());
+            ws_version = offered_ws_version(req.headers());
+            if let Some(required) = required_ws_version {
+                if ws_version != required {
+                    let mut refusal = ErrorResponse::new(Some(format!(
+                        "Unsupported WebSocket version {}; expected {}",
+                        ws_version, required
+                    )));
+                    *refusal.status_mut() = StatusCode::UPGRADE_REQUIRED;
+                    refusal.headers_mut().insert(
+                        "sec-websocket-version",
+                        HeaderValue::from(u16::from(required)),
+                    );
+                    return Err(refusal);
+                }
+            }
+            if echo_subprotocol {
+                if let Some(protocol) = first_offered_subprotocol(req.headers()) {
+                    response
+                        .headers_mut()
+                        .insert("sec-websocket-protocol", protocol);
+                }
+            }
+            extensions = negotiated_extensions(response.headers());
+            Ok(response)
+        };
+
+        let config = self.websocket_config();
+        let websocket = match timeout(
+            HANDSHAKE_TIMEOUT,
+            accept_hdr_async_with_config(stream, callback, config),
+        )
+        .await
+        {
+            Ok(Ok(websocket)) => websocket,
+            Ok(Err(e)) => {
+                self.counters.record_handshake_failure();
+                return Err(e.into());
+            }
             Err(_) => {
+                self.counters.record_handshake_failure();
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::TimedOut,
                     "WebSocket handshake timed out",
                 )
                 .into());
             }
-        };
-        let connection_id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        };
+        self.counters.record_accepted();
+
+        let connection_id = self.assign_connection_id();
+
+        let mut connection = Connection::new(connection_id, websocket, addr);
+
+        connection.defer_start();
+        connection.set_query(&raw_query);
+        connection.set_headers(headers);
+        connection.set_negotiated_extensions(extensions);
+        connection.set_ws_version(ws_version);
+        if let Some(keepalive) = self.keepalive {
+            connection.set_keepalive(keepalive);
+        }
+        if let Some(ref workers) = self.workers {
+            connection.set_worker(workers.worker_for(connection_id));
+        }
+        #[cfg(feature = "json")]
+        connection.set_auto_error_reply(self.auto_error_reply);
+        connection.set_clients_registry(Arc::clone(&self.clients));
+
+        let mut broadcaster = Broadcaster::new(Arc::clone(&self.clients), connection_id);
+        broadcaster.coalescer = self.broadcast_coalescer.clone();
+        broadcaster.yield_every = self.broadcast_yield_interval;
+
+        let mut handle = ConnectionHandle::new(
+            connection.id(),
+            Arc::clone(&connection.writer),
+            addr,
+            broadcaster,
+            Arc::clone(&connection.state),
+            Arc::clone(&self.room_sender),
+        );
+        handle.groups = Arc::clone(&self.groups);
+        handle.users = Arc::clone(&self.users);
+        handle.tags = Arc::clone(&self.tags);
+        handle.outbound_transform = connection.outbound_transform.clone();
+        handle.accepted_kinds = Arc::clone(&connection.accepted_kinds);
+        handle.headers = Arc::clone(&connection.headers);
+        handle.inbound_muted = Arc::clone(&connection.inbound_muted);
+        handle.max_total_bytes = self.max_total_bytes;
+        handle.max_close_reason_len = self.max_close_reason_len;
+        handle.auto_pong = self.auto_pong;
+        handle.require_room_membership = self.require_room_membership;
+        handle.handler_permits = self.handler_permits.clone();
+        handle.set_response_capacity(self.room_response_capacity);
+        handle.accepted_at = accepted_at;
+        handle.setup_times = Arc::clone(&self.setup_times);
+        handle.counters = Arc::clone(&self.counters);
+        if let Some(capacity) = self.send_queue_capacity {
+            handle.set_send_queue_capacity(capacity);
+        }
+        let handle = Arc::new(handle);
+
+        let arc_connection = Arc::new(connection);
+
+        arc_connection.set_handle(Arc::clone(&handle)).await;
+
+        {
+            let clients_registry = Arc::clone(&self.clients);
+            let rooms_registry = Arc::clone(&self.rooms);
+            let groups_registry = Arc::clone(&self.groups);
+            let users_registry = Arc::clone(&self.users);
+            let tags_registry = Arc::clone(&self.tags);
+            let handle_id = handle.id();
+            arc_connection
+                .on_cleanup(move |_event| {
+                    let clients_registry = Arc::clone(&clients_registry);
+                    let rooms_registry = Arc::clone(&rooms_registry);
+                    let groups_registry = Arc::clone(&groups_registry);
+                    let users_registry = Arc::clone(&users_registry);
+                    let tags_registry = Arc::clone(&tags_registry);
+                    async move {
+                        let mut clients = clients_registry.lock().await;
+                        clients.retain(|_c, h| h.0.id() != handle_id);
+
+                        let mut rooms = rooms_registry.lock().await;
+                        for room in rooms.iter_mut() {
+                            room.room_clients.remove(&handle_id);
+                        }
+                        rooms.retain(|room| !room.is_disposable());
+
+                        let mut groups = groups_registry.lock().await;
+                        for members in groups.values_mut() {
+                            members.remove(&handle_id);
+                        }
+                        groups.retain(|_, members| !members.is_empty());
+
+                        let mut users = users_registry.lock().await;
+                        for connections in users.values_mut() {
+                            connections.remove(&handle_id);
+                        }
+                        users.retain(|_, connections| !connections.is_empty());
+
+                        let mut tags = tags_registry.lock().await;
+                        for connections in tags.values_mut() {
+                            connections.remove(&handle_id);
+                        }
+                        tags.retain(|_, connections| !connections.is_empty());
+                    }
+                })
+                .await;
+        }
+
+        // Cleanup is registered first, so an eviction right after the
+        // insert already clears everything the connection joins.
+        self.register_client(&arc_connection, &handle).await;
+
+        arc_connection.on_open(|_handle| async move {}).await;
+
+        let middleware_result = self
+            .execute_middleware_chain(Arc::clone(&arc_connection), Arc::clone(&handle))
+            .await;
+
+        match middleware_result {
+            Err(err) => {
+                let state = handle.state().await;
+                if state == ConnState::OPEN || state == ConnState::CONNECTING {
+                    let _ = handle
+                        .send_text(self.rejection_reply(&err, "Connection rejected by server"))
+                        .await;
+                    let _ = handle.close().await;
+                }
+                eprintln!("Middleware error: {}", err);
+                return Err(err.into());
+            }
+            Ok((final_conn, final_handle)) => {
+                let state = final_handle.state().await;
+                if state == ConnState::CLOSED || state == ConnState::CLOSING {
+                    return Ok(());
+                }
+
+                self.run_connection_handlers(&final_conn).await;
+
+                // Every handler is registered now, so it is safe to start reading.
+                final_conn.start().await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts processing room events on a fresh room channel.
+    ///
+    /// Called once the server starts so that joins, leaves and room messages
+    /// sent through connection handles are applied to the shared room state.
+    pub(crate) fn start_room_processor(&mut self) {
+        let (room_sender, room_receiver) =
+            tokio::sync::mpsc::channel::<RoomEvents<T>>(self.room_event_channel_capacity);
+        self.room_sender = Arc::new(room_sender);
+
+        Self::handle_communication(
+            room_receiver,
+            Arc::clone(&self.rooms),
+            Arc::clone(&self.clients),
+            self.broadcast_yield_interval,
+            self.max_rooms,
+            self.flood_watch(),
+            Arc::clone(&self.counters),
+        );
+    }
+
+    /// Returns the room flood watch, if both a limit and a handler are set.
+    fn flood_watch(&self) -> Option<FloodWatch> {
+        let (max_msgs, window) = self.room_flood_limit?;
+        let handler = Arc::clone(self.room_flood_handler.as_ref()?);
+        Some(FloodWatch {
+            max_msgs,
+            window,
+            handler,
+        })
+    }
+
+    /// Spawns the task that processes room events for this server.
+    fn handle_communication(
+        mut room_receiver: Receiver<RoomEvents<T>>,
+        rooms: Arc<tokio::sync::Mutex<Vec<Room<T>>>>,
+        clients: ClientRegistry<T>,
+        yield_every: usize,
+        max_rooms: Option<usize>,
+        flood_watch: Option<FloodWatch>,
+        counters: Arc<Counters>,
+    ) {
+        tokio::spawn(async move {
+            while let Some(room_data) = room_receiver.recv().await {
+                match room_data {
+                    RoomEvents::JoinRoom {
+                        client_id,
+                        handle,
+                        room_name,
+                        ack,
+                    } => {
+                        let mut rooms = rooms.lock().await;
+                        let joined =
+                            join_room(&mut rooms, client_id, &handle, &room_name, max_rooms);
+                        drop(rooms);
+
+                        match joined {
+                            Ok(replay) => {
+                                replay_history(&handle, replay).await;
+                                let _ = ack.send(Ok(()));
+                            }
+                            Err(e) => {
+                                let _ = ack.send(Err(e));
+                            }
+                        }
+                    }
+                    RoomEvents::TextMessage {
+                        room_name,
+                        text,
+                        client_id,
+                        delivered,
+                    } => {
+                        let mut report = DeliveryReport::new(delivered);
+                        let mut rooms = rooms.lock().await;
+
+                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
+
+                        if maybe_room.is_none() {
+                            continue;
+                        }
+
+                        let room = maybe_room.unwrap();
+                        if !room.room_clients.contains_key(&client_id) {
+                            continue;
+                        }
+                        if !room.admit() {
+                            continue;
+                        }
+                        if let Some(ref flood_watch) = flood_watch {
+                            flood_watch.observe(room);
+                        }
+                        counters.record_room_messages(1);
+                        room.record(&BroadcastPayload::Text(text.clone()));
+
+                        let handles: Vec<_> = {
+                            if let Some(room) = rooms.iter().find(|r| r.room_name == room_name) {
+                                room.room_clients.values().cloned().collect()
+                            } else {
+                                Vec::new()
+                            }
+                        };
+                        // Release the rooms lock before the fan-out, so slow
+                        // recipients don't stall other room events.
+                        drop(rooms);
+
+                        if handles.is_empty() {
+                            eprintln!("Room not found: {}", room_name);
+                        } else {
+                            for (sent, h) in handles.into_iter().enumerate() {
+                                yield_periodically(sent, yield_every).await;
+                                if h.id == client_id {
+                                    continue;
+                                } else {
+                                    match h.send_text(text.clone()).await {
+                                        Ok(()) => report.delivered(),
+                                        Err(e) => {
+                                            eprintln!("Failed to send text to client: {}", e)
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    RoomEvents::TextSequence {
+                        room_name,
+                        texts,
+                        client_id,
+                    } => {
+                        let handles: Vec<_> = {
+                            let mut rooms = rooms.lock().await;
+                            let Some(room) =
+                                rooms.iter_mut().find(|room| room.room_name == room_name)
+                            else {
+                                continue;
+                            };
+                            if !room.room_clients.contains_key(&client_id) || !room.admit() {
+                                continue;
+                            }
+                            if let Some(ref flood_watch) = flood_watch {
+                                flood_watch.observe(room);
+                            }
+                            counters.record_room_messages(texts.len());
+                            for text in &texts {
+                                room.record(&BroadcastPayload::Text(text.clone()));
+                            }
+                            room.room_clients.values().cloned().collect()
+                        };
+
+                        for (sent, h) in handles.into_iter().enumerate() {
+                            yield_periodically(sent, yield_every).await;
+                            if h.id == client_id {
+                                continue;
+                            }
+                            if let Err(e) = h.send_text_sequence(&texts).await {
+                                eprintln!("Failed to send text sequence to client: {}", e);
+                            }
+                        }
+                    }
+                    RoomEvents::BinaryMessage {
+                        room_name,
+                        bytes,
+                        client_id,
+                    } => {
+                        let mut rooms = rooms.lock().await;
+
+                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
+
+                        if maybe_room.is_none() {
+                            continue;
+                        }
+
+                        let room = maybe_room.unwrap();
+                        if !room.room_clients.contains_key(&client_id) {
+                            continue;
+                        }
+                        if !room.admit() {
+                            continue;
+                        }
+                        if let Some(ref flood_watch) = flood_watch {
+                            flood_watch.observe(room);
+                        }
+                        counters.record_room_messages(1);
+                        room.record(&BroadcastPayload::Binary(bytes.clone()));
+
+                        let recipients = {
+                            rooms
+                                .iter()
+                                .find(|r| r.room_name == room_name)
+                                .map(|r| r.room_clients.values().cloned().collect::<Vec<_>>())
+                        };
+                        drop(rooms);
+                        if let Some(recipients) = recipients {
+                            for (sent, h) in recipients.into_iter().enumerate() {
+                                yield_periodically(sent, yield_every).await;
+                                if h.id == client_id {
+                                    continue;
+                                } else {
+                                    if let Err(e) = h.send_binary(bytes.clone()).await {
+                                        eprintln!("Failed to send binary to client: {}", e);
+                                    }
+                                }
+                            }
+                        } else {
+                            println!("Room not found: {}", room_name);
+                        }
+                    }
+                    RoomEvents::EmitTextMessage {
+                        client_id,
+                        room_name,
+                        text,
+                        delivered,
+                    } => {
+                        let mut report = DeliveryReport::new(delivered);
+                        let mut rooms = rooms.lock().await;
+
+                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
+
+                        if maybe_room.is_none() {
+                            continue;
+                        }
+
+                        let room = maybe_room.unwrap();
+                        if !room.room_clients.contains_key(&client_id) {
+                            continue;
+                        }
+                        if !room.admit() {
+                            continue;
+                        }
+                        if let Some(ref flood_watch) = flood_watch {
+                            flood_watch.observe(room);
+                        }
+                        counters.record_room_messages(1);
+                        room.record(&BroadcastPayload::Text(text.clone()));
+
+                        let handles: Vec<_> = {
+                            if let Some(room) = rooms.iter().find(|r| r.room_name == room_name) {
+                                room.room_clients.values().cloned().collect()
+                            } else {
+                                Vec::new()
+                            }
+                        };
+                        drop(rooms);
+                        if handles.is_empty() {
+                            eprintln!("Room not found: {}", room_name);
+                        } else {
+                            for (sent, h) in handles.into_iter().enumerate() {
+                                yield_periodically(sent, yield_every).await;
+                                match h.send_text(text.clone()).await {
+                                    Ok(()) => report.delivered(),
+                                    Err(e) => eprintln!("Failed to send text to client: {}", e),
+                                }
+                            }
+                        }
+                    }
+                    RoomEvents::EmitBinaryMessage {
+                        client_id,
+                        room_name,
+                        bytes,
+                    } => {
+                        let mut rooms = rooms.lock().await;
+
+                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
+
+                        if maybe_room.is_none() {
+                            continue;
+                        }
+
+                        let room = maybe_room.unwrap();
+                        if !room.room_clients.contains_key(&client_id) {
+                            continue;
+                        }
+                        if !room.admit() {
+                            continue;
+                        }
+                        if let Some(ref flood_watch) = flood_watch {
+                            flood_watch.observe(room);
+                        }
+                        counters.record_room_messages(1);
+                        room.record(&BroadcastPayload::Binary(bytes.clone()));
+
+                        let recipients = {
+                            rooms
+                                .iter()
+                                .find(|r| r.room_name == room_name)
+                                .map(|r| r.room_clients.values().cloned().collect::<Vec<_>>())
+                        };
+                        drop(rooms);
+                        if let Some(recipients) = recipients {
+                            for (sent, h) in recipients.into_iter().enumerate() {
+                                yield_periodically(sent, yield_every).await;
+                                if let Err(e) = h.send_binary(bytes.clone()).await {
+                                    eprintln!("Failed to send binary to client: {}", e);
+                                }
+                            }
+                        } else {
+                            println!("Room not found: {}", room_name);
+                        }
+                    }
+                    RoomEvents::LeaveRoom {
+                        client_id,
+                        room_name,
+                    } => {
+                        let mut rooms_guard = rooms.lock().await;
+                        leave_room(&mut rooms_guard, client_id, &room_name);
+                    }
+                    RoomEvents::MoveRoom {
+                        client_id,
+                        handle,
+                        from,
+                        to,
+                        ack,
+                    } => {
+                        // Both steps run under one lock, so no other room event
+                        // observes the client in neither or both rooms.
+                        let mut rooms_guard = rooms.lock().await;
+                        // Leaving first frees `from` if the client was its last
+                        // member, so moving at the room cap still succeeds.
+                        let was_member = rooms_guard.iter().any(|room| {
+                            room.room_name == from && room.room_clients.contains_key(&client_id)
+                        });
+                        leave_room(&mut rooms_guard, client_id, &from);
+                        if !room_available(&rooms_guard, &to, max_rooms) {
+                            if was_member {
+                                let _ =
+                                    join_room(&mut rooms_guard, client_id, &handle, &from, None);
+                            }
+                            let _ = ack.send(Err(RoomError::CapacityExceeded(to)));
+                            continue;
+                        }
+                        let replay = join_room(&mut rooms_guard, client_id, &handle, &to, None)
+                            .unwrap_or_default();
+                        drop(rooms_guard);
+
+                        replay_history(&handle, replay).await;
+                        let _ = ack.send(Ok(()));
+                    }
+                    RoomEvents::ListRooms { client_id } => {
+                        let rooms_guard = rooms.lock().await;
+                        let mut list = Vec::new();
+                        for room in rooms_guard.iter() {
+                            if room.room_clients.contains_key(&client_id) {
+                                list.push(room.room_name.clone());
+                            }
+                        }
+
+                        let clients = Arc::clone(&clients);
+                        let clients_guard = clients.lock().await;
+                        if let Some((_, handle)) =
+                            clients_guard.iter().find(|(_, h)| h.0.id() == client_id)
+                        {
+                            // Never wait on a slow reader here: that would stall
+                            // every room in the processor.
+                            match handle.1.response_sender.try_send(list) {
+                                Ok(()) => {}
+                                Err(tokio::sync::mpsc::error::TrySendError::Full(list)) => {
+                                    let response_sender = Arc::clone(&handle.1.response_sender);
+                                    tokio::spawn(async move {
+                                        let _ = response_sender.send(list).await;
+                                    });
+                                }
+                                Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => {
+                                    eprintln!(
+                                        "Failed to send list rooms response to client {}: channel closed",
+                                        client_id
+                                    );
+                                }
+                            }
+                        } else {
+                            eprintln!("Client {} not found for list rooms response", client_id);
+                        }
+                    }
+                    RoomEvents::IsMember {
+                        client_id,
+                        room_name,
+                        response_to,
+                    } => {
+                        let rooms = rooms.lock().await;
+                        let member = rooms.iter().any(|room| {
+                            room.room_name == room_name
+                                && room.room_clients.contains_key(&client_id)
+                        });
+                        let _ = response_to.send(member);
+                    }
+                    RoomEvents::ListRoomsResponse {
+                        client_id: _,
+                        rooms: _,
+                    } => {}
+                    RoomEvents::LeaveAllRooms { client_id } => {
+                        let mut rooms_guard = rooms.lock().await;
+                        let mut rooms_to_remove = Vec::new();
+
+                        for (index, room) in rooms_guard.iter_mut().enumerate() {
+                            if room.room_clients.contains_key(&client_id) {
+                                room.room_clients.remove(&client_id);
+
+                                if room.is_disposable() {
+                                    rooms_to_remove.push(index);
+                                }
+                            }
+                        }
+
+                        for index in rooms_to_remove.iter().rev() {
+                            rooms_guard.remove(*index);
+                        }
+                    }
+                    RoomEvents::ListUsers { response_to } => {
+                        let clients = Arc::clone(&clients);
+                        let clients = clients.lock().await;
+                        let mut clients_info = Vec::new();
+
+                        for client in clients.iter() {
+                            clients_info.push(ClientInfo {
+                                id: *client.0,
+                                handle: Arc::clone(&client.1 .1),
+                            })
+                        }
+
+                        if response_to.send(clients_info).is_err() {
+                            eprintln!("Failed to send ListUsers response: receiver dropped");
+                        }
+                    }
+                    RoomEvents::CountMembers {
+                        room_name,
+                        response_to,
+                    } => {
+                        let rooms = rooms.lock().await;
+                        let count = rooms
+                            .iter()
+                            .find(|room| room.room_name == room_name)
+                            .map_or(0, Room::member_count);
+                        let _ = response_to.send(count);
+                    }
+                    RoomEvents::ListMembers {
+                        room_name,
+                        response_to,
+                    } => {
+                        let rooms = rooms.lock().await;
+                        let ids = rooms
+                            .iter()
+                            .find(|room| room.room_name == room_name)
+                            .map(Room::member_ids)
+                            .unwrap_or_default();
+                        let _ = response_to.send(ids);
+                    }
+                }
+            }
+        });
+    }
+}
 
-        let mut connection = Connection::new(connection_id, websocket, addr);
+/// Requests a graceful shutdown of a server started with [`Wynd::listen`].
+///
+/// Obtained from [`Wynd::shutdown_handle`] before the server is started.
+/// Handles are cheap to clone and can be moved into a signal handler or any
+/// other task.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    requested: Arc<watch::Sender<bool>>,
+}
 
-        connection.set_clients_registry(Arc::clone(&self.clients));
+impl ShutdownHandle {
+    /// Asks the server to shut down.
+    ///
+    /// [`Wynd::listen`] stops accepting connections, closes every client with
+    /// code `1001` (going away) and returns once all clients have
+    /// disconnected or the shutdown timeout has passed. Calling this more
+    /// than once, or before the server has started, is harmless: a server
+    /// started after a shutdown was requested shuts down immediately.
+    pub fn shutdown(&self) {
+        self.requested.send_replace(true);
+    }
+
+    /// Returns `true` once [`ShutdownHandle::shutdown`] was called.
+    pub fn is_shutdown_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+}
+
+/// Reads the metrics of a server started with [`Wynd::listen`].
+///
+/// Obtained from [`Wynd::metrics_handle`] before the server is started, so
+/// another task can poll the server's metrics while `listen` runs. Handles
+/// are cheap to clone.
+pub struct MetricsHandle<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    clients: ClientRegistry<T>,
+    counters: Arc<Counters>,
+    setup_times: Arc<Histogram>,
+}
+
+impl<T> MetricsHandle<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    /// Returns a snapshot of the server's metrics, same as [`Wynd::metrics`].
+    pub async fn snapshot(&self) -> WyndMetrics {
+        let connections = self.clients.lock().await.len();
+        WyndMetrics::collect(connections, &self.counters, &self.setup_times)
+    }
+}
 
-        let broadcaster = Broadcaster {
+impl<T> Clone for MetricsHandle<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
             clients: Arc::clone(&self.clients),
-            current_client_id: connection_id,
-        };
+            counters: Arc::clone(&self.counters),
+            setup_times: Arc::clone(&self.setup_times),
+        }
+    }
+}
+
+impl<T> Debug for MetricsHandle<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsHandle").finish()
+    }
+}
+
+/// A standalone server started with [`Wynd::spawn_on`].
+///
+/// Dropping this value leaves the server running; call
+/// [`SpawnedServer::shutdown`] to stop it.
+#[derive(Debug)]
+pub struct SpawnedServer {
+    wynd: Arc<Wynd<TcpStream>>,
+    addr: SocketAddr,
+    runtime: tokio::runtime::Handle,
+    accept: tokio::task::JoinHandle<()>,
+}
+
+impl SpawnedServer {
+    /// Returns the address the server is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns the running server, for broadcasts and other server-side calls.
+    pub fn server(&self) -> &Wynd<TcpStream> {
+        &self.wynd
+    }
+
+    /// Moves the server to a new port without dropping anything.
+    ///
+    /// The new port is bound first, so if that fails the server keeps
+    /// accepting on its current port. Otherwise the old listener is closed
+    /// and new connections are accepted on the new port, while existing
+    /// connections, rooms, groups and handlers are untouched. Useful for
+    /// recovering from a port conflict or hot reconfiguration.
+    ///
+    /// ## Parameters
+    ///
+    /// - `port`: The port number to move to; `0` picks a free port
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` once the server accepts on the new port, or an error
+    /// if the port could not be bound.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let runtime = tokio::runtime::Builder::new_multi_thread()
+    ///     .enable_all()
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.on_connection(|_conn| async move {});
+    /// let mut server = wynd.spawn_on(0, runtime.handle()).unwrap();
+    ///
+    /// server.rebind(0).unwrap();
+    /// println!("Now listening on {}", server.local_addr());
+    /// ```
+    pub fn rebind(&mut self, port: u16) -> Result<(), WyndError> {
+        let (listener, addr) = bind_listener(port, &self.runtime)?;
+        self.accept.abort();
+        self.addr = addr;
+        self.accept = self
+            .runtime
+            .spawn(Wynd::accept_loop(Arc::clone(&self.wynd), listener));
+        Ok(())
+    }
+
+    /// Stops accepting connections and shuts the server down gracefully.
+    ///
+    /// Every client is closed with code `1001` (going away). Returns once
+    /// all clients have disconnected, or after the shutdown timeout set with
+    /// [`Wynd::set_shutdown_timeout`], when remaining connections are aborted.
+    pub async fn shutdown(self) {
+        self.accept.abort();
+        let _ = self.accept.await;
+        self.wynd.shut_down_gracefully().await;
+    }
+}
 
-        let (response_sender, response_receiver) = tokio::sync::mpsc::channel(10);
-
-        let handle = Arc::new(ConnectionHandle {
-            id: connection.id(),
-            writer: Arc::clone(&connection.writer),
-            addr: addr,
-            broadcast: broadcaster,
-            state: Arc::clone(&connection.state),
-            room_sender: Arc::clone(&self.room_sender),
-            response_sender: Arc::new(response_sender),
-            response_receiver: Arc::new(tokio::sync::Mutex::new(response_receiver)),
-        });
+/// Binds a listener on `port` for use on `runtime`.
+fn bind_listener(
+    port: u16,
+    runtime: &tokio::runtime::Handle,
+) -> Result<(TcpListener, SocketAddr), WyndError> {
+    let addr = format!("0.0.0.0:{}", port);
+    let bind_error =
+        |e: std::io::Error| WyndError::with_source(format!("Failed to bind {}: {}", addr, e), e);
+    let listener = std::net::TcpListener::bind(&addr).map_err(bind_error)?;
+    listener.set_nonblocking(true).map_err(bind_error)?;
+    let local_addr = listener.local_addr().map_err(bind_error)?;
 
-        let arc_connection = Arc::new(connection);
+    let _runtime = runtime.enter();
+    let listener = TcpListener::from_std(listener).map_err(bind_error)?;
+    Ok((listener, local_addr))
+}
 
-        arc_connection.set_handle(Arc::clone(&handle)).await;
+impl Wynd<TcpStream> {
+    /// Answers plain HTTP requests for `path` with `200 OK`.
+    ///
+    /// Load balancer health checks usually send a plain `GET` rather than a
+    /// WebSocket upgrade, which would otherwise fail the handshake and be
+    /// reported as an error. With a health path set, the request line of
+    /// each new connection is peeked before the handshake; a `GET` for
+    /// `path` gets a `200 OK` response and the connection is closed, so one
+    /// port serves both health checks and WebSocket clients. Peers refused
+    /// by [`Wynd::set_ip_allowlist`] or [`Wynd::set_ip_blocklist`] are
+    /// dropped without an answer.
+    ///
+    /// ## Parameters
+    ///
+    /// - `path`: The request path to answer, such as `"/healthz"`
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.set_health_path("/healthz");
+    /// ```
+    pub fn set_health_path(&mut self, path: impl Into<String>) {
+        self.health_path = Some(path.into());
+    }
 
-        {
-            let mut clients = self.clients.lock().await;
-            clients.insert(
-                arc_connection.id(),
-                (Arc::clone(&arc_connection), Arc::clone(&handle)),
-            );
-        }
+    /// Responds to a health check on `stream`, if that is what it carries.
+    ///
+    /// Returns `true` if the request was a health check and has been
+    /// answered, or if the client sent nothing within the handshake timeout;
+    /// either way the connection should be dropped. `buf` is scratch space
+    /// for the peeked bytes.
+    async fn answer_health_check(&self, stream: &mut TcpStream, buf: &mut [u8]) -> bool {
+        let Some(path) = self.health_path.as_deref() else {
+            return false;
+        };
 
-        {
-            let clients_registry = Arc::clone(&self.clients);
-            let rooms_registry = Arc::clone(&self.rooms);
-            let handle_id = handle.id();
-            arc_connection.on_close(move |_event| {
-                let clients_registry = Arc::clone(&clients_registry);
-                let rooms_registry = Arc::clone(&rooms_registry);
-                async move {
-                    let mut clients = clients_registry.lock().await;
-                    clients.retain(|_c, h| h.0.id() != handle_id);
-
-                    let mut rooms = rooms_registry.lock().await;
-                    for room in rooms.iter_mut() {
-                        room.room_clients.remove(&handle_id);
-                    }
-                    rooms.retain(|room| !room.room_clients.is_empty());
-                }
-            });
+        let peeked = match timeout(HANDSHAKE_TIMEOUT, stream.peek(buf)).await {
+            Ok(Ok(n)) => &buf[..n],
+            Ok(Err(_)) => return false,
+            Err(_) => {
+                self.counters.record_handshake_failure();
+                return true;
+            }
+        };
+        let Some(line_end) = peeked.windows(2).position(|w| w == b"\r\n") else {
+            return false;
+        };
+        let request_line = String::from_utf8_lossy(&peeked[..line_end]);
+        let mut parts = request_line.split_whitespace();
+        if parts.next() != Some("GET") || parts.next() != Some(path) {
+            return false;
         }
 
-        arc_connection.on_open(|_handle| async move {}).await;
-
-        let middleware_result = self
-            .execute_middleware_chain(Arc::clone(&arc_connection), Arc::clone(&handle))
+        // Consume what was peeked so the response isn't answered by a reset.
+        let _ = stream.read(buf).await;
+        let _ = stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK",
+            )
             .await;
+        let _ = stream.shutdown().await;
+        true
+    }
 
-        match middleware_result {
-            Err(err) => {
-                let state = handle.state().await;
-                if state == ConnState::OPEN || state == ConnState::CONNECTING {
-                    let _ = handle
-                        .send_text("Connection rejected by server".to_string())
-                        .await;
-                    let _ = handle.close().await;
+    /// Registers a hook that can refuse WebSocket upgrades.
+    ///
+    /// The hook runs for every new connection before the handshake is
+    /// answered and receives the upgrade request's headers, keyed by
+    /// lowercase name as in [`Connection::headers`]. Returning `Ok(())` lets
+    /// the handshake continue; returning an [`UpgradeRejection`] answers the
+    /// request with that HTTP status and body and closes the socket, so
+    /// authentication can happen before a connection exists. Refused
+    /// requests never create a [`Connection`] or use up a connection ID.
+    ///
+    /// The request is peeked from the socket. Requests whose headers exceed
+    /// [`Wynd::set_max_header_size`] are refused before the hook runs.
+    ///
+    /// ## Parameters
+    ///
+    /// - `hook`: Async function deciding whether to accept the upgrade
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::types::UpgradeRejection;
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    /// wynd.on_upgrade(|headers| async move {
+    ///     match headers.get("authorization") {
+    ///         Some(token) if token == "Bearer secret" => Ok(()),
+    ///         _ => Err(UpgradeRejection::new(401, "unauthorized")),
+    ///     }
+    /// });
+    /// ```
+    pub fn on_upgrade<F, Fut>(&mut self, hook: F)
+    where
+        F: Fn(HashMap<String, String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), UpgradeRejection>> + Send + 'static,
+    {
+        self.upgrade_hook = Some(Box::new(move |headers| Box::pin(hook(headers))));
+    }
+
+    /// Checks the upgrade request peeked from `stream` against the header
+    /// size limit and the upgrade hook.
+    ///
+    /// Returns `true` if the upgrade was refused and the rejection has been
+    /// sent, or if the client did not send a complete request within the
+    /// handshake timeout; either way the connection should be dropped.
+    /// `buf` is scratch space for the peeked bytes and must hold one byte
+    /// more than the header size limit.
+    async fn refuse_upgrade(&self, stream: &mut TcpStream, buf: &mut [u8]) -> bool {
+        let max_header_size = self.max_header_size;
+        let peek = async {
+            loop {
+                let peeked = match stream.peek(buf).await {
+                    Ok(0) | Err(_) => return Err(()),
+                    Ok(n) => n,
+                };
+                match HandshakeRequest::try_parse(&buf[..peeked]) {
+                    Ok(Some((size, _))) if size > max_header_size => return Ok(None),
+                    Ok(Some((_, request))) => return Ok(Some(request)),
+                    Ok(None) if peeked == buf.len() => return Ok(None),
+                    Ok(None) => {
+                        // A peek leaves the socket marked readable, so clear
+                        // that before waiting for the rest of the request.
+                        let _ = stream.try_io(Interest::READABLE, || {
+                            Err::<(), _>(std::io::ErrorKind::WouldBlock.into())
+                        });
+                        let _ = timeout(UPGRADE_PEEK_INTERVAL, stream.readable()).await;
+                    }
+                    // Let the handshake report malformed requests.
+                    Err(_) => return Err(()),
                 }
-                eprintln!("Middleware error: {}", err);
-                return Err(err.into());
             }
-            Ok((final_conn, final_handle)) => {
-                let state = final_handle.state().await;
-                if state == ConnState::CLOSED || state == ConnState::CLOSING {
-                    return Ok(());
-                }
+        };
+        let request = match timeout(HANDSHAKE_TIMEOUT, peek).await {
+            Ok(Ok(request)) => request,
+            Ok(Err(())) => return false,
+            Err(_) => {
+                self.counters.record_handshake_failure();
+                return true;
+            }
+        };
 
-                if let Some(ref handler) = self.connection_handler {
-                    handler(final_conn).await;
-                }
+        let rejection = match request {
+            Some(request) => {
+                let Some(ref hook) = self.upgrade_hook else {
+                    return false;
+                };
+                let Err(rejection) = hook(request_headers(request.headers())).await else {
+                    return false;
+                };
+                rejection
             }
-        }
+            None => {
+                self.counters.record_handshake_failure();
+                UpgradeRejection::new(431, "request header fields too large")
+            }
+        };
 
-        Ok(())
+        // Consume what was sent so the response isn't answered by a reset.
+        let _ = stream.read(buf).await;
+        let _ = stream.write_all(rejection.to_http().as_bytes()).await;
+        let _ = stream.shutdown().await;
+        let _ = timeout(Duration::from_secs(1), async {
+            while let Ok(1..) = stream.read(buf).await {}
+        })
+        .await;
+        true
     }
-}
 
-impl Wynd<TcpStream> {
     /// Starts the WebSocket server and begins listening for connections.
     ///
-    /// This method starts the server on the specified port and begins accepting
-    /// WebSocket connections. The server will run indefinitely until an error
-    /// occurs or the process is terminated.
+    /// This method starts the server on the specified port of every interface
+    /// (`0.0.0.0`) and begins accepting
+    /// WebSocket connections. The server runs until a shutdown is requested
+    /// through a [`ShutdownHandle`] from [`Wynd::shutdown_handle`]. It then
+    /// stops accepting connections, closes every client with code `1001` and
+    /// returns once they have disconnected or the shutdown timeout set with
+    /// [`Wynd::set_shutdown_timeout`] has passed.
+    ///
+    /// ## Parameters
+    ///
+    /// - `port`: The port number to listen on
+    /// - `on_listening`: A closure that will be called when the server starts listening
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` if the server runs successfully, or an error if the
+    /// server fails to start or encounters a fatal error. Fails with a
+    /// [`WyndError`] before binding if no handler was registered with
+    /// [`Wynd::on_connection`], since connections would be accepted without
+    /// any application code running for them.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///     
+    ///     // Set up handlers...
+    ///     
+    ///     wynd.listen(8080, || {
+    ///         println!("Server listening on port 8080");
+    ///     });
+    /// }
+    /// ```
     pub async fn listen<F>(
-        mut self,
+        self,
         port: u16,
         on_listening: F,
     ) -> Result<(), Box<dyn std::error::Error>>
     where
         F: FnOnce() + Send + 'static,
     {
-        let addr = format!("0.0.0.0:{}", port);
-        let listener = TcpListener::bind(&addr).await?;
-        self.addr = listener.local_addr().unwrap();
-
-        let (room_sender, room_receiver) =
-            tokio::sync::mpsc::channel::<RoomEvents<TcpStream>>(self.room_event_channel_capacity);
-        self.room_sender = Arc::new(room_sender);
-
-        let rooms = Arc::clone(&self.rooms);
-        let clients = Arc::clone(&self.clients);
-
-        Self::handle_communication(room_receiver, rooms, clients);
-
-        on_listening();
-
-        let wynd = Arc::new(tokio::sync::Mutex::new(self));
-
-        loop {
-            match listener.accept().await {
-                Ok((stream, addr)) => {
-                    let wynd_clone = Arc::clone(&wynd);
-                    tokio::spawn(async move {
-                        if let Err(e) = wynd_clone
-                            .lock()
-                            .await
-                            .handle_connection(stream, addr)
-                            .await
-                        {
-                            eprintln!("Error handling connection: {}", e);
-                        }
-                    });
-                }
-                Err(e) => {
-                    let wynd_guard = wynd.lock().await;
-                    let handler = wynd_guard.error_handler.as_ref();
-
-                    if let Some(handler) = handler {
-                        handler(WyndError::new(e.to_string())).await;
-                    } else {
-                        eprintln!("Error accepting connection: {}", e);
-                    }
-
-                    eprintln!("accept() failed: {e}. Retrying...");
-                    tokio::time::sleep(Duration::from_secs(1)).await;
-
-                    continue;
-                }
-            }
-        }
+        self.listen_on(("0.0.0.0", port), |_| on_listening()).await
     }
 
-    fn handle_communication(
-        mut room_receiver: Receiver<RoomEvents<TcpStream>>,
-        rooms: Arc<tokio::sync::Mutex<Vec<Room<TcpStream>>>>,
-        clients: ClientRegistry<TcpStream>,
-    ) {
-        tokio::spawn(async move {
-            while let Some(room_data) = room_receiver.recv().await {
-                match room_data {
-                    RoomEvents::JoinRoom {
-                        client_id,
-                        handle,
-                        room_name,
-                    } => {
-                        let mut rooms = rooms.lock().await;
-                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
-                        if let Some(room) = maybe_room {
-                            if room.room_clients.contains_key(&client_id) {
-                                continue;
-                            } else {
-                                room.room_clients.insert(client_id, handle);
-                            }
-                        } else {
-                            let room = Room {
-                                room_clients: HashMap::from([(client_id, handle)]),
-                                room_name,
-                            };
-
-                            rooms.push(room);
-                        }
-                    }
-                    RoomEvents::TextMessage {
-                        room_name,
-                        text,
-                        client_id,
-                    } => {
-                        let mut rooms = rooms.lock().await;
-
-                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
-
-                        if maybe_room.is_none() {
-                            return;
-                        }
-
-                        if !maybe_room.unwrap().room_clients.contains_key(&client_id) {
-                            return;
-                        }
-
-                        let handles: Vec<_> = {
-                            if let Some(room) = rooms.iter().find(|r| r.room_name == room_name) {
-                                room.room_clients.values().cloned().collect()
-                            } else {
-                                Vec::new()
-                            }
-                        };
-
-                        if handles.is_empty() {
-                            eprintln!("Room not found: {}", room_name);
-                        } else {
-                            for h in handles {
-                                if h.id == client_id {
-                                    continue;
-                                } else {
-                                    if let Err(e) = h.send_text(text.clone()).await {
-                                        eprintln!("Failed to send text to client: {}", e);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    RoomEvents::BinaryMessage {
-                        room_name,
-                        bytes,
-                        client_id,
-                    } => {
-                        let mut rooms = rooms.lock().await;
-
-                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
-
-                        if maybe_room.is_none() {
-                            return;
-                        }
-
-                        if !maybe_room.unwrap().room_clients.contains_key(&client_id) {
-                            return;
-                        }
-
-                        let recipients = {
-                            rooms
-                                .iter()
-                                .find(|r| r.room_name == room_name)
-                                .map(|r| r.room_clients.values().cloned().collect::<Vec<_>>())
-                        };
-                        if let Some(recipients) = recipients {
-                            for h in recipients {
-                                if h.id == client_id {
-                                    continue;
-                                } else {
-                                    if let Err(e) = h.send_binary(bytes.clone()).await {
-                                        eprintln!("Failed to send binary to client: {}", e);
-                                    }
-                                }
-                            }
-                        } else {
-                            println!("Room not found: {}", room_name);
-                        }
-                    }
-                    RoomEvents::EmitTextMessage {
-                        client_id,
-                        room_name,
-                        text,
-                    } => {
-                        let mut rooms = rooms.lock().await;
+    /// Starts the WebSocket server on a specific address.
+    ///
+    /// Works like [`Wynd::listen`], but binds `addr` instead of every
+    /// interface, e.g. `127.0.0.1:8080` to stay reachable only locally or
+    /// `[::]:8080` for IPv6. Port `0` picks a free port, which `on_listening`
+    /// receives as part of the bound address.
+    ///
+    /// ## Parameters
+    ///
+    /// - `addr`: The address to bind; the first one that binds is used if it
+    ///   resolves to several
+    /// - `on_listening`: A closure called with the bound address once the
+    ///   server is listening
+    ///
+    /// ## Returns
+    ///
+    /// Returns `Ok(())` after a graceful shutdown, or an error if `addr`
+    /// could not be bound or no handler was registered with
+    /// [`Wynd::on_connection`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut wynd: Wynd<Standalone> = Wynd::new();
+    ///
+    ///     // Set up handlers...
+    ///
+    ///     wynd.listen_on("127.0.0.1:8080", |addr| {
+    ///         println!("Server listening on {}", addr);
+    ///     });
+    /// }
+    /// ```
+    pub async fn listen_on<A, F>(
+        mut self,
+        addr: A,
+        on_listening: F,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        A: tokio::net::ToSocketAddrs,
+        F: FnOnce(SocketAddr) + Send + 'static,
+    {
+        self.require_connection_handler()?;
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| WyndError::with_source(format!("Failed to bind listener: {}", e), e))?;
+        self.addr = listener.local_addr()?;
 
-                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
+        self.counters.record_start();
+        self.start_room_processor();
+        self.start_metrics_reporter();
 
-                        if maybe_room.is_none() {
-                            return;
-                        }
+        on_listening(self.addr);
 
-                        if !maybe_room.unwrap().room_clients.contains_key(&client_id) {
-                            return;
-                        }
+        // Connections only need shared access, so a slow handshake or
+        // connection handler never holds up the accept loop or other clients.
+        let wynd = Arc::new(self);
+        let mut shutdown = wynd.shutdown.subscribe();
+        tokio::select! {
+            _ = Self::accept_loop(Arc::clone(&wynd), listener) => {}
+            _ = shutdown.wait_for(|requested| *requested) => {}
+        }
+        wynd.shut_down_gracefully().await;
+        Ok(())
+    }
 
-                        let handles: Vec<_> = {
-                            if let Some(room) = rooms.iter().find(|r| r.room_name == room_name) {
-                                room.room_clients.values().cloned().collect()
-                            } else {
-                                Vec::new()
-                            }
-                        };
-                        if handles.is_empty() {
-                            eprintln!("Room not found: {}", room_name);
-                        } else {
-                            for h in handles {
-                                if let Err(e) = h.send_text(text.clone()).await {
-                                    eprintln!("Failed to send text to client: {}", e);
-                                }
-                            }
-                        }
-                    }
-                    RoomEvents::EmitBinaryMessage {
-                        client_id,
-                        room_name,
-                        bytes,
-                    } => {
-                        let mut rooms = rooms.lock().await;
+    /// Starts the server on an existing runtime without blocking the caller.
+    ///
+    /// [`Wynd::listen`] runs the accept loop on whatever runtime awaits it.
+    /// `spawn_on` instead binds the port immediately and spawns the accept
+    /// loop, the room processor and every connection task onto `runtime`,
+    /// which suits apps that embed the server next to other services or
+    /// use their own runtime topology. It can be called from outside any
+    /// runtime.
+    ///
+    /// ## Parameters
+    ///
+    /// - `port`: The port number to listen on; `0` picks a free port
+    /// - `runtime`: The runtime the server runs on
+    ///
+    /// ## Returns
+    ///
+    /// Returns a [`SpawnedServer`] for finding the bound address and shutting
+    /// the server down, or an error if the port could not be bound or no
+    /// handler was registered with [`Wynd::on_connection`].
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// use wynd::wynd::{Wynd, Standalone};
+    ///
+    /// let runtime = tokio::runtime::Builder::new_multi_thread()
+    ///     .enable_all()
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut wynd: Wynd<Standalone> = Wynd::new();
+    /// wynd.on_connection(|_conn| async move {});
+    /// let server = wynd.spawn_on(0, runtime.handle()).unwrap();
+    /// println!("Listening on {}", server.local_addr());
+    ///
+    /// runtime.block_on(server.shutdown());
+    /// ```
+    pub fn spawn_on(
+        mut self,
+        port: u16,
+        runtime: &tokio::runtime::Handle,
+    ) -> Result<SpawnedServer, WyndError> {
+        self.require_connection_handler()?;
+        let (listener, addr) = bind_listener(port, runtime)?;
+        self.addr = addr;
 
-                        let maybe_room = rooms.iter_mut().find(|room| room.room_name == room_name);
+        let _runtime = runtime.enter();
+        self.counters.record_start();
+        self.start_room_processor();
+        self.start_metrics_reporter();
 
-                        if maybe_room.is_none() {
-                            return;
-                        }
+        let wynd = Arc::new(self);
+        let accept = runtime.spawn(Self::accept_loop(Arc::clone(&wynd), listener));
 
-                        if !maybe_room.unwrap().room_clients.contains_key(&client_id) {
-                            return;
-                        }
+        Ok(SpawnedServer {
+            wynd,
+            addr,
+            runtime: runtime.clone(),
+            accept,
+        })
+    }
 
-                        let recipients = {
-                            rooms
-                                .iter()
-                                .find(|r| r.room_name == room_name)
-                                .map(|r| r.room_clients.values().cloned().collect::<Vec<_>>())
-                        };
-                        if let Some(recipients) = recipients {
-                            for h in recipients {
-                                if let Err(e) = h.send_binary(bytes.clone()).await {
-                                    eprintln!("Failed to send binary to client: {}", e);
-                                }
-                            }
-                        } else {
-                            println!("Room not found: {}", room_name);
-                        }
-                    }
-                    RoomEvents::LeaveRoom {
-                        client_id,
-                        room_name,
-                    } => {
-                        let mut rooms_guard = rooms.lock().await;
-                        let mut remove_room = false;
-                        if let Some(room) = rooms_guard
-                            .iter_mut()
-                            .find(|room| room.room_name == room_name)
-                        {
-                            room.room_clients.remove(&client_id);
-                            remove_room = room.room_clients.is_empty();
-                        }
-                        if remove_room {
-                            rooms_guard.retain(|r| r.room_name != room_name);
-                        }
-                    }
-                    RoomEvents::ListRooms { client_id } => {
-                        let rooms_guard = rooms.lock().await;
-                        let mut list = Vec::new();
-                        for room in rooms_guard.iter() {
-                            if room.room_clients.contains_key(&client_id) {
-                                list.push(room.room_name);
-                            }
-                        }
+    /// Fails if no connection handler is registered.
+    fn require_connection_handler(&self) -> Result<(), WyndError> {
+        if self.connection_handlers.is_empty() {
+            return Err(WyndError::new(
+                "no connection handler registered: call Wynd::on_connection before starting the server"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
 
-                        let clients = Arc::clone(&clients);
-                        let clients_guard = clients.lock().await;
-                        if let Some((_, handle)) =
-                            clients_guard.iter().find(|(_, h)| h.0.id() == client_id)
-                        {
-                            if let Err(e) = handle.1.response_sender.send(list).await {
-                                eprintln!(
-                                    "Failed to send list rooms response to client {}: {}",
-                                    client_id, e
-                                );
+    /// Accepts connections forever, handling each on its own task.
+    async fn accept_loop(wynd: Arc<Self>, listener: TcpListener) {
+        loop {
+            match listener.accept().await {
+                Ok((mut stream, addr)) => {
+                    let wynd_clone = Arc::clone(&wynd);
+                    tokio::spawn(async move {
+                        // Peers outside the access lists are refused by
+                        // `handle_connection` before anything is read.
+                        if wynd_clone.is_ip_allowed(addr.ip()) {
+                            // One byte past the limit tells an oversized header
+                            // block apart from one that exactly fits.
+                            let mut buf = vec![0u8; wynd_clone.max_header_size + 1];
+                            if wynd_clone.answer_health_check(&mut stream, &mut buf).await
+                                || wynd_clone.refuse_upgrade(&mut stream, &mut buf).await
+                            {
+                                return;
                             }
-                        } else {
-                            eprintln!("Client {} not found for list rooms response", client_id);
                         }
-                    }
-                    RoomEvents::ListRoomsResponse {
-                        client_id: _,
-                        rooms: _,
-                    } => {}
-                    RoomEvents::LeaveAllRooms { client_id } => {
-                        let mut rooms_guard = rooms.lock().await;
-                        let mut rooms_to_remove = Vec::new();
-
-                        for (index, room) in rooms_guard.iter_mut().enumerate() {
-                            if room.room_clients.contains_key(&client_id) {
-                                room.room_clients.remove(&client_id);
-
-                                if room.room_clients.is_empty() {
-                                    rooms_to_remove.push(index);
-                                }
-                            }
+                        if let Err(e) = wynd_clone.handle_connection(stream, addr).await {
+                            eprintln!("Error handling connection: {}", e);
                         }
+                    });
+                }
+                Err(e) => {
+                    eprintln!("accept() failed: {e}. Retrying...");
 
-                        for index in rooms_to_remove.iter().rev() {
-                            rooms_guard.remove(*index);
-                        }
+                    let handler = wynd.error_handler.as_ref();
+
+                    if let Some(handler) = handler {
+                        handler(WyndError::with_source(e.to_string(), e)).await;
+                    } else {
+                        eprintln!("Error accepting connection: {}", e);
                     }
-                    RoomEvents::ListUsers { response_to } => {
-                        let clients = Arc::clone(&clients);
-                        let clients = clients.lock().await;
-                        let mut clients_info = Vec::new();
 
-                        for client in clients.iter() {
-                            clients_info.push(ClientInfo {
-                                id: client.0.clone(),
-                                handle: Arc::clone(&client.1 .1),
-                            })
-                        }
+                    tokio::time::sleep(Duration::from_secs(1)).await;
 
-                        if let Err(_) = response_to.send(clients_info) {
-                            eprintln!("Failed to send ListUsers response: receiver dropped");
-                        }
-                    }
+                    continue;
                 }
             }
-        });
+        }
     }
 }
 
@@ -1108,24 +4163,17 @@ impl Wynd<WithRipress> {
     ///   is sent to the client
     pub fn handler(
         self,
-    ) -> impl Fn(
-        hyper::Request<Full<hyper_tungstenite::hyper::body::Bytes>>,
-    ) -> Pin<
-        Box<
-            dyn Future<
-                    Output = hyper::Result<
-                        hyper::Response<Full<hyper_tungstenite::hyper::body::Bytes>>,
-                    >,
-                > + Send,
-        >,
-    > + Send
+    ) -> impl Fn(hyper::Request<Full<hyper_tungstenite::hyper::body::Bytes>>) -> HttpResponseFuture
+           + Send
            + Sync
            + 'static {
+        self.counters.record_start();
         let wynd = Arc::new(self);
         move |req: hyper::Request<Full<hyper_tungstenite::hyper::body::Bytes>>| {
             let wynd = Arc::clone(&wynd);
             let mut req = Arc::new(req);
             Box::pin(async move {
+                let accepted_at = Instant::now();
                 if let Some(ref handler) = wynd.request_handler {
                     use hyper_tungstenite::hyper::Response;
 
@@ -1135,10 +4183,9 @@ impl Wynd<WithRipress> {
                     })
                     .await;
 
-                    if response.is_some() {
-                        let response = response.unwrap();
+                    if let Some(response) = response {
                         return Ok(response);
-                    };
+                    }
                 }
                 let is_websocket_upgrade = req
                     .headers()
@@ -1160,6 +4207,16 @@ impl Wynd<WithRipress> {
                     return Ok(response);
                 }
 
+                if wynd.is_draining() {
+                    let response = hyper::Response::builder()
+                        .status(503)
+                        .body(Full::new(hyper_tungstenite::hyper::body::Bytes::from(
+                            "Server draining",
+                        )))
+                        .unwrap();
+                    return Ok(response);
+                }
+
                 let Some(req_for_upgrade) = Arc::get_mut(&mut req) else {
                     let response = hyper::Response::builder()
                         .status(500)
@@ -1170,90 +4227,176 @@ impl Wynd<WithRipress> {
                     return Ok(response);
                 };
 
-                match hyper_tungstenite::upgrade(req_for_upgrade, None) {
-                    Ok((response, websocket_future)) => {
+                let ws_version = offered_ws_version(req_for_upgrade.headers());
+                if let Some(required) = wynd.required_ws_version {
+                    if ws_version != required {
+                        let response = hyper::Response::builder()
+                            .status(426)
+                            .header("sec-websocket-version", u16::from(required))
+                            .body(Full::new(hyper_tungstenite::hyper::body::Bytes::from(
+                                format!(
+                                    "Unsupported WebSocket version {}; expected {}",
+                                    ws_version, required
+                                ),
+                            )))
+                            .unwrap();
+                        return Ok(response);
+                    }
+                }
+
+                let raw_query = req_for_upgrade.uri().query().unwrap_or("").to_string();
+                let headers = request_headers(req_for_upgrade.headers());
+                let offered_subprotocol = if wynd.echo_subprotocol {
+                    first_offered_subprotocol(req_for_upgrade.headers())
+                } else {
+                    None
+                };
+
+                match hyper_tungstenite::upgrade(req_for_upgrade, wynd.websocket_config()) {
+                    Ok((mut response, websocket_future)) => {
+                        if let Some(protocol) = offered_subprotocol {
+                            response
+                                .headers_mut()
+                                .insert("sec-websocket-protocol", protocol);
+                        }
+                        let extensions = negotiated_extensions(response.headers());
+
                         let wynd_clone = Arc::clone(&wynd);
                         tokio::spawn(async move {
                             let ws_stream = match websocket_future.await {
                                 Ok(stream) => stream,
                                 Err(e) => {
+                                    wynd_clone.counters.record_handshake_failure();
                                     if let Some(ref error_handler) = wynd_clone.error_handler {
-                                        error_handler(crate::types::WyndError::new(format!(
-                                            "WebSocket handshake failed: {:?}",
-                                            e
-                                        )))
+                                        error_handler(crate::types::WyndError::with_source(
+                                            format!("WebSocket handshake failed: {:?}", e),
+                                            e,
+                                        ))
                                         .await;
                                     }
                                     return;
                                 }
                             };
 
-                            let connection_id = wynd_clone
-                                .next_connection_id
-                                .fetch_add(1, Ordering::Relaxed);
+                            wynd_clone.counters.record_accepted();
+
+                            let connection_id = wynd_clone.assign_connection_id();
 
                             let mut connection =
                                 Connection::new(connection_id, ws_stream, wynd_clone.addr);
 
+                            connection.defer_start();
+                            connection.set_query(&raw_query);
+                            connection.set_headers(headers);
+                            connection.set_negotiated_extensions(extensions);
+                            connection.set_ws_version(ws_version);
+                            if let Some(keepalive) = wynd_clone.keepalive {
+                                connection.set_keepalive(keepalive);
+                            }
+                            if let Some(ref workers) = wynd_clone.workers {
+                                connection.set_worker(workers.worker_for(connection_id));
+                            }
+                            #[cfg(feature = "json")]
+                            connection.set_auto_error_reply(wynd_clone.auto_error_reply);
                             connection.set_clients_registry(Arc::clone(&wynd_clone.clients));
 
-                            let broadcaster = Broadcaster {
-                                clients: Arc::clone(&wynd_clone.clients),
-                                current_client_id: connection_id,
-                            };
+                            let mut broadcaster =
+                                Broadcaster::new(Arc::clone(&wynd_clone.clients), connection_id);
+                            broadcaster.coalescer = wynd_clone.broadcast_coalescer.clone();
+                            broadcaster.yield_every = wynd_clone.broadcast_yield_interval;
 
-                            let (response_sender, response_receiver) =
-                                tokio::sync::mpsc::channel(wynd_clone.room_event_channel_capacity);
-
-                            let handle = Arc::new(ConnectionHandle {
-                                id: connection.id(),
-                                writer: Arc::clone(&connection.writer),
-                                addr: wynd_clone.addr,
-                                broadcast: broadcaster,
-                                state: Arc::clone(&connection.state),
-                                room_sender: wynd_clone.room_sender.clone(),
-                                response_sender: Arc::new(response_sender),
-                                response_receiver: Arc::new(tokio::sync::Mutex::new(
-                                    response_receiver,
-                                )),
-                            });
+                            let mut handle = ConnectionHandle::new(
+                                connection.id(),
+                                Arc::clone(&connection.writer),
+                                wynd_clone.addr,
+                                broadcaster,
+                                Arc::clone(&connection.state),
+                                wynd_clone.room_sender.clone(),
+                            );
+                            handle.groups = Arc::clone(&wynd_clone.groups);
+                            handle.users = Arc::clone(&wynd_clone.users);
+                            handle.tags = Arc::clone(&wynd_clone.tags);
+                            handle.outbound_transform = connection.outbound_transform.clone();
+                            handle.accepted_kinds = Arc::clone(&connection.accepted_kinds);
+                            handle.headers = Arc::clone(&connection.headers);
+                            handle.inbound_muted = Arc::clone(&connection.inbound_muted);
+                            handle.max_total_bytes = wynd_clone.max_total_bytes;
+                            handle.max_close_reason_len = wynd_clone.max_close_reason_len;
+                            handle.auto_pong = wynd_clone.auto_pong;
+                            handle.require_room_membership = wynd_clone.require_room_membership;
+                            handle.handler_permits = wynd_clone.handler_permits.clone();
+                            handle.set_response_capacity(wynd_clone.room_response_capacity);
+                            handle.accepted_at = accepted_at;
+                            handle.setup_times = Arc::clone(&wynd_clone.setup_times);
+                            handle.counters = Arc::clone(&wynd_clone.counters);
+                            if let Some(capacity) = wynd_clone.send_queue_capacity {
+                                handle.set_send_queue_capacity(capacity);
+                            }
+                            let handle = Arc::new(handle);
 
                             let arc_connection = Arc::new(connection);
 
                             arc_connection.set_handle(Arc::clone(&handle)).await;
 
-                            {
-                                let mut clients = wynd_clone.clients.lock().await;
-                                clients.insert(
-                                    arc_connection.id(),
-                                    (Arc::clone(&arc_connection), Arc::clone(&handle)),
-                                );
-                            }
-
                             {
                                 let clients_registry = Arc::clone(&wynd_clone.clients);
                                 let rooms_registry = Arc::clone(&wynd_clone.rooms);
+                                let groups_registry = Arc::clone(&wynd_clone.groups);
+                                let users_registry = Arc::clone(&wynd_clone.users);
+                                let tags_registry = Arc::clone(&wynd_clone.tags);
                                 let handle_id = handle.id();
-                                arc_connection.on_close(move |_event| {
-                                    let clients_registry = Arc::clone(&clients_registry);
-                                    let rooms_registry = Arc::clone(&rooms_registry);
-                                    let handle_id = handle_id;
-                                    async move {
-                                        {
-                                            let mut clients = clients_registry.lock().await;
-                                            clients.retain(|_c, h| h.0.id() != handle_id);
-                                        }
-                                        {
-                                            let mut rooms = rooms_registry.lock().await;
-                                            for room in rooms.iter_mut() {
-                                                room.room_clients.remove(&handle_id);
+                                arc_connection
+                                    .on_cleanup(move |_event| {
+                                        let clients_registry = Arc::clone(&clients_registry);
+                                        let rooms_registry = Arc::clone(&rooms_registry);
+                                        let groups_registry = Arc::clone(&groups_registry);
+                                        let users_registry = Arc::clone(&users_registry);
+                                        let tags_registry = Arc::clone(&tags_registry);
+                                        let handle_id = handle_id;
+                                        async move {
+                                            {
+                                                let mut clients = clients_registry.lock().await;
+                                                clients.retain(|_c, h| h.0.id() != handle_id);
+                                            }
+                                            {
+                                                let mut rooms = rooms_registry.lock().await;
+                                                for room in rooms.iter_mut() {
+                                                    room.room_clients.remove(&handle_id);
+                                                }
+                                                rooms.retain(|room| !room.is_disposable());
+                                            }
+                                            {
+                                                let mut groups = groups_registry.lock().await;
+                                                for members in groups.values_mut() {
+                                                    members.remove(&handle_id);
+                                                }
+                                                groups.retain(|_, members| !members.is_empty());
+                                            }
+                                            {
+                                                let mut users = users_registry.lock().await;
+                                                for connections in users.values_mut() {
+                                                    connections.remove(&handle_id);
+                                                }
+                                                users.retain(|_, connections| {
+                                                    !connections.is_empty()
+                                                });
+                                            }
+                                            {
+                                                let mut tags = tags_registry.lock().await;
+                                                for connections in tags.values_mut() {
+                                                    connections.remove(&handle_id);
+                                                }
+                                                tags.retain(|_, connections| {
+                                                    !connections.is_empty()
+                                                });
                                             }
-                                            rooms.retain(|room| !room.room_clients.is_empty());
                                         }
-                                    }
-                                });
+                                    })
+                                    .await;
                             }
 
+                            wynd_clone.register_client(&arc_connection, &handle).await;
+
                             let middleware_result = wynd_clone
                                 .execute_middleware_chain(
                                     Arc::clone(&arc_connection),
@@ -1265,7 +4408,9 @@ impl Wynd<WithRipress> {
                                 Err(err) => {
                                     let state = handle.state().await;
                                     if state == ConnState::OPEN || state == ConnState::CONNECTING {
-                                        let _ = handle.send_text(err.clone()).await;
+                                        let _ = handle
+                                            .send_text(wynd_clone.rejection_reply(&err, &err))
+                                            .await;
                                         let _ = handle.close().await;
                                     }
                                     if let Some(ref error_handler) = wynd_clone.error_handler {
@@ -1275,7 +4420,6 @@ impl Wynd<WithRipress> {
                                         .await;
                                     }
                                     eprintln!("Middleware error: {}", err);
-                                    return;
                                 }
                                 Ok((final_conn, final_handle)) => {
                                     let state = final_handle.state().await;
@@ -1323,10 +4467,188 @@ impl Wynd<WithRipress> {
             })
             .await;
 
-        if let Some(ref handler) = self.connection_handler {
-            handler(connection).await;
-        }
+        self.run_connection_handlers(&connection).await;
+
+        // Every handler is registered now, so it is safe to start reading.
+        connection.start().await;
 
         Ok(())
     }
 }
+
+/// Returns the first subprotocol listed in the `Sec-WebSocket-Protocol` request headers.
+fn first_offered_subprotocol(
+    headers: &tokio_tungstenite::tungstenite::http::HeaderMap,
+) -> Option<HeaderValue> {
+    headers
+        .get_all("sec-websocket-protocol")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .find(|protocol| !protocol.is_empty())
+        .and_then(|protocol| HeaderValue::from_str(protocol).ok())
+}
+
+/// Returns the `Sec-WebSocket-Version` request header, or `0` if it is
+/// missing or not a number.
+fn offered_ws_version(headers: &tokio_tungstenite::tungstenite::http::HeaderMap) -> u8 {
+    headers
+        .get("sec-websocket-version")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Collects handshake request headers into a map keyed by lowercase name,
+/// joining repeated headers with `", "`.
+pub(crate) fn request_headers(
+    headers: &tokio_tungstenite::tungstenite::http::HeaderMap,
+) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    for (name, value) in headers {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        map.entry(name.as_str().to_string())
+            .and_modify(|joined| {
+                joined.push_str(", ");
+                joined.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    map
+}
+
+/// Returns the extension names listed in the `Sec-WebSocket-Extensions`
+/// response headers, i.e. the extensions that were negotiated.
+pub(crate) fn negotiated_extensions(
+    headers: &tokio_tungstenite::tungstenite::http::HeaderMap,
+) -> Vec<String> {
+    headers
+        .get_all("sec-websocket-extensions")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|extension| extension.split(';').next())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns a room sender whose receiver is already dropped.
+///
+/// Used until the room processor starts, so that room operations report
+/// [`RoomError::ChannelClosed`] instead of queueing events nobody reads.
+fn closed_room_channel<T>() -> Arc<tokio::sync::mpsc::Sender<RoomEvents<T>>>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    Arc::new(tokio::sync::mpsc::channel(1).0)
+}
+
+/// Returns `true` if `room_name` exists or another room may be created.
+fn room_available<T>(rooms: &[Room<T>], room_name: &str, max_rooms: Option<usize>) -> bool
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    max_rooms.is_none_or(|max| rooms.len() < max)
+        || rooms.iter().any(|room| room.room_name == room_name)
+}
+
+/// Adds a client to a room, creating the room if needed and `max_rooms`
+/// allows it.
+///
+/// Returns the room history to replay if the client was not already a member.
+fn join_room<T>(
+    rooms: &mut Vec<Room<T>>,
+    client_id: ConnectionId,
+    handle: &ConnectionHandle<T>,
+    room_name: &str,
+    max_rooms: Option<usize>,
+) -> Result<Vec<BroadcastPayload>, RoomError>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    if !room_available(rooms, room_name, max_rooms) {
+        return Err(RoomError::CapacityExceeded(room_name.to_string()));
+    }
+    Ok(
+        match rooms.iter_mut().find(|room| room.room_name == room_name) {
+            Some(room) => match room.room_clients.entry(client_id) {
+                Entry::Occupied(_) => Vec::new(),
+                Entry::Vacant(entry) => {
+                    entry.insert(handle.clone());
+                    room.history.iter().cloned().collect()
+                }
+            },
+            None => {
+                let mut room = Room::named(room_name);
+                room.room_clients.insert(client_id, handle.clone());
+                rooms.push(room);
+                Vec::new()
+            }
+        },
+    )
+}
+
+/// Removes a client from a room, dropping the room if it becomes disposable.
+fn leave_room<T>(rooms: &mut Vec<Room<T>>, client_id: ConnectionId, room_name: &str)
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    let mut remove_room = false;
+    if let Some(room) = rooms.iter_mut().find(|room| room.room_name == room_name) {
+        room.room_clients.remove(&client_id);
+        remove_room = room.is_disposable();
+    }
+    if remove_room {
+        rooms.retain(|r| r.room_name != room_name);
+    }
+}
+
+/// Closes a connection the server evicted to admit a new one.
+///
+/// The connection is dropped from rooms, groups, users and tags at once.
+/// If the client does not finish the closing handshake within
+/// [`EVICTION_CLOSE_TIMEOUT`], its message loop is stopped.
+async fn evict<T>(connection: Arc<Connection<T>>, handle: Arc<ConnectionHandle<T>>)
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    const REASON: &str = "Evicted to admit a new connection";
+
+    connection
+        .run_cleanup(CloseEvent::new(
+            u16::from(CloseCode::Again),
+            REASON.to_string(),
+        ))
+        .await;
+    let closed = timeout(EVICTION_CLOSE_TIMEOUT, async {
+        let _ = handle.send_close_frame(CloseCode::Again, REASON).await;
+        while handle.state().await != ConnState::CLOSED {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    if closed.is_err() {
+        handle.abort();
+    }
+}
+
+/// Sends a room's recent messages to a late joiner, oldest first.
+async fn replay_history<T>(handle: &ConnectionHandle<T>, replay: Vec<BroadcastPayload>)
+where
+    T: AsyncRead + AsyncWrite + Unpin + Debug + Send + 'static,
+{
+    for payload in replay {
+        let result = match payload {
+            BroadcastPayload::Text(text) => handle.send_text(text).await,
+            BroadcastPayload::Binary(bytes) => handle.send_binary(bytes).await,
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to replay room history to client: {}", e);
+        }
+    }
+}